@@ -0,0 +1,369 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, PoisonError};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use crate::client::API;
+use crate::error::{Error, ErrorKind};
+use crate::requests::message::SendMessage;
+
+const DEFAULT_DRAIN_TIMEOUT: Duration = Duration::from_secs(30);
+
+type FailureCallback = Box<dyn Fn(&SendMessage, &Error) + Send + Sync>;
+
+/// What [BackgroundSender::enqueue](struct.BackgroundSender.html#method.enqueue) does when the
+/// queue is full.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum OverflowPolicy {
+    /// Discard the oldest queued message to make room.
+    DropOldest,
+    /// Discard the message being queued.
+    DropNewest,
+    /// Wait until the worker makes room.
+    Block,
+}
+
+/// Sends messages from a worker thread so callers never wait on Pushover.
+///
+/// Messages are queued in memory, up to `capacity`, and delivered in order. Dropping the sender
+/// delivers whatever is still queued before returning, waiting at most the
+/// [drain timeout](#method.drain_timeout); messages still queued after that are abandoned.
+///
+/// ```rust,no_run
+/// use pushover::{BackgroundSender, OverflowPolicy, API};
+/// use pushover::requests::message::SendMessage;
+///
+/// let sender = BackgroundSender::new(API::new(), 100)
+///     .overflow_policy(OverflowPolicy::DropOldest)
+///     .on_failure(|message, error| eprintln!("Error sending {:?}: {}", message, error));
+///
+/// sender
+///     .enqueue(SendMessage::new("token", "user_key", "hello"))
+///     .expect("Background sender has stopped");
+/// ```
+pub struct BackgroundSender {
+    shared: Arc<Shared>,
+    handle: Option<JoinHandle<()>>,
+    drain_timeout: Duration,
+}
+
+struct Shared {
+    queue: Mutex<Queue>,
+    changed: Condvar,
+    on_failure: Mutex<Option<FailureCallback>>,
+}
+
+struct Queue {
+    messages: VecDeque<SendMessage>,
+    capacity: usize,
+    policy: OverflowPolicy,
+    sending: bool,
+    stopped: bool,
+    /// Whether the worker thread is still running; cleared if it exits or panics.
+    running: bool,
+}
+
+impl Queue {
+    /// Queues `message`, returning the message dropped to honour the policy, if any.
+    fn push(&mut self, message: SendMessage) -> Option<SendMessage> {
+        if !self.is_full() {
+            self.messages.push_back(message);
+            return None;
+        }
+
+        match self.policy {
+            OverflowPolicy::DropOldest => {
+                let dropped = self.messages.pop_front();
+                self.messages.push_back(message);
+                dropped
+            }
+            OverflowPolicy::DropNewest | OverflowPolicy::Block => Some(message),
+        }
+    }
+
+    fn is_full(&self) -> bool {
+        self.messages.len() >= self.capacity
+    }
+
+    fn is_idle(&self) -> bool {
+        self.messages.is_empty() && !self.sending
+    }
+}
+
+impl BackgroundSender {
+    /// Starts a worker sending through `api`, queueing at most `capacity` messages.
+    pub fn new(api: API, capacity: usize) -> Self {
+        let shared = Arc::new(Shared {
+            queue: Mutex::new(Queue {
+                messages: VecDeque::new(),
+                capacity: capacity.max(1),
+                policy: OverflowPolicy::Block,
+                sending: false,
+                stopped: false,
+                running: true,
+            }),
+            changed: Condvar::new(),
+            on_failure: Mutex::new(None),
+        });
+
+        let handle = {
+            let shared = shared.clone();
+            thread::spawn(move || {
+                let _exited = Exited(&shared);
+                run(&api, &shared)
+            })
+        };
+
+        Self {
+            shared,
+            handle: Some(handle),
+            drain_timeout: DEFAULT_DRAIN_TIMEOUT,
+        }
+    }
+
+    /// Sets what happens when the queue is full. Defaults to
+    /// [OverflowPolicy::Block](enum.OverflowPolicy.html#variant.Block).
+    pub fn overflow_policy(self, policy: OverflowPolicy) -> Self {
+        self.shared.queue.lock().unwrap().policy = policy;
+        self
+    }
+
+    /// How long dropping the sender waits for queued messages to be delivered. Defaults to 30
+    /// seconds.
+    pub fn drain_timeout(mut self, timeout: Duration) -> Self {
+        self.drain_timeout = timeout;
+        self
+    }
+
+    /// Called from the worker thread for every message that could not be delivered.
+    pub fn on_failure<F>(self, callback: F) -> Self
+    where
+        F: Fn(&SendMessage, &Error) + Send + Sync + 'static,
+    {
+        *self.shared.on_failure.lock().unwrap() = Some(Box::new(callback));
+        self
+    }
+
+    /// Queues `message` for delivery, returning the message dropped to make room, if any.
+    ///
+    /// Only blocks when the queue is full and the policy is
+    /// [OverflowPolicy::Block](enum.OverflowPolicy.html#variant.Block). Fails with
+    /// [ErrorKind::SenderStopped](enum.ErrorKind.html#variant.SenderStopped) if the worker thread
+    /// has died.
+    pub fn enqueue(&self, message: SendMessage) -> Result<Option<SendMessage>, Error> {
+        let mut queue = self.shared.lock();
+
+        while queue.running && queue.is_full() && queue.policy == OverflowPolicy::Block {
+            queue = self.shared.wait(queue);
+        }
+
+        if !queue.running {
+            return Err(ErrorKind::SenderStopped.into());
+        }
+
+        let dropped = queue.push(message);
+        self.shared.changed.notify_all();
+        Ok(dropped)
+    }
+
+    /// Number of messages waiting to be sent.
+    pub fn len(&self) -> usize {
+        self.shared.lock().messages.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Waits up to `timeout` for every queued message to be sent, returning whether it was.
+    pub fn flush(&self, timeout: Duration) -> bool {
+        let queue = self.shared.lock();
+        let queue = self
+            .shared
+            .wait_while(queue, timeout, |queue| queue.running && !queue.is_idle());
+
+        queue.is_idle()
+    }
+}
+
+impl Drop for BackgroundSender {
+    fn drop(&mut self) {
+        let mut queue = self.shared.lock();
+        queue.stopped = true;
+        self.shared.changed.notify_all();
+
+        let queue = self
+            .shared
+            .wait_while(queue, self.drain_timeout, |queue| queue.running);
+        let exited = !queue.running;
+        drop(queue);
+
+        // A worker stuck on a send is left to finish on its own rather than hang the caller.
+        if let (true, Some(handle)) = (exited, self.handle.take()) {
+            let _ = handle.join();
+        }
+    }
+}
+
+impl Shared {
+    /// Locks the queue, which stays consistent even if a failure callback panicked.
+    fn lock(&self) -> MutexGuard<'_, Queue> {
+        self.queue.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn wait<'a>(&self, queue: MutexGuard<'a, Queue>) -> MutexGuard<'a, Queue> {
+        self.changed
+            .wait(queue)
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Waits up to `timeout` for `condition` to become false.
+    fn wait_while<'a, F>(
+        &self,
+        mut queue: MutexGuard<'a, Queue>,
+        timeout: Duration,
+        condition: F,
+    ) -> MutexGuard<'a, Queue>
+    where
+        F: Fn(&Queue) -> bool,
+    {
+        let deadline = Instant::now() + timeout;
+
+        while condition(&queue) {
+            let now = Instant::now();
+
+            if now >= deadline {
+                break;
+            }
+
+            queue = self
+                .changed
+                .wait_timeout(queue, deadline - now)
+                .unwrap_or_else(PoisonError::into_inner)
+                .0;
+        }
+
+        queue
+    }
+}
+
+/// Marks the worker as gone when its thread ends, including by panicking.
+struct Exited<'a>(&'a Shared);
+
+impl Drop for Exited<'_> {
+    fn drop(&mut self) {
+        let mut queue = self.0.lock();
+        queue.running = false;
+        queue.sending = false;
+        self.0.changed.notify_all();
+    }
+}
+
+fn run(api: &API, shared: &Shared) {
+    loop {
+        let message = {
+            let mut queue = shared.lock();
+
+            loop {
+                if let Some(message) = queue.messages.pop_front() {
+                    queue.sending = true;
+                    break message;
+                }
+
+                if queue.stopped {
+                    return;
+                }
+
+                queue = shared.wait(queue);
+            }
+        };
+        shared.changed.notify_all();
+
+        if let Err(e) = api.send(&message) {
+            if let Some(ref callback) = *shared.on_failure.lock().unwrap() {
+                callback(&message, &e);
+            }
+        }
+
+        shared.lock().sending = false;
+        shared.changed.notify_all();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn queue(policy: OverflowPolicy) -> Queue {
+        Queue {
+            messages: VecDeque::new(),
+            capacity: 2,
+            policy,
+            sending: false,
+            stopped: false,
+            running: true,
+        }
+    }
+
+    fn message(text: &str) -> SendMessage {
+        SendMessage::new("token", "user", text)
+    }
+
+    fn texts(queue: &Queue) -> Vec<&str> {
+        queue.messages.iter().map(|m| m.message.as_str()).collect()
+    }
+
+    #[test]
+    fn drop_oldest_makes_room() {
+        let mut queue = queue(OverflowPolicy::DropOldest);
+
+        assert_eq!(queue.push(message("1")), None);
+        assert_eq!(queue.push(message("2")), None);
+        assert_eq!(queue.push(message("3")), Some(message("1")));
+        assert_eq!(texts(&queue), ["2", "3"]);
+    }
+
+    #[test]
+    fn drop_newest_keeps_queue() {
+        let mut queue = queue(OverflowPolicy::DropNewest);
+
+        queue.push(message("1"));
+        queue.push(message("2"));
+
+        assert_eq!(queue.push(message("3")), Some(message("3")));
+        assert_eq!(texts(&queue), ["1", "2"]);
+    }
+
+    fn unreachable() -> API {
+        API::new()
+            .base_url("http://127.0.0.1:1")
+            .timeout(Duration::from_secs(1))
+    }
+
+    #[test]
+    fn enqueue_fails_once_worker_is_gone() {
+        let sender = BackgroundSender::new(unreachable(), 1)
+            .on_failure(|_, _| panic!("failure callback panicked"));
+
+        sender.enqueue(message("1")).unwrap();
+        sender.flush(Duration::from_secs(5));
+
+        match sender.enqueue(message("2")) {
+            Err(Error(ErrorKind::SenderStopped, _)) => {}
+            _ => panic!("Did not receive SenderStopped"),
+        }
+    }
+
+    #[test]
+    fn drop_gives_up_on_stuck_worker() {
+        let sender = BackgroundSender::new(unreachable(), 1)
+            .drain_timeout(Duration::from_millis(100))
+            .on_failure(|_, _| thread::sleep(Duration::from_secs(10)));
+        sender.enqueue(message("1")).unwrap();
+
+        let started = Instant::now();
+        drop(sender);
+
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+}
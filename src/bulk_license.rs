@@ -0,0 +1,303 @@
+use crate::client::API;
+use crate::error::{Error, ErrorKind};
+use crate::requests::license::{Assign, CheckCredits};
+use crate::requests::verification::{Verification, VerificationResponse};
+use crate::types::{OperatingSystem, UserType};
+
+/// Assigns licenses to many users, one at a time, until the credits run out.
+///
+/// The remaining credits are checked before anything is assigned. With
+/// [skip_licensed](#method.skip_licensed), user keys are verified first and users that already
+/// hold a license for the platform are skipped; email addresses can't be verified and are always
+/// assigned a license.
+///
+/// ```rust,no_run
+/// use pushover::{BulkLicense, OperatingSystem, UserType, API};
+///
+/// let users = vec![
+///     UserType::UserKey(String::from("uQiRzpo4DXghDmr9QzzfQu27cmVRsG")),
+///     UserType::Email(String::from("alice@example.com")),
+/// ];
+///
+/// let licenses = BulkLicense::new("token")
+///     .os(OperatingSystem::Android)
+///     .skip_licensed(true);
+/// let report = licenses.assign(&API::new(), users).expect("Error checking credits");
+///
+/// for result in &report.results {
+///     println!("{:?}: {:?}", result.user, result.outcome);
+/// }
+/// println!("{} credits left", report.credits);
+/// ```
+#[derive(Clone, Debug)]
+pub struct BulkLicense {
+    token: String,
+    os: Option<OperatingSystem>,
+    skip_licensed: bool,
+}
+
+/// What happened to one user.
+#[derive(Debug)]
+pub enum LicenseOutcome {
+    /// A license was assigned, leaving `credits` credits.
+    Assigned {
+        credits: u16,
+    },
+    /// The user already holds a license for the platform.
+    AlreadyLicensed,
+    Failed(Error),
+    /// No license was assigned because the credits ran out.
+    NotAttempted,
+}
+
+/// Outcome for one user.
+#[derive(Debug)]
+pub struct LicenseResult {
+    pub user: UserType,
+    pub outcome: LicenseOutcome,
+}
+
+/// Outcome of a bulk assignment, in input order.
+#[derive(Debug)]
+pub struct LicenseReport {
+    /// Credits available before the first assignment.
+    pub starting_credits: u16,
+    /// Credits left after the last assignment.
+    pub credits: u16,
+    /// Whether the credits ran out before every user was licensed.
+    pub exhausted: bool,
+    pub results: Vec<LicenseResult>,
+}
+
+impl BulkLicense {
+    pub fn new<T>(token: T) -> Self
+    where
+        T: Into<String>,
+    {
+        Self {
+            token: token.into(),
+            os: None,
+            skip_licensed: false,
+        }
+    }
+
+    /// Assign licenses for `os` rather than for each user's first platform.
+    pub fn os(mut self, os: OperatingSystem) -> Self {
+        self.os = Some(os);
+        self
+    }
+
+    /// Verify user keys first and skip users that already hold a license.
+    pub fn skip_licensed(mut self, skip_licensed: bool) -> Self {
+        self.skip_licensed = skip_licensed;
+        self
+    }
+
+    /// Assigns a license to every user. Fails only if the credits can't be checked.
+    pub fn assign(&self, api: &API, users: Vec<UserType>) -> Result<LicenseReport, Error> {
+        let credits = api.send(&CheckCredits::new(self.token.as_str()))?.credits;
+        let mut report = LicenseReport::new(credits);
+
+        for user in users {
+            if report.credits == 0 {
+                report.push(user, LicenseOutcome::NotAttempted);
+                continue;
+            }
+
+            if let Some(verification) = self.verification(&user) {
+                match api.send(&verification) {
+                    Ok(ref response) if self.is_licensed(response) => {
+                        report.push(user, LicenseOutcome::AlreadyLicensed);
+                        continue;
+                    }
+                    Ok(_) => {}
+                    Err(e) => {
+                        report.push(user, LicenseOutcome::Failed(e));
+                        continue;
+                    }
+                }
+            }
+
+            let result = api.send(&self.license(&user));
+            report.record(user, result.map(|response| response.credits));
+        }
+
+        Ok(report)
+    }
+
+    pub async fn assign_async(
+        &self,
+        api: &API,
+        users: Vec<UserType>,
+    ) -> Result<LicenseReport, Error> {
+        let credits = api
+            .send_async(&CheckCredits::new(self.token.as_str()))
+            .await?
+            .credits;
+        let mut report = LicenseReport::new(credits);
+
+        for user in users {
+            if report.credits == 0 {
+                report.push(user, LicenseOutcome::NotAttempted);
+                continue;
+            }
+
+            if let Some(verification) = self.verification(&user) {
+                match api.send_async(&verification).await {
+                    Ok(ref response) if self.is_licensed(response) => {
+                        report.push(user, LicenseOutcome::AlreadyLicensed);
+                        continue;
+                    }
+                    Ok(_) => {}
+                    Err(e) => {
+                        report.push(user, LicenseOutcome::Failed(e));
+                        continue;
+                    }
+                }
+            }
+
+            let result = api.send_async(&self.license(&user)).await;
+            report.record(user, result.map(|response| response.credits));
+        }
+
+        Ok(report)
+    }
+
+    /// The verification to run before licensing `user`, if any.
+    fn verification(&self, user: &UserType) -> Option<Verification> {
+        match user {
+            UserType::UserKey(key) if self.skip_licensed => {
+                Some(Verification::new(self.token.as_str(), key.as_str()))
+            }
+            _ => None,
+        }
+    }
+
+    /// Whether the user holds a license for the platform, or any license if none was given.
+    fn is_licensed(&self, response: &VerificationResponse) -> bool {
+        match self.os {
+            Some(ref os) => response.licenses.contains(&os.to_string()),
+            None => !response.licenses.is_empty(),
+        }
+    }
+
+    fn license(&self, user: &UserType) -> Assign {
+        let mut assign = Assign::new(self.token.as_str(), user.clone());
+        if let Some(ref os) = self.os {
+            assign.set_os(os.clone());
+        }
+        assign
+    }
+}
+
+impl LicenseReport {
+    fn new(credits: u16) -> Self {
+        Self {
+            starting_credits: credits,
+            credits,
+            exhausted: false,
+            results: Vec::new(),
+        }
+    }
+
+    /// Number of licenses assigned.
+    pub fn assigned(&self) -> usize {
+        self.results
+            .iter()
+            .filter(|result| matches!(result.outcome, LicenseOutcome::Assigned { .. }))
+            .count()
+    }
+
+    fn push(&mut self, user: UserType, outcome: LicenseOutcome) {
+        if let LicenseOutcome::NotAttempted = outcome {
+            self.exhausted = true;
+        }
+        self.results.push(LicenseResult { user, outcome });
+    }
+
+    fn record(&mut self, user: UserType, result: Result<u16, Error>) {
+        match result {
+            Ok(credits) => {
+                self.credits = credits;
+                self.push(user, LicenseOutcome::Assigned { credits });
+            }
+            Err(e) => {
+                // Pushover may hold on to a credit that was counted as available.
+                if is_out_of_credits(&e) {
+                    self.credits = 0;
+                    self.exhausted = true;
+                }
+                self.push(user, LicenseOutcome::Failed(e));
+            }
+        }
+    }
+}
+
+fn is_out_of_credits(error: &Error) -> bool {
+    match error.kind() {
+        ErrorKind::PushoverError { errors, .. } => {
+            errors.iter().any(|error| error.contains("credit"))
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn user(key: &str) -> UserType {
+        UserType::UserKey(key.to_owned())
+    }
+
+    fn response(licenses: &[&str]) -> VerificationResponse {
+        VerificationResponse {
+            devices: Vec::new(),
+            group: false,
+            licenses: licenses.iter().map(|license| license.to_string()).collect(),
+            request: String::from("request"),
+        }
+    }
+
+    #[test]
+    fn checks_license_for_platform() {
+        let android = BulkLicense::new("token").os(OperatingSystem::Android);
+        let any = BulkLicense::new("token");
+
+        assert!(android.is_licensed(&response(&["iOS", "Android"])));
+        assert!(!android.is_licensed(&response(&["Desktop"])));
+        assert!(any.is_licensed(&response(&["Desktop"])));
+        assert!(!any.is_licensed(&response(&[])));
+    }
+
+    #[test]
+    fn verifies_only_user_keys_when_skipping() {
+        let licenses = BulkLicense::new("token").skip_licensed(true);
+
+        assert!(licenses.verification(&user("key")).is_some());
+        assert!(licenses
+            .verification(&UserType::Email(String::from("alice@example.com")))
+            .is_none());
+        assert!(BulkLicense::new("token")
+            .verification(&user("key"))
+            .is_none());
+    }
+
+    #[test]
+    fn stops_when_out_of_credits() {
+        let mut report = LicenseReport::new(2);
+        let out_of_credits = ErrorKind::PushoverError {
+            status: 0,
+            errors: vec![String::from("no license credits available")],
+            request: String::from("request"),
+        };
+
+        report.record(user("a"), Ok(1));
+        report.record(user("b"), Err(out_of_credits.into()));
+
+        assert!(report.exhausted);
+        assert_eq!(report.credits, 0);
+        assert_eq!(report.assigned(), 1);
+        assert_eq!(report.starting_credits, 2);
+    }
+}
@@ -0,0 +1,278 @@
+use std::thread;
+use std::time::Duration;
+
+use futures::stream::StreamExt;
+use serde::Serialize;
+
+use crate::client::{send_each, send_each_blocking, API};
+use crate::error::{Error, ErrorKind};
+use crate::rate_limit::RateLimiter;
+use crate::requests::verification::{Verification, VerificationResponse};
+
+const DEFAULT_CONCURRENCY: usize = 4;
+
+/// Verifies many user and group keys, a few at a time.
+///
+/// Verification calls don't count as messages, so the [API](struct.API.html)'s own rate limiter
+/// doesn't apply to them; give the verifier one of its own to pace them. The API keeps its
+/// limiter either way. If Pushover answers
+/// with HTTP 429, the keys not checked yet are reported as
+/// [KeyStatus::Unchecked](enum.KeyStatus.html#variant.Unchecked).
+///
+/// ```rust,no_run
+/// use pushover::{BulkVerifier, RateLimiter, API};
+/// use pushover::requests::verification::Verification;
+///
+/// let keys = vec!["uQiRzpo4DXghDmr9QzzfQu27cmVRsG", "gznej3rKEVAvPUxu9vvNnqpmZpokzF"];
+///
+/// let verifier = BulkVerifier::new(API::new())
+///     .concurrency(8)
+///     .rate_limiter(RateLimiter::new(5.0, 10));
+/// let report = verifier.verify(keys.into_iter().map(|key| Verification::new("token", key)));
+///
+/// println!("{}", serde_json::to_string_pretty(&report).unwrap());
+/// ```
+pub struct BulkVerifier {
+    api: API,
+    concurrency: usize,
+    rate_limiter: Option<RateLimiter>,
+}
+
+/// What a key turned out to be.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum KeyStatus {
+    ValidUser,
+    ValidGroup,
+    InvalidKey,
+    /// The key is valid but doesn't have the device that was asked for.
+    InvalidDevice,
+    /// The key couldn't be checked, e.g. because of a network error.
+    Error,
+    /// The key wasn't checked because Pushover's rate limit was reached.
+    Unchecked,
+}
+
+/// Result of verifying one key.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct KeyReport {
+    pub user: String,
+    pub device: Option<String>,
+    pub status: KeyStatus,
+    /// The user's active devices, for valid user keys.
+    pub devices: Vec<String>,
+    pub errors: Vec<String>,
+}
+
+/// Results of a bulk verification, in input order.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct VerificationReport {
+    pub keys: Vec<KeyReport>,
+}
+
+impl BulkVerifier {
+    pub fn new(api: API) -> Self {
+        Self {
+            api,
+            concurrency: DEFAULT_CONCURRENCY,
+            rate_limiter: None,
+        }
+    }
+
+    /// Verify at most `concurrency` keys at a time. Defaults to 4.
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    /// Pace verification calls with `rate_limiter`.
+    pub fn rate_limiter(mut self, rate_limiter: RateLimiter) -> Self {
+        self.rate_limiter = Some(rate_limiter);
+        self
+    }
+
+    /// Verifies every request, blocking until all of them have been checked.
+    pub fn verify<I>(&self, requests: I) -> VerificationReport
+    where
+        I: IntoIterator<Item = Verification>,
+    {
+        let requests: Vec<Verification> = requests.into_iter().collect();
+        let results = send_each_blocking(&requests, self.concurrency, |request| {
+            let wait = self.acquire()?;
+            if wait > Duration::from_secs(0) {
+                thread::sleep(wait);
+            }
+
+            self.api.send(request)
+        });
+
+        report(&requests, results)
+    }
+
+    pub async fn verify_async<I>(&self, requests: I) -> VerificationReport
+    where
+        I: IntoIterator<Item = Verification>,
+    {
+        let requests: Vec<Verification> = requests.into_iter().collect();
+        let results = send_each(&requests, self.concurrency, |request| async move {
+            let wait = self.acquire()?;
+            if wait > Duration::from_secs(0) {
+                tokio::time::delay_for(wait).await;
+            }
+
+            self.api.send_async(request).await
+        })
+        .collect()
+        .await;
+
+        report(&requests, results)
+    }
+
+    /// Takes a token from the verifier's rate limiter, if it has one.
+    fn acquire(&self) -> Result<Duration, Error> {
+        match self.rate_limiter {
+            Some(ref rate_limiter) => rate_limiter.acquire(),
+            None => Ok(Duration::from_secs(0)),
+        }
+    }
+}
+
+impl VerificationReport {
+    /// Number of keys with `status`.
+    pub fn count(&self, status: KeyStatus) -> usize {
+        self.keys.iter().filter(|key| key.status == status).count()
+    }
+}
+
+fn classify(request: &Verification, result: Result<VerificationResponse, Error>) -> KeyReport {
+    let mut report = KeyReport {
+        user: request.user.clone(),
+        device: request.device.clone(),
+        status: KeyStatus::Error,
+        devices: Vec::new(),
+        errors: Vec::new(),
+    };
+
+    match result {
+        Ok(response) => {
+            report.status = if response.group {
+                KeyStatus::ValidGroup
+            } else {
+                KeyStatus::ValidUser
+            };
+            report.devices = response.devices;
+        }
+        Err(Error(ErrorKind::InvalidRecipient { field, errors, .. }, _)) => {
+            report.status = if field == "device" {
+                KeyStatus::InvalidDevice
+            } else {
+                KeyStatus::InvalidKey
+            };
+            report.errors = errors;
+        }
+        Err(Error(ErrorKind::PushoverError { errors, .. }, _)) => report.errors = errors,
+        Err(e) => report.errors = vec![e.to_string()],
+    }
+
+    report
+}
+
+/// Classifies the results, in input order, filling in the keys left unchecked after the rate
+/// limit was reached.
+fn report(
+    requests: &[Verification],
+    results: Vec<Result<VerificationResponse, Error>>,
+) -> VerificationReport {
+    let mut results = results.into_iter();
+    let keys = requests
+        .iter()
+        .map(|request| match results.next() {
+            Some(result) => classify(request, result),
+            None => KeyReport {
+                user: request.user.clone(),
+                device: request.device.clone(),
+                status: KeyStatus::Unchecked,
+                devices: Vec::new(),
+                errors: Vec::new(),
+            },
+        })
+        .collect();
+
+    VerificationReport { keys }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(device: Option<&str>) -> Verification {
+        let mut request = Verification::new("token", "key");
+        if let Some(device) = device {
+            request.set_device(device);
+        }
+        request
+    }
+
+    fn rejected(field: &str, error: &str) -> Result<VerificationResponse, Error> {
+        Err(ErrorKind::InvalidRecipient {
+            field: field.to_owned(),
+            errors: vec![error.to_owned()],
+            request: String::from("request"),
+        }
+        .into())
+    }
+
+    #[test]
+    fn classifies_responses() {
+        let response = |group| VerificationResponse {
+            devices: vec![String::from("phone")],
+            group,
+            licenses: Vec::new(),
+            request: String::from("request"),
+        };
+
+        let user = classify(&request(None), Ok(response(false)));
+        assert_eq!(user.status, KeyStatus::ValidUser);
+        assert_eq!(user.devices, ["phone"]);
+
+        assert_eq!(
+            classify(&request(None), Ok(response(true))).status,
+            KeyStatus::ValidGroup
+        );
+        assert_eq!(
+            classify(&request(None), rejected("user", "user key is invalid")).status,
+            KeyStatus::InvalidKey
+        );
+
+        let device = classify(
+            &request(Some("tablet")),
+            rejected("device", "device name is not valid for user"),
+        );
+        assert_eq!(device.status, KeyStatus::InvalidDevice);
+        assert_eq!(device.device.as_deref(), Some("tablet"));
+        assert_eq!(device.errors, ["device name is not valid for user"]);
+
+        let token: Result<VerificationResponse, Error> = Err(ErrorKind::PushoverError {
+            status: 0,
+            errors: vec![String::from("application token is invalid")],
+            request: String::from("request"),
+        }
+        .into());
+        assert_eq!(classify(&request(None), token).status, KeyStatus::Error);
+    }
+
+    #[test]
+    fn reports_unchecked_keys() {
+        let report = report(
+            &[request(None), request(None), request(None)],
+            vec![rejected("user", "user key is invalid")],
+        );
+
+        assert_eq!(report.count(KeyStatus::InvalidKey), 1);
+        assert_eq!(report.count(KeyStatus::Unchecked), 2);
+        assert_eq!(
+            serde_json::to_value(&report).unwrap()["keys"][1]["status"],
+            "unchecked"
+        );
+    }
+}
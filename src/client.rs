@@ -1,18 +1,38 @@
+use futures::stream::{self, Stream, StreamExt};
+use reqwest::StatusCode;
 use url::form_urlencoded;
 use url::Url;
 
+use std::future::{self, Future};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::thread;
 use std::time::Duration;
 
-use crate::error::Error;
+use crate::error::{Error, ErrorKind};
+use crate::rate_limit::RateLimiter;
+use crate::redact::SanitizedRequest;
+use crate::requests::message::{
+    Limits, LimitsResponse, RecipientResult, SendMessage, SendMessageResponse,
+};
 use crate::requests::{Request, Response};
 
 pub const API_URL: &str = "https://api.pushover.net";
 pub const API_VERSION: &str = "1";
 const DEFAULT_TIMEOUT: u64 = 30;
 
+/// Client for the Pushover API.
+///
+/// Connections are pooled and reused by every request sent through the same `API`, including
+/// clones of it.
+#[derive(Clone)]
 pub struct API {
     base_url: String,
     timeout: Duration,
+    validate: bool,
+    rate_limiter: Option<Arc<RateLimiter>>,
+    client: OnceLock<reqwest::Client>,
+    blocking_client: OnceLock<reqwest::blocking::Client>,
 }
 
 impl Default for API {
@@ -20,6 +40,10 @@ impl Default for API {
         Self {
             timeout: Duration::from_secs(DEFAULT_TIMEOUT),
             base_url: API_URL.to_owned(),
+            validate: false,
+            rate_limiter: None,
+            client: OnceLock::new(),
+            blocking_client: OnceLock::new(),
         }
     }
 }
@@ -31,6 +55,8 @@ impl API {
 
     pub fn timeout(mut self, timeout: Duration) -> Self {
         self.timeout = timeout;
+        self.client = OnceLock::new();
+        self.blocking_client = OnceLock::new();
         self
     }
 
@@ -39,67 +65,472 @@ impl API {
         self
     }
 
-    pub fn send<R: Request>(&self, request: &R) -> Result<<R as Request>::ResponseType, Error> {
-        let mut url = Url::parse(&self.base_url).unwrap();
-        url.set_path(API_VERSION);
+    /// Check requests against Pushover's limits before sending them, failing with
+    /// [ErrorKind::InvalidRequest](enum.ErrorKind.html#variant.InvalidRequest) instead of
+    /// spending a call on a request Pushover would reject.
+    pub fn validate(mut self, validate: bool) -> Self {
+        self.validate = validate;
+        self
+    }
 
-        let client_builder = reqwest::blocking::ClientBuilder::new().timeout(self.timeout);
+    /// Limit how fast messages are sent. See [RateLimiter](struct.RateLimiter.html).
+    pub fn rate_limiter(mut self, rate_limiter: RateLimiter) -> Self {
+        self.rate_limiter = Some(Arc::new(rate_limiter));
+        self
+    }
 
-        request.build_url(&mut url);
+    /// Fetch the app's quota with [Limits](requests/message/struct.Limits.html) and pass it to
+    /// the rate limiter, if there is one.
+    pub fn refresh_limits<T: Into<String>>(&self, token: T) -> Result<LimitsResponse, Error> {
+        let limits = self.send(&Limits::new(token))?;
+
+        if let Some(ref rate_limiter) = self.rate_limiter {
+            rate_limiter.update_from_limits(&limits);
+        }
+
+        Ok(limits)
+    }
+
+    /// Asynchronous version of [refresh_limits](#method.refresh_limits).
+    pub async fn refresh_limits_async<T: Into<String>>(
+        &self,
+        token: T,
+    ) -> Result<LimitsResponse, Error> {
+        let limits = self.send_async(&Limits::new(token)).await?;
+
+        if let Some(ref rate_limiter) = self.rate_limiter {
+            rate_limiter.update_from_limits(&limits);
+        }
+
+        Ok(limits)
+    }
+
+    /// Returns the final URL and body of a request with credentials redacted.
+    pub fn sanitized<R: Request>(&self, request: &R) -> SanitizedRequest {
+        SanitizedRequest::new(
+            request.get_method(),
+            &self.build_url(request),
+            request.get_form_parameters(),
+        )
+    }
 
-        let req = client_builder.build()?.request(request.get_method(), url);
+    pub fn send<R: Request>(&self, request: &R) -> Result<<R as Request>::ResponseType, Error> {
+        request.check_single()?;
+        if self.validate {
+            request.validate()?;
+        }
 
-        let req = if let Some(params) = request.get_form_parameters() {
-            let encoded: String = form_urlencoded::Serializer::new(String::new())
-                .extend_pairs(params)
-                .finish();
+        let wait = self.acquire(request)?;
+        if wait > Duration::from_secs(0) {
+            thread::sleep(wait);
+        }
 
-            req.body(encoded)
+        let req = self
+            .blocking_client()?
+            .request(request.get_method(), self.build_url(request));
+
+        let req = if let Some(body) = encode_body(request) {
+            req.body(body)
         } else {
             req
         };
 
         let res = req.send()?;
+        let status = res.status();
+        self.observe_headers(res.headers());
+        if status.is_server_error() {
+            return Err(ErrorKind::ServerError(status.as_u16()).into());
+        }
 
         res.json()
             .map_err(From::from)
-            .and_then(|value| match value {
-                Response::Success::<R>(raw) => Ok(R::map(raw)),
-                Response::Error::<R>(err) => Err(err.into()),
-            })
+            .and_then(|value| map_response::<R>(status, value))
     }
 
     pub async fn send_async<R: Request>(
         &self,
         request: &R,
     ) -> Result<<R as Request>::ResponseType, Error> {
-        let mut url = Url::parse(&self.base_url).unwrap();
-        url.set_path(API_VERSION);
-
-        let client_builder = reqwest::ClientBuilder::new().timeout(self.timeout);
-
-        request.build_url(&mut url);
+        request.check_single()?;
+        if self.validate {
+            request.validate()?;
+        }
 
-        let req = client_builder.build()?.request(request.get_method(), url);
+        let wait = self.acquire(request)?;
+        if wait > Duration::from_secs(0) {
+            tokio::time::delay_for(wait).await;
+        }
 
-        let req = if let Some(params) = request.get_form_parameters() {
-            let encoded: String = form_urlencoded::Serializer::new(String::new())
-                .extend_pairs(params.into_iter())
-                .finish();
+        let req = self
+            .client()?
+            .request(request.get_method(), self.build_url(request));
 
-            req.body(encoded)
+        let req = if let Some(body) = encode_body(request) {
+            req.body(body)
         } else {
             req
         };
 
         let res = req.send().await?;
+        let status = res.status();
+        self.observe_headers(res.headers());
+        if status.is_server_error() {
+            return Err(ErrorKind::ServerError(status.as_u16()).into());
+        }
 
         res.json()
             .await
             .map_err(From::from)
-            .and_then(|value| match value {
-                Response::Success::<R>(raw) => Ok(R::map(raw)),
-                Response::Error::<R>(err) => Err(err.into()),
-            })
+            .and_then(|value| map_response::<R>(status, value))
+    }
+
+    /// Send many requests, at most `concurrency` at a time, yielding their results in input
+    /// order.
+    ///
+    /// The stream ends after the first
+    /// [ErrorKind::QuotaExceeded](enum.ErrorKind.html#variant.QuotaExceeded), leaving the
+    /// remaining requests unsent.
+    pub fn send_all<'a, R, I>(
+        &'a self,
+        requests: I,
+        concurrency: usize,
+    ) -> impl Stream<Item = Result<<R as Request>::ResponseType, Error>> + 'a
+    where
+        R: Request + 'a,
+        I: IntoIterator<Item = R>,
+        I::IntoIter: 'a,
+    {
+        send_each(requests, concurrency, move |request| async move {
+            self.send_async(&request).await
+        })
+    }
+
+    /// Blocking version of [send_all](#method.send_all), sending from up to `concurrency`
+    /// threads.
+    pub fn send_all_blocking<R, I>(
+        &self,
+        requests: I,
+        concurrency: usize,
+    ) -> Vec<Result<<R as Request>::ResponseType, Error>>
+    where
+        R: Request + Sync,
+        <R as Request>::ResponseType: Send,
+        I: IntoIterator<Item = R>,
+    {
+        let requests: Vec<R> = requests.into_iter().collect();
+
+        send_each_blocking(&requests, concurrency, |request| self.send(request))
+    }
+
+    /// Send every part of a message split by its [Overflow](requests/message/enum.Overflow.html)
+    /// policy, in order, returning the result for each part sent.
+    ///
+    /// Stops at the first failure, which is the last result; the parts after it are not sent.
+    pub fn send_parts(&self, message: &SendMessage) -> Vec<Result<SendMessageResponse, Error>> {
+        let mut results = Vec::new();
+
+        for part in message.parts() {
+            let result = self.send(&part);
+            let failed = result.is_err();
+            results.push(result);
+
+            if failed {
+                break;
+            }
+        }
+
+        results
+    }
+
+    /// Asynchronous version of [send_parts](#method.send_parts).
+    pub async fn send_parts_async(
+        &self,
+        message: &SendMessage,
+    ) -> Vec<Result<SendMessageResponse, Error>> {
+        let mut results = Vec::new();
+
+        for part in message.parts() {
+            let result = self.send_async(&part).await;
+            let failed = result.is_err();
+            results.push(result);
+
+            if failed {
+                break;
+            }
+        }
+
+        results
+    }
+
+    /// Send a message to any number of recipients, in calls of at most 50 recipients each.
+    ///
+    /// Returns which response covered each recipient. When Pushover rejects a recipient of a call,
+    /// the call's recipients are retried one by one so that invalid keys are reported
+    /// individually. Any other rejection, such as an invalid token, is reported for every
+    /// recipient of the call without retrying. Other errors, such as a lost connection, stop the
+    /// whole send.
+    pub fn send_batched(&self, message: &SendMessage) -> Result<Vec<RecipientResult>, Error> {
+        let mut results = Vec::new();
+
+        for batch in message.batches() {
+            match self.send(&batch) {
+                Ok(response) => results.extend(covered(&batch, response)),
+                Err(err) if is_invalid_recipient(&err) && batch.recipients.len() > 1 => {
+                    for recipient in batch.recipients.iter() {
+                        results.push(RecipientResult {
+                            recipient: recipient.to_owned(),
+                            result: self.send(&batch.for_recipient(recipient)),
+                        });
+                    }
+                }
+                Err(err) if is_rejection(&err) => results.extend(rejected(&batch, &err)),
+                Err(err) => return Err(err),
+            }
+        }
+
+        Ok(results)
+    }
+
+    /// Asynchronous version of [send_batched](#method.send_batched).
+    pub async fn send_batched_async(
+        &self,
+        message: &SendMessage,
+    ) -> Result<Vec<RecipientResult>, Error> {
+        let mut results = Vec::new();
+
+        for batch in message.batches() {
+            match self.send_async(&batch).await {
+                Ok(response) => results.extend(covered(&batch, response)),
+                Err(err) if is_invalid_recipient(&err) && batch.recipients.len() > 1 => {
+                    for recipient in batch.recipients.iter() {
+                        results.push(RecipientResult {
+                            recipient: recipient.to_owned(),
+                            result: self.send_async(&batch.for_recipient(recipient)).await,
+                        });
+                    }
+                }
+                Err(err) if is_rejection(&err) => results.extend(rejected(&batch, &err)),
+                Err(err) => return Err(err),
+            }
+        }
+
+        Ok(results)
+    }
+
+    fn acquire<R: Request>(&self, request: &R) -> Result<Duration, Error> {
+        match (&self.rate_limiter, request.message_priority()) {
+            (Some(rate_limiter), Some(priority)) => rate_limiter.acquire_message(priority),
+            _ => Ok(Duration::from_secs(0)),
+        }
+    }
+
+    fn observe_headers(&self, headers: &reqwest::header::HeaderMap) {
+        if let Some(ref rate_limiter) = self.rate_limiter {
+            rate_limiter.update_from_headers(headers);
+        }
+    }
+
+    fn client(&self) -> Result<&reqwest::Client, Error> {
+        if let Some(client) = self.client.get() {
+            return Ok(client);
+        }
+
+        let client = reqwest::ClientBuilder::new()
+            .timeout(self.timeout)
+            .build()?;
+
+        Ok(self.client.get_or_init(|| client))
     }
+
+    fn blocking_client(&self) -> Result<&reqwest::blocking::Client, Error> {
+        if let Some(client) = self.blocking_client.get() {
+            return Ok(client);
+        }
+
+        let client = reqwest::blocking::ClientBuilder::new()
+            .timeout(self.timeout)
+            .build()?;
+
+        Ok(self.blocking_client.get_or_init(|| client))
+    }
+
+    fn build_url<R: Request>(&self, request: &R) -> Url {
+        let mut url = Url::parse(&self.base_url).unwrap();
+        url.set_path(API_VERSION);
+
+        request.build_url(&mut url);
+
+        url
+    }
+}
+
+fn map_response<R: Request>(
+    status: StatusCode,
+    value: Response<R>,
+) -> Result<<R as Request>::ResponseType, Error> {
+    match value {
+        Response::Success(raw) => Ok(R::map(raw)),
+        Response::Error(ErrorKind::PushoverError {
+            errors, request, ..
+        }) if status == StatusCode::TOO_MANY_REQUESTS => {
+            Err(ErrorKind::QuotaExceeded { errors, request }.into())
+        }
+        Response::Error(err) => Err(err.into()),
+    }
+}
+
+/// Runs `send` on every request, at most `concurrency` at a time, yielding the results in input
+/// order and stopping after the first
+/// [ErrorKind::QuotaExceeded](enum.ErrorKind.html#variant.QuotaExceeded).
+pub(crate) fn send_each<'a, R, I, T, F, Fut>(
+    requests: I,
+    concurrency: usize,
+    send: F,
+) -> impl Stream<Item = Result<T, Error>> + 'a
+where
+    R: 'a,
+    T: 'a,
+    I: IntoIterator<Item = R>,
+    I::IntoIter: 'a,
+    F: FnMut(R) -> Fut + 'a,
+    Fut: Future<Output = Result<T, Error>> + 'a,
+{
+    stream::iter(requests)
+        .map(send)
+        .buffered(concurrency.max(1))
+        .scan(false, |exhausted, result| {
+            if *exhausted {
+                return future::ready(None);
+            }
+
+            *exhausted = is_quota_exceeded(&result);
+
+            future::ready(Some(result))
+        })
+}
+
+/// Blocking version of [send_each](fn.send_each.html), running `send` from up to `concurrency`
+/// threads.
+pub(crate) fn send_each_blocking<R, T, F>(
+    requests: &[R],
+    concurrency: usize,
+    send: F,
+) -> Vec<Result<T, Error>>
+where
+    R: Sync,
+    T: Send,
+    F: Fn(&R) -> Result<T, Error> + Sync,
+{
+    let results: Mutex<Vec<Option<Result<_, Error>>>> =
+        Mutex::new(requests.iter().map(|_| None).collect());
+    let next = AtomicUsize::new(0);
+    let exhausted = AtomicBool::new(false);
+
+    thread::scope(|scope| {
+        for _ in 0..concurrency.max(1).min(requests.len()) {
+            scope.spawn(|| loop {
+                if exhausted.load(Ordering::SeqCst) {
+                    break;
+                }
+
+                let index = next.fetch_add(1, Ordering::SeqCst);
+                let request = match requests.get(index) {
+                    Some(request) => request,
+                    None => break,
+                };
+
+                let result = send(request);
+                if is_quota_exceeded(&result) {
+                    exhausted.store(true, Ordering::SeqCst);
+                }
+
+                results.lock().unwrap()[index] = Some(result);
+            });
+        }
+    });
+
+    let mut ordered = Vec::new();
+    for result in results.into_inner().unwrap().into_iter().map_while(|r| r) {
+        let stop = is_quota_exceeded(&result);
+        ordered.push(result);
+
+        if stop {
+            break;
+        }
+    }
+
+    ordered
+}
+
+fn is_quota_exceeded<T>(result: &Result<T, Error>) -> bool {
+    matches!(result, Err(Error(ErrorKind::QuotaExceeded { .. }, _)))
+}
+
+/// Whether Pushover, or validation ahead of it, refused the request itself.
+fn is_rejection(err: &Error) -> bool {
+    matches!(
+        err.kind(),
+        ErrorKind::PushoverError { .. }
+            | ErrorKind::InvalidRecipient { .. }
+            | ErrorKind::InvalidRequest(..)
+    )
+}
+
+fn is_invalid_recipient(err: &Error) -> bool {
+    matches!(err.kind(), ErrorKind::InvalidRecipient { .. })
+}
+
+/// The rejection of `batch`, reported for each of its recipients.
+fn rejected(batch: &SendMessage, err: &Error) -> Vec<RecipientResult> {
+    batch
+        .recipients
+        .iter()
+        .map(|recipient| RecipientResult {
+            recipient: recipient.to_owned(),
+            result: Err(match err.kind() {
+                ErrorKind::PushoverError {
+                    status,
+                    errors,
+                    request,
+                } => ErrorKind::PushoverError {
+                    status: *status,
+                    errors: errors.clone(),
+                    request: request.clone(),
+                },
+                ErrorKind::InvalidRecipient {
+                    field,
+                    errors,
+                    request,
+                } => ErrorKind::InvalidRecipient {
+                    field: field.clone(),
+                    errors: errors.clone(),
+                    request: request.clone(),
+                },
+                ErrorKind::InvalidRequest(violations) => {
+                    ErrorKind::InvalidRequest(violations.clone())
+                }
+                _ => ErrorKind::Msg(err.to_string()),
+            }
+            .into()),
+        })
+        .collect()
+}
+
+fn covered(batch: &SendMessage, response: SendMessageResponse) -> Vec<RecipientResult> {
+    batch
+        .recipients
+        .iter()
+        .map(|recipient| RecipientResult {
+            recipient: recipient.to_owned(),
+            result: Ok(response.clone()),
+        })
+        .collect()
+}
+
+fn encode_body<R: Request>(request: &R) -> Option<String> {
+    request.get_form_parameters().map(|params| {
+        form_urlencoded::Serializer::new(String::new())
+            .extend_pairs(params)
+            .finish()
+    })
 }
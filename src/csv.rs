@@ -0,0 +1,123 @@
+//! Minimal CSV reading and writing (RFC 4180) for group exports.
+use std::io::{self, Write};
+
+pub(crate) fn write_row<W: Write>(writer: &mut W, fields: &[&str]) -> io::Result<()> {
+    let fields: Vec<String> = fields.iter().map(|field| quote(field)).collect();
+
+    write!(writer, "{}\r\n", fields.join(","))
+}
+
+fn quote(field: &str) -> String {
+    if field.contains(&[',', '"', '\r', '\n'][..]) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_owned()
+    }
+}
+
+/// Splits `input` into rows of fields, skipping blank lines.
+///
+/// Each row comes with the number of the line it starts on, counting from 1, so that rows after a
+/// blank line or a field spanning several lines are still numbered as they appear in the file.
+pub(crate) fn parse(input: &str) -> Result<Vec<(usize, Vec<String>)>, String> {
+    let mut rows = Vec::new();
+    let mut row = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut line = 1;
+    let mut start = line;
+    let mut chars = input.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted => {
+                if chars.peek() == Some(&'"') {
+                    chars.next();
+                    field.push('"');
+                } else {
+                    quoted = false;
+                }
+            }
+            '"' if field.is_empty() => quoted = true,
+            ',' if !quoted => row.push(std::mem::take(&mut field)),
+            '\r' if !quoted && chars.peek() == Some(&'\n') => {}
+            '\n' if !quoted => {
+                row.push(std::mem::take(&mut field));
+                push_row(&mut rows, start, std::mem::take(&mut row));
+                line += 1;
+                start = line;
+            }
+            '\n' => {
+                line += 1;
+                field.push(c);
+            }
+            c => field.push(c),
+        }
+    }
+
+    if quoted {
+        return Err(String::from("unterminated quoted field"));
+    }
+
+    row.push(field);
+    push_row(&mut rows, start, row);
+
+    Ok(rows)
+}
+
+fn push_row(rows: &mut Vec<(usize, Vec<String>)>, line: usize, row: Vec<String>) {
+    if !(row.len() == 1 && row[0].is_empty()) {
+        rows.push((line, row));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_special_characters() {
+        let mut out = Vec::new();
+        write_row(
+            &mut out,
+            &["plain", "with, comma", "with \"quotes\"", "two\nlines"],
+        )
+        .unwrap();
+        write_row(&mut out, &["", "last"]).unwrap();
+
+        let out = String::from_utf8(out).unwrap();
+
+        assert_eq!(
+            out,
+            "plain,\"with, comma\",\"with \"\"quotes\"\"\",\"two\nlines\"\r\n,last\r\n"
+        );
+        assert_eq!(
+            parse(&out).unwrap(),
+            [
+                (
+                    1,
+                    vec!["plain", "with, comma", "with \"quotes\"", "two\nlines"]
+                        .into_iter()
+                        .map(String::from)
+                        .collect::<Vec<_>>()
+                ),
+                (3, vec![String::from(""), String::from("last")]),
+            ]
+        );
+    }
+
+    #[test]
+    fn numbers_rows_by_line() {
+        let rows = parse("header\n\na\r\n\r\nb\n").unwrap();
+
+        assert_eq!(
+            rows.iter().map(|(line, _)| *line).collect::<Vec<_>>(),
+            [1, 3, 5]
+        );
+    }
+
+    #[test]
+    fn rejects_unterminated_quote() {
+        assert!(parse("a,\"b\n").is_err());
+    }
+}
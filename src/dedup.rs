@@ -0,0 +1,407 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::client::API;
+use crate::clock::{Clock, SystemClock};
+use crate::error::Error;
+use crate::requests::message::{SendMessage, SendMessageResponse};
+use crate::validation::MAX_MESSAGE_LENGTH;
+
+/// Suppresses repeats of the same message within a window.
+///
+/// The first message for a key is sent and opens a window; repeats within the window are counted
+/// instead of sent. Once the window has closed, the last repeat is sent once with
+/// `(+N similar suppressed)` appended, either by [flush](#method.flush) or, if the same alert
+/// fires again first, on that next message.
+///
+/// Messages are keyed by a hash of their title, message and recipients, or by a key of the
+/// caller's choosing with [send_keyed](#method.send_keyed).
+///
+/// ```rust,no_run
+/// use std::time::Duration;
+/// use pushover::{Deduplicator, API};
+/// use pushover::requests::message::SendMessage;
+///
+/// let dedup = Deduplicator::new(API::new(), Duration::from_secs(300));
+///
+/// for _ in 0..10 {
+///     let msg = SendMessage::new("token", "user_key", "db01 is down");
+///     dedup.send(&msg).expect("Error sending message");
+/// }
+///
+/// // Later, e.g. from a timer:
+/// dedup.flush().expect("Error sending summaries");
+/// ```
+pub struct Deduplicator {
+    api: API,
+    window: Duration,
+    clock: Arc<dyn Clock>,
+    windows: Mutex<HashMap<String, Window>>,
+}
+
+struct Window {
+    opened: SystemTime,
+    suppressed: u32,
+    last: Option<SendMessage>,
+}
+
+/// A message let through by `admit`, with the window it replaced.
+struct Admitted {
+    message: SendMessage,
+    opened: SystemTime,
+    previous: Option<Window>,
+}
+
+impl Window {
+    fn summary(&self) -> SendMessage {
+        summarize(
+            self.last.as_ref().expect("closed window without messages"),
+            self.suppressed,
+        )
+    }
+}
+
+impl Deduplicator {
+    pub fn new(api: API, window: Duration) -> Self {
+        Self {
+            api,
+            window,
+            clock: Arc::new(SystemClock),
+            windows: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn clock<C: Clock + 'static>(mut self, clock: C) -> Self {
+        self.clock = Arc::new(clock);
+        self
+    }
+
+    /// Sends `message` unless a similar one was sent within the window, returning `None` if it
+    /// was suppressed.
+    pub fn send(&self, message: &SendMessage) -> Result<Option<SendMessageResponse>, Error> {
+        self.send_keyed(&key(message), message)
+    }
+
+    /// Like [send](#method.send), deduplicating by `key` instead of the message's content.
+    pub fn send_keyed(
+        &self,
+        key: &str,
+        message: &SendMessage,
+    ) -> Result<Option<SendMessageResponse>, Error> {
+        let admitted = match self.admit(key, message) {
+            Some(admitted) => admitted,
+            None => return Ok(None),
+        };
+
+        let result = self.api.send(&admitted.message);
+        if result.is_err() {
+            self.reject(key, admitted);
+        }
+        result.map(Some)
+    }
+
+    pub async fn send_async(
+        &self,
+        message: &SendMessage,
+    ) -> Result<Option<SendMessageResponse>, Error> {
+        self.send_keyed_async(&key(message), message).await
+    }
+
+    pub async fn send_keyed_async(
+        &self,
+        key: &str,
+        message: &SendMessage,
+    ) -> Result<Option<SendMessageResponse>, Error> {
+        let admitted = match self.admit(key, message) {
+            Some(admitted) => admitted,
+            None => return Ok(None),
+        };
+
+        let result = self.api.send_async(&admitted.message).await;
+        if result.is_err() {
+            self.reject(key, admitted);
+        }
+        result.map(Some)
+    }
+
+    /// Sends a summary for every window that has closed with messages suppressed.
+    ///
+    /// Stops at the first error; the remaining summaries are sent by the next call.
+    pub fn flush(&self) -> Result<Vec<SendMessageResponse>, Error> {
+        let mut responses = Vec::new();
+
+        for (key, window) in self.closed() {
+            match self.api.send(&window.summary()) {
+                Ok(response) => responses.push(response),
+                Err(e) => {
+                    self.restore(key, window);
+                    return Err(e);
+                }
+            }
+        }
+
+        Ok(responses)
+    }
+
+    pub async fn flush_async(&self) -> Result<Vec<SendMessageResponse>, Error> {
+        let mut responses = Vec::new();
+
+        for (key, window) in self.closed() {
+            match self.api.send_async(&window.summary()).await {
+                Ok(response) => responses.push(response),
+                Err(e) => {
+                    self.restore(key, window);
+                    return Err(e);
+                }
+            }
+        }
+
+        Ok(responses)
+    }
+
+    /// Number of messages suppressed so far in the window for `key`.
+    pub fn suppressed(&self, key: &str) -> u32 {
+        self.windows
+            .lock()
+            .unwrap()
+            .get(key)
+            .map_or(0, |window| window.suppressed)
+    }
+
+    /// Records `message` under `key`, returning the message to send now, if any.
+    fn admit(&self, key: &str, message: &SendMessage) -> Option<Admitted> {
+        let now = self.clock.now();
+        let mut windows = self.windows.lock().unwrap();
+
+        let carried = match windows.get_mut(key) {
+            Some(window) if !self.is_closed(window, now) => {
+                window.suppressed += 1;
+                window.last = Some(message.clone());
+                return None;
+            }
+            Some(window) => window.suppressed,
+            None => 0,
+        };
+
+        let previous = windows.insert(
+            key.to_owned(),
+            Window {
+                opened: now,
+                suppressed: 0,
+                last: None,
+            },
+        );
+
+        Some(Admitted {
+            message: summarize(message, carried),
+            opened: now,
+            previous,
+        })
+    }
+
+    /// Undoes [admit](#method.admit) for a message that failed to send, so that retries aren't
+    /// suppressed. Repeats suppressed in the meantime are kept for the next summary.
+    fn reject(&self, key: &str, admitted: Admitted) {
+        let mut windows = self.windows.lock().unwrap();
+
+        // The window may have closed and been reopened by another message since.
+        let current = match windows.remove(key) {
+            Some(window) if window.opened == admitted.opened => window,
+            Some(window) => {
+                windows.insert(key.to_owned(), window);
+                return;
+            }
+            None => return,
+        };
+
+        // Without a previous window, keep the repeats in one that is already closed.
+        let mut window = admitted.previous.unwrap_or(Window {
+            opened: admitted
+                .opened
+                .checked_sub(self.window)
+                .unwrap_or(UNIX_EPOCH),
+            suppressed: 0,
+            last: None,
+        });
+        window.suppressed += current.suppressed;
+        if current.last.is_some() {
+            window.last = current.last;
+        }
+
+        if window.last.is_some() {
+            windows.insert(key.to_owned(), window);
+        }
+    }
+
+    /// Removes the closed windows that suppressed messages, for their summaries to be sent.
+    fn closed(&self) -> Vec<(String, Window)> {
+        let now = self.clock.now();
+        let mut windows = self.windows.lock().unwrap();
+        let keys: Vec<String> = windows
+            .iter()
+            .filter(|(_, window)| self.is_closed(window, now))
+            .map(|(key, _)| key.clone())
+            .collect();
+
+        keys.into_iter()
+            .filter_map(|key| {
+                let window = windows.remove(&key)?;
+                window.last.as_ref()?;
+                Some((key, window))
+            })
+            .collect()
+    }
+
+    /// Puts back a window whose summary could not be sent, unless the key has been seen again
+    /// since.
+    fn restore(&self, key: String, window: Window) {
+        self.windows.lock().unwrap().entry(key).or_insert(window);
+    }
+
+    fn is_closed(&self, window: &Window, now: SystemTime) -> bool {
+        now.duration_since(window.opened)
+            .is_ok_and(|elapsed| elapsed >= self.window)
+    }
+}
+
+fn key(message: &SendMessage) -> String {
+    let mut hasher = DefaultHasher::new();
+    message.title.hash(&mut hasher);
+    message.message.hash(&mut hasher);
+    message.recipients.to_string().hash(&mut hasher);
+    format!("{:016x}", hasher.finish())
+}
+
+/// `message` with the number of suppressed repeats appended, keeping it within Pushover's limit.
+fn summarize(message: &SendMessage, suppressed: u32) -> SendMessage {
+    let mut message = message.clone();
+
+    if suppressed > 0 {
+        let note = format!(" (+{} similar suppressed)", suppressed);
+        let keep = MAX_MESSAGE_LENGTH.saturating_sub(note.chars().count());
+
+        if message.message.chars().count() > keep {
+            message.message = message.message.chars().take(keep).collect();
+        }
+        message.message.push_str(&note);
+    }
+
+    message
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::ManualClock;
+
+    fn dedup(clock: &ManualClock) -> Deduplicator {
+        Deduplicator::new(API::new(), Duration::from_secs(60)).clock(clock.clone())
+    }
+
+    #[test]
+    fn suppresses_repeats_within_window() {
+        let clock = ManualClock::new(SystemTime::UNIX_EPOCH);
+        let dedup = dedup(&clock);
+        let message = SendMessage::new("token", "user", "db01 is down");
+
+        assert!(dedup.admit("k", &message).is_some());
+        assert!(dedup.admit("k", &message).is_none());
+        assert!(dedup.admit("k", &message).is_none());
+        assert!(dedup.admit("other", &message).is_some());
+        assert_eq!(dedup.suppressed("k"), 2);
+        assert!(dedup.closed().is_empty());
+    }
+
+    #[test]
+    fn summarizes_when_window_closes() {
+        let clock = ManualClock::new(SystemTime::UNIX_EPOCH);
+        let dedup = dedup(&clock);
+        let message = SendMessage::new("token", "user", "db01 is down");
+
+        dedup.admit("k", &message);
+        dedup.admit("k", &message);
+        dedup.admit("k", &message);
+        clock.advance(Duration::from_secs(60));
+
+        let closed = dedup.closed();
+
+        assert_eq!(closed.len(), 1);
+        assert_eq!(
+            closed[0].1.summary().message,
+            "db01 is down (+2 similar suppressed)"
+        );
+        assert_eq!(dedup.suppressed("k"), 0);
+    }
+
+    #[test]
+    fn carries_count_into_next_message() {
+        let clock = ManualClock::new(SystemTime::UNIX_EPOCH);
+        let dedup = dedup(&clock);
+        let message = SendMessage::new("token", "user", "db01 is down");
+
+        dedup.admit("k", &message);
+        dedup.admit("k", &message);
+        clock.advance(Duration::from_secs(90));
+
+        let sent = dedup.admit("k", &message).unwrap().message;
+
+        assert_eq!(sent.message, "db01 is down (+1 similar suppressed)");
+        assert!(dedup.closed().is_empty());
+    }
+
+    #[test]
+    fn failed_send_does_not_suppress_retries() {
+        let dedup = Deduplicator::new(
+            API::new()
+                .base_url("http://127.0.0.1:1")
+                .timeout(Duration::from_secs(1)),
+            Duration::from_secs(60),
+        );
+        let message = SendMessage::new("token", "user", "db01 is down");
+
+        assert!(dedup.send_keyed("k", &message).is_err());
+        assert!(dedup.send_keyed("k", &message).is_err());
+        assert_eq!(dedup.suppressed("k"), 0);
+    }
+
+    #[test]
+    fn reject_keeps_repeats_suppressed_meanwhile() {
+        let clock = ManualClock::new(SystemTime::UNIX_EPOCH);
+        let dedup = dedup(&clock);
+        let message = SendMessage::new("token", "user", "db01 is down");
+
+        let admitted = dedup.admit("k", &message).unwrap();
+        assert!(dedup.admit("k", &message).is_none());
+        dedup.reject("k", admitted);
+
+        let sent = dedup.admit("k", &message).unwrap().message;
+        assert_eq!(sent.message, "db01 is down (+1 similar suppressed)");
+    }
+
+    #[test]
+    fn default_key_covers_title_message_and_recipients() {
+        let message = SendMessage::new("token", "user", "message");
+        let mut titled = message.clone();
+        titled.set_title("title");
+
+        assert_eq!(key(&message), key(&message.clone()));
+        assert_ne!(key(&message), key(&titled));
+        assert_ne!(
+            key(&message),
+            key(&SendMessage::new("token", "other", "message"))
+        );
+    }
+
+    #[test]
+    fn summary_stays_within_limit() {
+        let message = SendMessage::new("token", "user", "a".repeat(MAX_MESSAGE_LENGTH));
+
+        let summary = summarize(&message, 14);
+
+        assert_eq!(summary.message.chars().count(), MAX_MESSAGE_LENGTH);
+        assert!(summary.message.ends_with(" (+14 similar suppressed)"));
+    }
+}
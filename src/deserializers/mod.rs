@@ -1,4 +1,9 @@
+use std::time::SystemTime;
+
 use serde::de::{Deserialize, Deserializer};
+use serde::Serializer;
+
+use crate::types::{from_unix_seconds, TimestampExt};
 
 pub fn deserialize_option_empty_string<'de, D>(deserializer: D) -> Result<Option<String>, D::Error>
 where
@@ -11,3 +16,19 @@ where
         _ => Ok(result),
     }
 }
+
+pub fn deserialize_unix_timestamp<'de, D>(deserializer: D) -> Result<SystemTime, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let seconds: u64 = Deserialize::deserialize(deserializer)?;
+
+    Ok(from_unix_seconds(seconds))
+}
+
+pub fn serialize_unix_timestamp<S>(timestamp: &SystemTime, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    serializer.serialize_u64(timestamp.unix_seconds())
+}
@@ -0,0 +1,452 @@
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+use crate::client::API;
+use crate::clock::{Clock, SystemClock};
+use crate::error::{Error, ErrorKind};
+use crate::requests::message::{SendMessage, SendMessageResponse};
+use crate::types::Priority;
+use crate::validation::MAX_MESSAGE_LENGTH;
+
+/// Collects low-priority messages and sends them per recipient as a single digest.
+///
+/// Messages with [Priority::Lowest](enum.Priority.html#variant.Lowest) or
+/// [Priority::Low](enum.Priority.html#variant.Low) are held until `max_items` are pending for a
+/// recipient or the oldest has waited `interval`; every other message is sent immediately. Call
+/// [tick](#method.tick) periodically to send digests that are due when nothing else is being
+/// sent. When done, call [finish](#method.finish) or [finish_async](#method.finish_async), which
+/// send everything still pending and hand back whatever could not be sent: dropping a `Digest`
+/// doesn't send anything, so messages still pending are lost.
+///
+/// ```rust,no_run
+/// use std::time::Duration;
+/// use pushover::{Digest, Priority, API};
+/// use pushover::requests::message::SendMessage;
+///
+/// let digest = Digest::new(API::new(), Duration::from_secs(15 * 60), 20).html(true);
+///
+/// let mut msg = SendMessage::new("token", "user_key", "Backup finished");
+/// msg.set_priority(Priority::Low);
+/// digest.send(&msg).expect("Error sending message");
+///
+/// digest.finish().expect("Error sending digests");
+/// ```
+#[must_use = "pending messages are lost unless the digest is finished"]
+pub struct Digest {
+    api: API,
+    interval: Duration,
+    max_items: usize,
+    html: bool,
+    clock: Arc<dyn Clock>,
+    pending: Mutex<BTreeMap<Group, Pending>>,
+}
+
+/// App token and recipient key a digest is sent with.
+type Group = (String, String);
+
+struct Pending {
+    since: SystemTime,
+    messages: Vec<SendMessage>,
+}
+
+impl Digest {
+    pub fn new(api: API, interval: Duration, max_items: usize) -> Self {
+        Self {
+            api,
+            interval,
+            max_items: max_items.max(1),
+            html: false,
+            clock: Arc::new(SystemClock),
+            pending: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    /// Render digests with bold titles using Pushover's HTML formatting.
+    pub fn html(mut self, html: bool) -> Self {
+        self.html = html;
+        self
+    }
+
+    pub fn clock<C: Clock + 'static>(mut self, clock: C) -> Self {
+        self.clock = Arc::new(clock);
+        self
+    }
+
+    /// Sends `message` now or adds it to the digest, depending on its priority.
+    ///
+    /// Returns the responses for what this call sent: the message itself, or the digests that
+    /// became due.
+    pub fn send(&self, message: &SendMessage) -> Result<Vec<SendMessageResponse>, Error> {
+        if !is_digested(message) {
+            return self.api.send(message).map(|response| vec![response]);
+        }
+
+        self.add(message);
+        self.tick()
+    }
+
+    pub async fn send_async(
+        &self,
+        message: &SendMessage,
+    ) -> Result<Vec<SendMessageResponse>, Error> {
+        if !is_digested(message) {
+            return self
+                .api
+                .send_async(message)
+                .await
+                .map(|response| vec![response]);
+        }
+
+        self.add(message);
+        self.tick_async().await
+    }
+
+    /// Sends the digests that are due.
+    pub fn tick(&self) -> Result<Vec<SendMessageResponse>, Error> {
+        self.deliver(self.take(false))
+    }
+
+    pub async fn tick_async(&self) -> Result<Vec<SendMessageResponse>, Error> {
+        self.deliver_async(self.take(false)).await
+    }
+
+    /// Sends every pending digest, due or not.
+    pub fn flush(&self) -> Result<Vec<SendMessageResponse>, Error> {
+        self.deliver(self.take(true))
+    }
+
+    pub async fn flush_async(&self) -> Result<Vec<SendMessageResponse>, Error> {
+        self.deliver_async(self.take(true)).await
+    }
+
+    /// Sends every pending digest and consumes the `Digest`.
+    ///
+    /// If a digest can't be sent, returns
+    /// [ErrorKind::DigestUnsent](enum.ErrorKind.html#variant.DigestUnsent) with the messages
+    /// that are still pending, chained to the error that stopped delivery.
+    pub fn finish(self) -> Result<Vec<SendMessageResponse>, Error> {
+        self.flush().map_err(|e| self.unsent(e))
+    }
+
+    pub async fn finish_async(self) -> Result<Vec<SendMessageResponse>, Error> {
+        match self.flush_async().await {
+            Ok(responses) => Ok(responses),
+            Err(e) => Err(self.unsent(e)),
+        }
+    }
+
+    /// Number of messages waiting to be sent in a digest.
+    pub fn len(&self) -> usize {
+        self.pending
+            .lock()
+            .unwrap()
+            .values()
+            .map(|pending| pending.messages.len())
+            .sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn add(&self, message: &SendMessage) {
+        let now = self.clock.now();
+        let mut pending = self.pending.lock().unwrap();
+
+        for recipient in message.recipients.iter() {
+            pending
+                .entry((message.token.clone(), recipient.to_owned()))
+                .or_insert_with(|| Pending {
+                    since: now,
+                    messages: Vec::new(),
+                })
+                .messages
+                .push(message.for_recipient(recipient));
+        }
+    }
+
+    fn unsent(self, error: Error) -> Error {
+        let messages = self
+            .take(true)
+            .into_iter()
+            .flat_map(|(_, group)| group.messages)
+            .collect();
+
+        Error::with_chain(error, ErrorKind::DigestUnsent(messages))
+    }
+
+    /// Removes the groups that are due, or all of them.
+    fn take(&self, all: bool) -> Vec<(Group, Pending)> {
+        let now = self.clock.now();
+        let mut pending = self.pending.lock().unwrap();
+        let due: Vec<Group> = pending
+            .iter()
+            .filter(|(_, group)| all || self.is_due(group, now))
+            .map(|(key, _)| key.clone())
+            .collect();
+
+        due.into_iter()
+            .filter_map(|key| pending.remove(&key).map(|group| (key, group)))
+            .collect()
+    }
+
+    /// Puts back groups that could not be sent, ahead of anything added since.
+    fn restore(&self, groups: impl IntoIterator<Item = (Group, Pending)>) {
+        let mut pending = self.pending.lock().unwrap();
+
+        for (key, mut group) in groups {
+            if let Some(newer) = pending.remove(&key) {
+                group.messages.extend(newer.messages);
+            }
+            pending.insert(key, group);
+        }
+    }
+
+    fn deliver(&self, groups: Vec<(Group, Pending)>) -> Result<Vec<SendMessageResponse>, Error> {
+        let mut responses = Vec::new();
+        let mut groups = groups.into_iter();
+
+        while let Some(group) = groups.next() {
+            match self.api.send(&render(&group.1.messages, self.html)) {
+                Ok(response) => responses.push(response),
+                Err(e) => {
+                    self.restore(std::iter::once(group).chain(groups));
+                    return Err(e);
+                }
+            }
+        }
+
+        Ok(responses)
+    }
+
+    async fn deliver_async(
+        &self,
+        groups: Vec<(Group, Pending)>,
+    ) -> Result<Vec<SendMessageResponse>, Error> {
+        let mut responses = Vec::new();
+        let mut groups = groups.into_iter();
+
+        while let Some(group) = groups.next() {
+            let digest = render(&group.1.messages, self.html);
+
+            match self.api.send_async(&digest).await {
+                Ok(response) => responses.push(response),
+                Err(e) => {
+                    self.restore(std::iter::once(group).chain(groups));
+                    return Err(e);
+                }
+            }
+        }
+
+        Ok(responses)
+    }
+
+    fn is_due(&self, group: &Pending, now: SystemTime) -> bool {
+        group.messages.len() >= self.max_items
+            || now
+                .duration_since(group.since)
+                .is_ok_and(|waited| waited >= self.interval)
+    }
+}
+
+fn is_digested(message: &SendMessage) -> bool {
+    matches!(
+        message.priority,
+        Some(Priority::Lowest) | Some(Priority::Low)
+    )
+}
+
+/// Combines the messages for one recipient into a single message within Pushover's limit.
+fn render(messages: &[SendMessage], html: bool) -> SendMessage {
+    if let [message] = messages {
+        return message.clone();
+    }
+
+    let lines: Vec<String> = messages.iter().map(|message| line(message, html)).collect();
+
+    let mut shown = lines.len();
+    let body = loop {
+        let mut body = lines[..shown].join("\n");
+
+        if shown < lines.len() {
+            if shown > 0 {
+                body.push('\n');
+            }
+            body.push_str(&format!("… and {} more", lines.len() - shown));
+        }
+
+        if shown == 0 || body.chars().count() <= MAX_MESSAGE_LENGTH {
+            break body;
+        }
+
+        shown -= 1;
+    };
+
+    let mut digest = SendMessage {
+        message: body,
+        devices: Vec::new(),
+        title: Some(format!("{} notifications", messages.len())),
+        url: None,
+        url_title: None,
+        priority: messages.iter().filter_map(|message| message.priority).max(),
+        timestamp: None,
+        sound: None,
+        overflow: None,
+        html,
+        ..messages[0].clone()
+    };
+
+    // Devices are only kept if every message was addressed to the same ones.
+    if messages
+        .iter()
+        .all(|message| message.devices == messages[0].devices)
+    {
+        digest.devices = messages[0].devices.clone();
+    }
+
+    digest
+}
+
+/// One message as a line of a digest, cut short so that at least one fits.
+fn line(message: &SendMessage, html: bool) -> String {
+    let text = |value: &str| {
+        if html && !message.html {
+            escape(value)
+        } else {
+            value.to_owned()
+        }
+    };
+
+    let mut line = String::from("• ");
+    if let Some(ref title) = message.title {
+        if html {
+            line.push_str(&format!("<b>{}</b>: ", escape(title)));
+        } else {
+            line.push_str(&format!("{}: ", title));
+        }
+    }
+    line.push_str(&text(&message.message).replace('\n', " "));
+
+    let limit = MAX_MESSAGE_LENGTH / 2;
+    if line.chars().count() > limit {
+        line = line.chars().take(limit - 1).collect();
+        if html {
+            // Don't leave half an entity or tag at the cut.
+            for (open, close) in [('&', ';'), ('<', '>')] {
+                if let Some(start) = line.rfind(open) {
+                    if !line[start..].contains(close) {
+                        line.truncate(start);
+                    }
+                }
+            }
+        }
+        line.push('…');
+    }
+
+    line
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::ManualClock;
+    use std::time::UNIX_EPOCH;
+
+    fn low(recipient: &str, text: &str) -> SendMessage {
+        let mut message = SendMessage::new("token", recipient, text);
+        message.set_priority(Priority::Low);
+        message
+    }
+
+    fn digest(clock: &ManualClock) -> Digest {
+        Digest::new(API::new(), Duration::from_secs(600), 3).clock(clock.clone())
+    }
+
+    #[test]
+    fn groups_per_recipient_until_due() {
+        let clock = ManualClock::new(UNIX_EPOCH);
+        let digest = digest(&clock);
+
+        digest.add(&low("a,b", "first"));
+        digest.add(&low("a", "second"));
+
+        assert_eq!(digest.len(), 3);
+        assert!(digest.take(false).is_empty());
+
+        digest.add(&low("a", "third"));
+        let due = digest.take(false);
+
+        assert_eq!(due.len(), 1);
+        assert_eq!(due[0].0 .1, "a");
+        assert_eq!(digest.len(), 1);
+
+        clock.advance(Duration::from_secs(600));
+
+        assert_eq!(digest.take(false)[0].0 .1, "b");
+        assert!(digest.is_empty());
+    }
+
+    #[test]
+    fn renders_titles_and_messages() {
+        let mut titled = low("a", "disk at 91%");
+        titled.set_title("db01");
+
+        let digest = render(&[titled, low("a", "backup done")], false);
+
+        assert_eq!(digest.title.as_deref(), Some("2 notifications"));
+        assert_eq!(digest.message, "• db01: disk at 91%\n• backup done");
+        assert_eq!(digest.priority, Some(Priority::Low));
+        assert!(!digest.html);
+    }
+
+    #[test]
+    fn renders_html() {
+        let mut titled = low("a", "x < y");
+        titled.set_title("a&b");
+
+        let digest = render(&[titled, low("a", "done")], true);
+
+        assert_eq!(digest.message, "• <b>a&amp;b</b>: x &lt; y\n• done");
+        assert!(digest.html);
+    }
+
+    #[test]
+    fn stays_within_limit() {
+        let messages: Vec<SendMessage> = (0..100)
+            .map(|i| low("a", &format!("{} {}", i, "x".repeat(40))))
+            .collect();
+
+        let digest = render(&messages, false);
+
+        assert!(digest.message.chars().count() <= MAX_MESSAGE_LENGTH);
+        assert!(digest.message.ends_with(" more"));
+    }
+
+    #[test]
+    fn truncates_html_on_entity_boundaries() {
+        let limit = MAX_MESSAGE_LENGTH / 2;
+
+        for offset in 0..5 {
+            let text = format!("{}{}", "x".repeat(limit - 4 - offset), "&".repeat(10));
+            let line = line(&low("a", &text), true);
+
+            assert!(line.chars().count() <= limit);
+            assert!(line.ends_with("x…") || line.ends_with("&amp;…"), "{}", line);
+        }
+    }
+
+    #[test]
+    fn single_message_is_sent_unchanged() {
+        let message = low("a", "only");
+
+        assert_eq!(render(std::slice::from_ref(&message), true), message);
+    }
+}
@@ -1,10 +1,18 @@
+// `error_chain!` checks a cfg of its own crate that rustc doesn't know about.
+#![allow(unexpected_cfgs)]
+
 use error_chain::error_chain;
 
+use crate::redact::TransportError;
+use crate::requests::message::SendMessage;
+use crate::validation::Violation;
+
 error_chain! {
     foreign_links {
         Json(::serde_json::Error);
         Io(::std::io::Error);
-        Reqqest(::reqwest::Error);
+        Reqqest(TransportError);
+        Toml(::toml::de::Error);
     }
 
     errors {
@@ -13,5 +21,102 @@ error_chain! {
             errors: Vec<String>,
             request: String
         }
+
+        InvalidRecipient {
+            field: String,
+            errors: Vec<String>,
+            request: String
+        } {
+            description("Pushover rejected a recipient")
+            display("Pushover rejected the {}: {}", field, errors.join(", "))
+        }
+
+        QuotaExceeded {
+            errors: Vec<String>,
+            request: String
+        } {
+            description("app has used its monthly message quota")
+            display("app has used its monthly message quota: {}", errors.join(", "))
+        }
+
+        ServerError(status: u16) {
+            description("Pushover had a server error")
+            display("Pushover had a server error (HTTP {}), retry later", status)
+        }
+
+        RateLimited(retry_after: ::std::time::Duration) {
+            description("message would exceed the client-side rate limit")
+            display("message would exceed the client-side rate limit, retry after {:?}", retry_after)
+        }
+
+        QuotaReserved(remaining: u32) {
+            description("remaining quota is reserved for emergency messages")
+            display("remaining quota of {} messages is reserved for emergency messages", remaining)
+        }
+
+        InvalidRequest(violations: Vec<Violation>) {
+            description("request breaks Pushover's limits")
+            display(
+                "request breaks Pushover's limits: {}",
+                violations.iter().map(ToString::to_string).collect::<Vec<_>>().join(", ")
+            )
+        }
+
+        SenderStopped {
+            description("background sender's worker thread has stopped")
+            display("background sender's worker thread has stopped")
+        }
+
+        DigestUnsent(messages: Vec<SendMessage>) {
+            description("digest could not be sent")
+            display("{} digested messages could not be sent", messages.len())
+        }
+
+        SplitRequired(parts: usize) {
+            description("message must be sent in parts")
+            display("message is split into {} parts and must be sent with API::send_parts", parts)
+        }
+
+        DeviceNameTaken(reason: String) {
+            description("device name is already taken")
+            display("device name {}", reason)
+        }
+
+        InvalidDeviceName(reason: String) {
+            description("device name is invalid")
+            display("device name {}", reason)
+        }
+
+        InvalidRules(reason: String) {
+            description("routing rules are invalid")
+            display("routing rules are invalid: {}", reason)
+        }
+
+        InvalidSchedule(reason: String) {
+            description("on-call schedule is invalid")
+            display("on-call schedule is invalid: {}", reason)
+        }
+
+        NobodyOnCall(rotation: String) {
+            description("nobody is on call")
+            display("nobody is on call for rotation '{}'", rotation)
+        }
+    }
+}
+
+// Every `reqwest` error goes through `TransportError`, so `?` can't leak credentials.
+impl From<::reqwest::Error> for Error {
+    fn from(error: ::reqwest::Error) -> Self {
+        ErrorKind::Reqqest(error.into()).into()
+    }
+}
+
+/// Why a request failed: Pushover's own messages if it rejected it, otherwise the error.
+pub(crate) fn reason(error: &Error) -> String {
+    match error.kind() {
+        ErrorKind::PushoverError { errors, .. } | ErrorKind::InvalidRecipient { errors, .. } => {
+            errors.join(", ")
+        }
+        _ => error.to_string(),
     }
 }
@@ -0,0 +1,511 @@
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::client::API;
+use crate::error::{reason, Error, ErrorKind};
+use crate::requests::message::SendMessage;
+use crate::requests::receipt::{CancelEmergency, ReceiptStatus, ReceiptStatusResponse};
+use crate::types::{Priority, Recipients};
+use crate::validation::Validator;
+
+const DEFAULT_POLL_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Pages tiers of recipients in turn until an emergency message is acknowledged.
+///
+/// Each tier is paged with the emergency message and given its timeout to acknowledge it. If it
+/// doesn't, or its receipt expires, the next tier is paged and the previous receipt, unless it
+/// expired, is cancelled. The receipt is checked once more right before paging, so a late
+/// acknowledgement still stops the escalation. The last tier's receipt is left running once its
+/// timeout has passed.
+///
+/// ```rust,no_run
+/// use std::time::Duration;
+/// use pushover::{EmergencyOptions, EscalationPolicy, API};
+/// use pushover::requests::message::SendMessage;
+///
+/// let policy = EscalationPolicy::new()
+///     .tier("on_call_user_key", Duration::from_secs(5 * 60))
+///     .tier("team_group_key", Duration::from_secs(10 * 60))
+///     .tier("manager_user_key", Duration::from_secs(30 * 60));
+///
+/// let mut msg = SendMessage::new("token", "on_call_user_key", "db01 is down");
+/// msg.set_emergency(
+///     EmergencyOptions::builder(Duration::from_secs(60), Duration::from_secs(3600))
+///         .build()
+///         .unwrap(),
+/// );
+///
+/// match policy.run(&API::new(), &msg) {
+///     Ok(timeline) => println!("Acknowledged by {:?}", timeline.acknowledged_by()),
+///     Err(failure) => eprintln!(
+///         "Escalation stopped, still paging {:?}: {}",
+///         failure.timeline.active_receipts(),
+///         failure.error
+///     ),
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct EscalationPolicy {
+    tiers: Vec<EscalationTier>,
+    poll_interval: Duration,
+}
+
+/// Recipients paged together, and how long they have to acknowledge.
+#[derive(Clone, Debug, PartialEq)]
+pub struct EscalationTier {
+    pub recipients: Recipients,
+    pub timeout: Duration,
+}
+
+/// Something that happened while escalating, with the index of the tier it concerns.
+#[derive(Clone, Debug, PartialEq)]
+pub enum EscalationEvent {
+    Paged {
+        tier: usize,
+        recipients: Recipients,
+        receipt: String,
+        at: SystemTime,
+    },
+    Acknowledged {
+        tier: usize,
+        by: String,
+        device: String,
+        at: SystemTime,
+    },
+    /// The tier's receipt expired before anyone acknowledged it.
+    Expired { tier: usize, at: SystemTime },
+    /// The tier's receipt was cancelled after the next tier was paged.
+    Cancelled {
+        tier: usize,
+        receipt: String,
+        at: SystemTime,
+    },
+    /// Cancelling the tier's receipt failed, so its emergency message is still repeating.
+    CancelFailed {
+        tier: usize,
+        receipt: String,
+        reason: String,
+        at: SystemTime,
+    },
+    /// Every tier timed out without acknowledging.
+    Exhausted { at: SystemTime },
+}
+
+/// Everything that happened during an escalation, in order.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Timeline {
+    pub events: Vec<EscalationEvent>,
+}
+
+/// An escalation that stopped with an error, and what had happened up to then.
+#[derive(Debug)]
+pub struct EscalationFailure {
+    pub timeline: Timeline,
+    pub error: Error,
+}
+
+impl From<EscalationFailure> for Error {
+    fn from(failure: EscalationFailure) -> Self {
+        failure.error
+    }
+}
+
+impl Timeline {
+    /// User who acknowledged the message, if anyone did.
+    pub fn acknowledged_by(&self) -> Option<&str> {
+        self.events.iter().find_map(|event| match event {
+            EscalationEvent::Acknowledged { by, .. } => Some(by.as_str()),
+            _ => None,
+        })
+    }
+
+    /// Receipts of the tiers paged whose emergency messages are still repeating, i.e. not yet
+    /// acknowledged, expired or cancelled.
+    pub fn active_receipts(&self) -> Vec<&str> {
+        self.events
+            .iter()
+            .filter_map(|event| match event {
+                EscalationEvent::Paged { tier, receipt, .. } if !self.is_settled(*tier) => {
+                    Some(receipt.as_str())
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn is_settled(&self, tier: usize) -> bool {
+        self.events.iter().any(|event| match *event {
+            EscalationEvent::Acknowledged { tier: settled, .. }
+            | EscalationEvent::Expired { tier: settled, .. }
+            | EscalationEvent::Cancelled { tier: settled, .. } => settled == tier,
+            _ => false,
+        })
+    }
+
+    /// Every set of recipients that was paged, in order.
+    pub fn paged(&self) -> Vec<&Recipients> {
+        self.events
+            .iter()
+            .filter_map(|event| match event {
+                EscalationEvent::Paged { recipients, .. } => Some(recipients),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+/// What to do after polling a tier's receipt.
+enum Poll {
+    Acknowledged,
+    Expired,
+    Waiting,
+}
+
+impl Default for EscalationPolicy {
+    fn default() -> Self {
+        Self {
+            tiers: Vec::new(),
+            poll_interval: DEFAULT_POLL_INTERVAL,
+        }
+    }
+}
+
+impl EscalationPolicy {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Adds a tier paged after the previous ones, with `timeout` to acknowledge.
+    pub fn tier<R: Into<Recipients>>(mut self, recipients: R, timeout: Duration) -> Self {
+        self.tiers.push(EscalationTier {
+            recipients: recipients.into(),
+            timeout,
+        });
+        self
+    }
+
+    /// How often receipts are polled. Defaults to 30 seconds.
+    pub fn poll_interval(mut self, interval: Duration) -> Self {
+        self.poll_interval = interval;
+        self
+    }
+
+    pub fn tiers(&self) -> &[EscalationTier] {
+        &self.tiers
+    }
+
+    /// Escalates `message`, blocking until it is acknowledged or every tier has timed out.
+    ///
+    /// `message` must have emergency priority; its recipients are replaced by each tier's. Failed
+    /// receipt polls are retried at the next interval, and a superseded receipt that can't be
+    /// cancelled is recorded as
+    /// [CancelFailed](enum.EscalationEvent.html#variant.CancelFailed). Failing to page a tier
+    /// stops the escalation with the error and the timeline so far.
+    pub fn run(&self, api: &API, message: &SendMessage) -> Result<Timeline, EscalationFailure> {
+        let mut timeline = Timeline::default();
+
+        match self.escalate(api, message, &mut timeline) {
+            Ok(()) => Ok(timeline),
+            Err(error) => Err(EscalationFailure { timeline, error }),
+        }
+    }
+
+    pub async fn run_async(
+        &self,
+        api: &API,
+        message: &SendMessage,
+    ) -> Result<Timeline, EscalationFailure> {
+        let mut timeline = Timeline::default();
+
+        match self.escalate_async(api, message, &mut timeline).await {
+            Ok(()) => Ok(timeline),
+            Err(error) => Err(EscalationFailure { timeline, error }),
+        }
+    }
+
+    fn escalate(
+        &self,
+        api: &API,
+        message: &SendMessage,
+        timeline: &mut Timeline,
+    ) -> Result<(), Error> {
+        self.validate(message)?;
+
+        // The previous tier's receipt, while its message is still repeating.
+        let mut previous: Option<String> = None;
+
+        for (index, tier) in self.tiers.iter().enumerate() {
+            if let Some(ref superseded) = previous {
+                let status = ReceiptStatus::new(message.token.as_str(), superseded.as_str());
+                match timeline.record_status(index - 1, api.send(&status)) {
+                    Poll::Acknowledged => return Ok(()),
+                    Poll::Expired => previous = None,
+                    Poll::Waiting => {}
+                }
+            }
+
+            let receipt = receipt(api.send(&page(message, tier))?.receipt)?;
+            timeline.record_paged(index, tier, &receipt);
+
+            if let Some(superseded) = previous.take() {
+                let cancel = CancelEmergency::new(message.token.as_str(), superseded.as_str());
+                let cancelled = api.send(&cancel);
+                timeline.record_cancelled(index - 1, superseded, cancelled.map(drop));
+            }
+
+            let deadline = Instant::now() + tier.timeout;
+            previous = loop {
+                thread::sleep(self.next_poll(deadline));
+
+                let status = ReceiptStatus::new(message.token.as_str(), receipt.as_str());
+                match timeline.record_status(index, api.send(&status)) {
+                    Poll::Acknowledged => return Ok(()),
+                    Poll::Expired => break None,
+                    Poll::Waiting if Instant::now() >= deadline => break Some(receipt),
+                    Poll::Waiting => {}
+                }
+            };
+        }
+
+        timeline.events.push(EscalationEvent::Exhausted {
+            at: SystemTime::now(),
+        });
+
+        Ok(())
+    }
+
+    async fn escalate_async(
+        &self,
+        api: &API,
+        message: &SendMessage,
+        timeline: &mut Timeline,
+    ) -> Result<(), Error> {
+        self.validate(message)?;
+
+        // The previous tier's receipt, while its message is still repeating.
+        let mut previous: Option<String> = None;
+
+        for (index, tier) in self.tiers.iter().enumerate() {
+            if let Some(ref superseded) = previous {
+                let status = ReceiptStatus::new(message.token.as_str(), superseded.as_str());
+                match timeline.record_status(index - 1, api.send_async(&status).await) {
+                    Poll::Acknowledged => return Ok(()),
+                    Poll::Expired => previous = None,
+                    Poll::Waiting => {}
+                }
+            }
+
+            let receipt = receipt(api.send_async(&page(message, tier)).await?.receipt)?;
+            timeline.record_paged(index, tier, &receipt);
+
+            if let Some(superseded) = previous.take() {
+                let cancel = CancelEmergency::new(message.token.as_str(), superseded.as_str());
+                let cancelled = api.send_async(&cancel).await;
+                timeline.record_cancelled(index - 1, superseded, cancelled.map(drop));
+            }
+
+            let deadline = Instant::now() + tier.timeout;
+            previous = loop {
+                tokio::time::delay_for(self.next_poll(deadline)).await;
+
+                let status = ReceiptStatus::new(message.token.as_str(), receipt.as_str());
+                match timeline.record_status(index, api.send_async(&status).await) {
+                    Poll::Acknowledged => return Ok(()),
+                    Poll::Expired => break None,
+                    Poll::Waiting if Instant::now() >= deadline => break Some(receipt),
+                    Poll::Waiting => {}
+                }
+            };
+        }
+
+        timeline.events.push(EscalationEvent::Exhausted {
+            at: SystemTime::now(),
+        });
+
+        Ok(())
+    }
+
+    fn validate(&self, message: &SendMessage) -> Result<(), Error> {
+        let mut validator = Validator::new();
+
+        validator.check(
+            !self.tiers.is_empty(),
+            "tiers",
+            "escalation policy needs at least one tier",
+        );
+        validator.check(
+            message.priority == Some(Priority::Emergency),
+            "priority",
+            "escalation requires emergency priority",
+        );
+        validator.check(
+            self.poll_interval > Duration::from_secs(0),
+            "poll_interval",
+            "must be greater than zero",
+        );
+
+        validator.finish()
+    }
+
+    fn next_poll(&self, deadline: Instant) -> Duration {
+        self.poll_interval
+            .min(deadline.saturating_duration_since(Instant::now()))
+    }
+}
+
+impl Timeline {
+    fn record_paged(&mut self, tier: usize, escalation_tier: &EscalationTier, receipt: &str) {
+        self.events.push(EscalationEvent::Paged {
+            tier,
+            recipients: escalation_tier.recipients.clone(),
+            receipt: receipt.to_owned(),
+            at: SystemTime::now(),
+        });
+    }
+
+    fn record_cancelled(&mut self, tier: usize, receipt: String, result: Result<(), Error>) {
+        self.events.push(match result {
+            Ok(()) => EscalationEvent::Cancelled {
+                tier,
+                receipt,
+                at: SystemTime::now(),
+            },
+            Err(e) => EscalationEvent::CancelFailed {
+                tier,
+                receipt,
+                reason: reason(&e),
+                at: SystemTime::now(),
+            },
+        });
+    }
+
+    fn record_status(&mut self, tier: usize, status: Result<ReceiptStatusResponse, Error>) -> Poll {
+        let status = match status {
+            Ok(status) => status,
+            Err(_) => return Poll::Waiting,
+        };
+
+        if status.acknowledged == 1 {
+            self.events.push(EscalationEvent::Acknowledged {
+                tier,
+                by: status.acknowledged_by,
+                device: status.acknowledged_by_device,
+                at: status.acknowledged_at.unwrap_or_else(SystemTime::now),
+            });
+            Poll::Acknowledged
+        } else if status.expired == 1 {
+            self.events.push(EscalationEvent::Expired {
+                tier,
+                at: status.expires_at,
+            });
+            Poll::Expired
+        } else {
+            Poll::Waiting
+        }
+    }
+}
+
+fn page(message: &SendMessage, tier: &EscalationTier) -> SendMessage {
+    SendMessage {
+        recipients: tier.recipients.clone(),
+        ..message.clone()
+    }
+}
+
+fn receipt(receipt: Option<String>) -> Result<String, Error> {
+    receipt.ok_or_else(|| ErrorKind::Msg("emergency message sent without a receipt".into()).into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::UNIX_EPOCH;
+
+    fn status(acknowledged: u8, expired: u8) -> ReceiptStatusResponse {
+        ReceiptStatusResponse {
+            called_back: 0,
+            called_back_at: None,
+            acknowledged,
+            acknowledged_at: Some(UNIX_EPOCH + Duration::from_secs(100)),
+            acknowledged_by: String::from("user"),
+            acknowledged_by_device: String::from("phone"),
+            last_delivered_at: None,
+            expired,
+            expires_at: UNIX_EPOCH + Duration::from_secs(200),
+            request: String::from("request"),
+        }
+    }
+
+    #[test]
+    fn requires_tiers_and_emergency_priority() {
+        let message = SendMessage::new("token", "user", "message");
+
+        let policy = EscalationPolicy::new().poll_interval(Duration::from_secs(0));
+
+        match policy.validate(&message) {
+            Err(Error(ErrorKind::InvalidRequest(violations), _)) => {
+                let fields: Vec<_> = violations.iter().map(|v| v.field).collect();
+                assert_eq!(fields, ["tiers", "priority", "poll_interval"]);
+            }
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+
+    #[test]
+    fn records_acknowledgement() {
+        let mut timeline = Timeline::default();
+
+        assert!(matches!(
+            timeline.record_status(0, Ok(status(0, 0))),
+            Poll::Waiting
+        ));
+        assert!(matches!(
+            timeline.record_status(0, Err(ErrorKind::Msg("offline".into()).into())),
+            Poll::Waiting
+        ));
+        assert!(matches!(
+            timeline.record_status(1, Ok(status(1, 0))),
+            Poll::Acknowledged
+        ));
+        assert_eq!(timeline.acknowledged_by(), Some("user"));
+        assert_eq!(
+            timeline.events,
+            [EscalationEvent::Acknowledged {
+                tier: 1,
+                by: String::from("user"),
+                device: String::from("phone"),
+                at: UNIX_EPOCH + Duration::from_secs(100),
+            }]
+        );
+    }
+
+    #[test]
+    fn active_receipts_exclude_settled_tiers() {
+        let tier = |recipients: &str| EscalationTier {
+            recipients: Recipients::from(recipients),
+            timeout: Duration::from_secs(60),
+        };
+        let mut timeline = Timeline::default();
+
+        timeline.record_paged(0, &tier("one"), "receipt_one");
+        timeline.record_paged(1, &tier("two"), "receipt_two");
+        timeline.record_cancelled(0, String::from("receipt_one"), Ok(()));
+        timeline.record_paged(2, &tier("three"), "receipt_three");
+
+        assert_eq!(timeline.active_receipts(), ["receipt_two", "receipt_three"]);
+
+        timeline.record_status(1, Ok(status(0, 1)));
+        assert_eq!(timeline.active_receipts(), ["receipt_three"]);
+    }
+
+    #[test]
+    fn records_expiry() {
+        let mut timeline = Timeline::default();
+
+        assert!(matches!(
+            timeline.record_status(0, Ok(status(0, 1))),
+            Poll::Expired
+        ));
+        assert_eq!(timeline.acknowledged_by(), None);
+    }
+}
@@ -0,0 +1,256 @@
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+use crate::client::API;
+use crate::clock::{Clock, SystemClock};
+use crate::error::Error;
+use crate::requests::glance::Glance;
+
+/// Keeps a glance up to date without exceeding Pushover's glance rate limit.
+///
+/// Updates change the desired state, which is sent at most once per `min_interval` and only if it
+/// differs from what was last sent. Updates made in between are coalesced; call
+/// [tick](#method.tick) periodically to send them once the interval has passed, or
+/// [flush](#method.flush) to send them right away.
+///
+/// ```rust,no_run
+/// use std::time::Duration;
+/// use pushover::{GlanceUpdater, API};
+/// use pushover::requests::glance::Glance;
+///
+/// let glance = Glance::new("token", "uQiRzpo4DXghDmr9QzzfQu27cmVRsG");
+/// let updater = GlanceUpdater::new(API::new(), glance, Duration::from_secs(60));
+///
+/// for done in 0..=100 {
+///     updater
+///         .update(|glance| glance.set_percent(done))
+///         .expect("Error updating glance");
+/// }
+///
+/// updater.flush().expect("Error updating glance");
+/// ```
+pub struct GlanceUpdater {
+    api: API,
+    min_interval: Duration,
+    clock: Arc<dyn Clock>,
+    state: Mutex<State>,
+}
+
+struct State {
+    desired: Glance,
+    sent: Option<Glance>,
+    last_attempt: Option<SystemTime>,
+}
+
+impl GlanceUpdater {
+    /// `glance` names the user and device to update and holds the initial state, if any.
+    pub fn new(api: API, glance: Glance, min_interval: Duration) -> Self {
+        Self {
+            api,
+            min_interval,
+            clock: Arc::new(SystemClock),
+            state: Mutex::new(State {
+                desired: glance,
+                sent: None,
+                last_attempt: None,
+            }),
+        }
+    }
+
+    pub fn clock<C: Clock + 'static>(self, clock: C) -> Self {
+        self.shared_clock(Arc::new(clock))
+    }
+
+    pub(crate) fn shared_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    pub(crate) fn set_min_interval(&mut self, min_interval: Duration) {
+        self.min_interval = min_interval;
+    }
+
+    pub(crate) fn api(&self) -> &API {
+        &self.api
+    }
+
+    /// Changes the desired state with `change`, then sends it if it is due.
+    ///
+    /// The change is rejected, leaving the desired state as it was, if the result breaks
+    /// Pushover's limits. Returns the request id if an update was sent.
+    pub fn update<F>(&self, change: F) -> Result<Option<String>, Error>
+    where
+        F: FnOnce(&mut Glance),
+    {
+        self.change(change)?;
+        self.tick()
+    }
+
+    pub async fn update_async<F>(&self, change: F) -> Result<Option<String>, Error>
+    where
+        F: FnOnce(&mut Glance),
+    {
+        self.change(change)?;
+        self.tick_async().await
+    }
+
+    /// Sends the desired state if it changed and the minimum interval has passed.
+    pub fn tick(&self) -> Result<Option<String>, Error> {
+        match self.due(false) {
+            Some(glance) => {
+                let result = self.api.send(&glance);
+                self.sent(glance, result)
+            }
+            None => Ok(None),
+        }
+    }
+
+    pub async fn tick_async(&self) -> Result<Option<String>, Error> {
+        match self.due(false) {
+            Some(glance) => {
+                let result = self.api.send_async(&glance).await;
+                self.sent(glance, result)
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Sends the desired state if it changed, without waiting for the minimum interval.
+    pub fn flush(&self) -> Result<Option<String>, Error> {
+        match self.due(true) {
+            Some(glance) => {
+                let result = self.api.send(&glance);
+                self.sent(glance, result)
+            }
+            None => Ok(None),
+        }
+    }
+
+    pub async fn flush_async(&self) -> Result<Option<String>, Error> {
+        match self.due(true) {
+            Some(glance) => {
+                let result = self.api.send_async(&glance).await;
+                self.sent(glance, result)
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// The state updates are working towards.
+    pub fn desired(&self) -> Glance {
+        self.state.lock().unwrap().desired.clone()
+    }
+
+    /// Whether the desired state differs from what was last sent.
+    pub fn is_pending(&self) -> bool {
+        let state = self.state.lock().unwrap();
+        state.sent.as_ref() != Some(&state.desired)
+    }
+
+    pub(crate) fn change<F>(&self, change: F) -> Result<(), Error>
+    where
+        F: FnOnce(&mut Glance),
+    {
+        let mut state = self.state.lock().unwrap();
+        let mut desired = state.desired.clone();
+        change(&mut desired);
+        desired.validate()?;
+
+        state.desired = desired;
+        Ok(())
+    }
+
+    /// Returns the glance to send now, if any, counting it as an attempt.
+    fn due(&self, force: bool) -> Option<Glance> {
+        let now = self.clock.now();
+        let mut state = self.state.lock().unwrap();
+
+        if state.sent.as_ref() == Some(&state.desired) {
+            return None;
+        }
+        let waiting = state.last_attempt.is_some_and(|last| {
+            !now.duration_since(last)
+                .is_ok_and(|elapsed| elapsed >= self.min_interval)
+        });
+        if waiting && !force {
+            return None;
+        }
+
+        state.last_attempt = Some(now);
+        Some(state.desired.clone())
+    }
+
+    fn sent(&self, glance: Glance, result: Result<String, Error>) -> Result<Option<String>, Error> {
+        let request = result?;
+        self.state.lock().unwrap().sent = Some(glance);
+        Ok(Some(request))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::ManualClock;
+
+    const USER_KEY: &str = "uQiRzpo4DXghDmr9QzzfQu27cmVRsG";
+
+    fn updater(clock: &ManualClock) -> GlanceUpdater {
+        GlanceUpdater::new(
+            API::new(),
+            Glance::new("token", USER_KEY),
+            Duration::from_secs(60),
+        )
+        .clock(clock.clone())
+    }
+
+    #[test]
+    fn coalesces_updates_within_interval() {
+        let clock = ManualClock::new(SystemTime::UNIX_EPOCH);
+        let updater = updater(&clock);
+
+        updater.change(|glance| glance.set_percent(10)).unwrap();
+        let first = updater.due(false).unwrap();
+        updater.state.lock().unwrap().sent = Some(first);
+
+        updater.change(|glance| glance.set_percent(20)).unwrap();
+        updater.change(|glance| glance.set_percent(30)).unwrap();
+        assert!(updater.due(false).is_none());
+        assert!(updater.is_pending());
+
+        clock.advance(Duration::from_secs(60));
+        assert_eq!(updater.due(false).unwrap().percent, Some(30));
+    }
+
+    #[test]
+    fn skips_unchanged_state() {
+        let clock = ManualClock::new(SystemTime::UNIX_EPOCH);
+        let updater = updater(&clock);
+
+        updater
+            .change(|glance| glance.set_text("deploying"))
+            .unwrap();
+        let sent = updater.desired();
+        updater.state.lock().unwrap().sent = Some(sent);
+        updater
+            .change(|glance| glance.set_text("deploying"))
+            .unwrap();
+
+        assert!(!updater.is_pending());
+        assert!(updater.due(true).is_none());
+    }
+
+    #[test]
+    fn rejects_invalid_changes() {
+        let clock = ManualClock::new(SystemTime::UNIX_EPOCH);
+        let updater = updater(&clock);
+
+        updater.change(|glance| glance.set_percent(50)).unwrap();
+
+        assert!(updater.change(|glance| glance.set_percent(101)).is_err());
+        assert!(updater
+            .change(|glance| glance.set_title("a".repeat(101)))
+            .is_err());
+        assert_eq!(updater.desired().percent, Some(50));
+        assert!(updater.desired().title.is_none());
+    }
+}
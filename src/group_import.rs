@@ -0,0 +1,383 @@
+use std::io::Read;
+
+use crate::client::API;
+use crate::csv;
+use crate::error::{reason, Error, ErrorKind};
+use crate::requests::groups::{AddUser, GroupExport, ToggleUser, CSV_HEADER};
+use crate::requests::verification::Verification;
+use crate::types::User;
+
+/// Adds the users of an exported group to a group, verifying each of them first.
+///
+/// Reads the CSV and JSON formats written by
+/// [ListUsersResponse::write_csv](requests/groups/struct.ListUsersResponse.html#method.write_csv)
+/// and [write_json](requests/groups/struct.ListUsersResponse.html#method.write_json). Every user
+/// key, and device if one is given, is checked with
+/// [Verification](requests/verification/struct.Verification.html) before it is added; users that
+/// can't be read, verified or added are reported as rejected rather than stopping the import.
+/// Disabled users are disabled once added; if that fails they are reported as added but still
+/// enabled.
+///
+/// ```rust,no_run
+/// use std::fs::File;
+/// use pushover::{GroupImport, API};
+///
+/// let import = GroupImport::new("token", "group_key");
+/// let report = import
+///     .import_csv(&API::new(), File::open("ops.csv").unwrap())
+///     .expect("Error reading export");
+///
+/// for row in &report.rejected {
+///     println!("row {}: {}", row.row, row.reason);
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct GroupImport {
+    token: String,
+    group_key: String,
+}
+
+/// A user read from an export, with its row number.
+type Row = (usize, User);
+
+/// Outcome of an import.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ImportReport {
+    pub added: Vec<User>,
+    /// Added users that should have been disabled but are still enabled.
+    pub not_disabled: Vec<NotDisabled>,
+    pub rejected: Vec<RejectedRow>,
+}
+
+/// A row that was not imported. Rows are numbered by line from 1, the line after the CSV header,
+/// counting blank lines.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RejectedRow {
+    pub row: usize,
+    pub user: Option<User>,
+    pub reason: String,
+}
+
+/// A user that was added to the group, but could not be disabled afterwards.
+#[derive(Clone, Debug, PartialEq)]
+pub struct NotDisabled {
+    pub row: usize,
+    pub user: User,
+    pub reason: String,
+}
+
+impl GroupImport {
+    pub fn new<G, T>(token: T, group_key: G) -> Self
+    where
+        G: Into<String>,
+        T: Into<String>,
+    {
+        Self {
+            token: token.into(),
+            group_key: group_key.into(),
+        }
+    }
+
+    /// Imports a CSV export. Fails only if the export can't be read or has no `user` column.
+    pub fn import_csv<R: Read>(&self, api: &API, reader: R) -> Result<ImportReport, Error> {
+        let (rows, rejected) = read_csv(reader)?;
+        Ok(self.import_rows(api, rows, rejected))
+    }
+
+    /// Imports a JSON export. Fails only if the export can't be read.
+    pub fn import_json<R: Read>(&self, api: &API, reader: R) -> Result<ImportReport, Error> {
+        let export: GroupExport = serde_json::from_reader(reader)?;
+        Ok(self.import_users(api, export.users.into_owned()))
+    }
+
+    pub fn import_users(&self, api: &API, users: Vec<User>) -> ImportReport {
+        self.import_rows(api, numbered(users), Vec::new())
+    }
+
+    pub async fn import_csv_async<R: Read>(
+        &self,
+        api: &API,
+        reader: R,
+    ) -> Result<ImportReport, Error> {
+        let (rows, rejected) = read_csv(reader)?;
+        Ok(self.import_rows_async(api, rows, rejected).await)
+    }
+
+    pub async fn import_json_async<R: Read>(
+        &self,
+        api: &API,
+        reader: R,
+    ) -> Result<ImportReport, Error> {
+        let export: GroupExport = serde_json::from_reader(reader)?;
+        Ok(self
+            .import_users_async(api, export.users.into_owned())
+            .await)
+    }
+
+    pub async fn import_users_async(&self, api: &API, users: Vec<User>) -> ImportReport {
+        self.import_rows_async(api, numbered(users), Vec::new())
+            .await
+    }
+
+    fn import_rows(&self, api: &API, rows: Vec<Row>, rejected: Vec<RejectedRow>) -> ImportReport {
+        let mut report = ImportReport {
+            rejected,
+            ..Default::default()
+        };
+
+        for (row, user) in rows {
+            let added = self.add(&user).validate().and_then(|_| {
+                api.send(&self.verification(&user))?;
+                api.send(&self.add(&user)).map(drop)
+            });
+            let disabled = match added {
+                Ok(()) if user.disabled => api.send(&self.disable(&user)).map(drop),
+                _ => Ok(()),
+            };
+
+            report.record(row, user, added, disabled);
+        }
+
+        report.sort();
+        report
+    }
+
+    async fn import_rows_async(
+        &self,
+        api: &API,
+        rows: Vec<Row>,
+        rejected: Vec<RejectedRow>,
+    ) -> ImportReport {
+        let mut report = ImportReport {
+            rejected,
+            ..Default::default()
+        };
+
+        for (row, user) in rows {
+            let added: Result<(), Error> = async {
+                self.add(&user).validate()?;
+                api.send_async(&self.verification(&user)).await?;
+                api.send_async(&self.add(&user)).await.map(drop)
+            }
+            .await;
+            let disabled = match added {
+                Ok(()) if user.disabled => api.send_async(&self.disable(&user)).await.map(drop),
+                _ => Ok(()),
+            };
+
+            report.record(row, user, added, disabled);
+        }
+
+        report.sort();
+        report
+    }
+
+    fn verification(&self, user: &User) -> Verification {
+        let mut verification = Verification::new(self.token.as_str(), user.user.as_str());
+        if let Some(ref device) = user.device {
+            verification.set_device(device.as_str());
+        }
+        verification
+    }
+
+    fn add(&self, user: &User) -> AddUser {
+        AddUser::new(self.token.as_str(), self.group_key.as_str(), user)
+    }
+
+    fn disable(&self, user: &User) -> ToggleUser {
+        let mut disable = ToggleUser::new(
+            self.token.as_str(),
+            self.group_key.as_str(),
+            user.user.as_str(),
+            false,
+        );
+        if let Some(ref device) = user.device {
+            disable.set_device(device.as_str());
+        }
+        disable
+    }
+}
+
+impl ImportReport {
+    fn record(
+        &mut self,
+        row: usize,
+        user: User,
+        added: Result<(), Error>,
+        disabled: Result<(), Error>,
+    ) {
+        match (added, disabled) {
+            (Err(e), _) => self.rejected.push(RejectedRow {
+                row,
+                user: Some(user),
+                reason: reason(&e),
+            }),
+            (Ok(()), Err(e)) => {
+                self.added.push(user.clone());
+                self.not_disabled.push(NotDisabled {
+                    row,
+                    user,
+                    reason: reason(&e),
+                });
+            }
+            (Ok(()), Ok(())) => self.added.push(user),
+        }
+    }
+
+    fn sort(&mut self) {
+        self.rejected.sort_by_key(|rejected| rejected.row);
+    }
+}
+
+fn numbered(users: Vec<User>) -> Vec<Row> {
+    users
+        .into_iter()
+        .enumerate()
+        .map(|(index, user)| (index + 1, user))
+        .collect()
+}
+
+/// Reads the users of a CSV export, along with the rows that aren't valid users.
+fn read_csv<R: Read>(mut reader: R) -> Result<(Vec<Row>, Vec<RejectedRow>), Error> {
+    let mut input = String::new();
+    reader.read_to_string(&mut input)?;
+
+    let rows = csv::parse(&input).map_err(ErrorKind::Msg)?;
+    let mut rows = rows.into_iter();
+    let (header_line, header) = rows.next().unwrap_or_default();
+    let column = |name: &str| header.iter().position(|column| column.trim() == name);
+
+    let user_column = column(CSV_HEADER[1])
+        .ok_or_else(|| ErrorKind::Msg(String::from("group export has no 'user' column")))?;
+    let device_column = column(CSV_HEADER[2]);
+    let memo_column = column(CSV_HEADER[3]);
+    let disabled_column = column(CSV_HEADER[4]);
+
+    let mut users = Vec::new();
+    let mut rejected = Vec::new();
+
+    for (line, fields) in rows {
+        let field = |column: Option<usize>| {
+            column
+                .and_then(|column| fields.get(column))
+                .map(|field| field.trim())
+                .filter(|field| !field.is_empty())
+        };
+        let row = line - header_line;
+
+        let mut user = match field(Some(user_column)) {
+            Some(key) => User::new(key),
+            None => {
+                rejected.push(RejectedRow {
+                    row,
+                    user: None,
+                    reason: String::from("missing user key"),
+                });
+                continue;
+            }
+        };
+        if let Some(device) = field(device_column) {
+            user.set_device(device);
+        }
+        if let Some(memo) = field(memo_column) {
+            user.set_memo(memo);
+        }
+
+        match field(disabled_column).map(str::to_lowercase).as_deref() {
+            None | Some("false") | Some("0") | Some("no") => {}
+            Some("true") | Some("1") | Some("yes") => user.disabled = true,
+            Some(other) => {
+                rejected.push(RejectedRow {
+                    row,
+                    reason: format!("invalid disabled value '{}'", other),
+                    user: Some(user),
+                });
+                continue;
+            }
+        }
+
+        users.push((row, user));
+    }
+
+    Ok((users, rejected))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_csv_rows() {
+        let input = "group,user,device,memo,disabled\r\n\
+                     ops,uQiRzpo4DXghDmr9QzzfQu27cmVRsG,phone,\"Alice, on call\",false\r\n\
+                     ops,,,,\r\n\
+                     ops,gznej3rKEVAvPUxu9vvNnqpmZpokzF,,,maybe\r\n\
+                     ops,gznej3rKEVAvPUxu9vvNnqpmZpokzF,,,TRUE\r\n";
+
+        let (users, rejected) = read_csv(input.as_bytes()).unwrap();
+
+        assert_eq!(users.len(), 2);
+        assert_eq!(users[0].0, 1);
+        assert_eq!(users[0].1.device.as_deref(), Some("phone"));
+        assert_eq!(users[0].1.memo.as_deref(), Some("Alice, on call"));
+        assert!(users[1].1.disabled);
+        assert_eq!(rejected.iter().map(|r| r.row).collect::<Vec<_>>(), [2, 3]);
+        assert_eq!(rejected[1].reason, "invalid disabled value 'maybe'");
+    }
+
+    #[test]
+    fn counts_blank_lines_in_row_numbers() {
+        let input = "group,user,device,memo,disabled\n\
+                     ops,uQiRzpo4DXghDmr9QzzfQu27cmVRsG,,,false\n\
+                     \n\
+                     ops,gznej3rKEVAvPUxu9vvNnqpmZpokzF,,,maybe\n";
+
+        let (users, rejected) = read_csv(input.as_bytes()).unwrap();
+
+        assert_eq!(users[0].0, 1);
+        assert_eq!(rejected.len(), 1);
+        assert_eq!(rejected[0].row, 3);
+    }
+
+    #[test]
+    fn reports_users_left_enabled() {
+        let mut user = User::new("uQiRzpo4DXghDmr9QzzfQu27cmVRsG");
+        user.disabled = true;
+        let mut report = ImportReport::default();
+
+        report.record(
+            4,
+            user.clone(),
+            Ok(()),
+            Err(ErrorKind::Msg(String::from("offline")).into()),
+        );
+
+        assert_eq!(report.added, [user.clone()]);
+        assert!(report.rejected.is_empty());
+        assert_eq!(
+            report.not_disabled,
+            [NotDisabled {
+                row: 4,
+                user,
+                reason: String::from("offline"),
+            }]
+        );
+    }
+
+    #[test]
+    fn requires_user_column() {
+        assert!(read_csv("name,memo\nops,memo\n".as_bytes()).is_err());
+    }
+
+    #[test]
+    fn rejects_invalid_keys_without_calling_api() {
+        let report = GroupImport::new("token", "gznej3rKEVAvPUxu9vvNnqpmZpokzF").import_users(
+            &API::new().base_url("http://127.0.0.1:9"),
+            vec![User::new("short")],
+        );
+
+        assert!(report.added.is_empty());
+        assert_eq!(report.rejected[0].row, 1);
+        assert!(report.rejected[0].reason.contains("user"));
+    }
+}
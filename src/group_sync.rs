@@ -0,0 +1,456 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::client::API;
+use crate::error::Error;
+use crate::requests::groups::{AddUser, ListUsers, RemoveUser, ToggleUser};
+use crate::types::User;
+
+/// Brings a group's members in line with a desired list of users.
+///
+/// Members are matched by user key and device, so a user in the group with several devices is
+/// synced one membership at a time. Pushover can't edit a member's memo, so memo changes are
+/// applied by removing and re-adding the membership, putting the original back if re-adding
+/// fails. Members are added before others are removed, so a failed sync leaves the group with
+/// extra members rather than missing ones.
+///
+/// ```rust,no_run
+/// use pushover::{GroupSync, User, API};
+///
+/// let mut on_call = User::new("uQiRzpo4DXghDmr9QzzfQu27cmVRsG");
+/// on_call.set_memo("Alice");
+///
+/// let sync = GroupSync::new("token", "group_key", vec![on_call]).dry_run(true);
+/// let report = sync.sync(&API::new()).expect("Error listing group");
+///
+/// print!("{}", report.plan);
+/// ```
+#[derive(Clone, Debug)]
+pub struct GroupSync {
+    token: String,
+    group_key: String,
+    desired: Vec<User>,
+    dry_run: bool,
+}
+
+/// One change to a group's membership.
+#[derive(Clone, Debug, PartialEq)]
+pub enum GroupChange {
+    Add(User),
+    Remove(User),
+    /// Replace a member whose memo differs.
+    Update {
+        from: User,
+        to: User,
+    },
+    Enable(User),
+    Disable(User),
+}
+
+/// The changes needed to sync a group, in the order they are applied.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct GroupPlan {
+    pub changes: Vec<GroupChange>,
+}
+
+/// Outcome of applying one change.
+#[derive(Debug)]
+pub struct ChangeResult {
+    pub change: GroupChange,
+    pub result: Result<(), Error>,
+}
+
+/// Plan of a sync and, unless it was a dry run, the outcome of each change.
+#[derive(Debug)]
+pub struct SyncReport {
+    pub plan: GroupPlan,
+    pub results: Vec<ChangeResult>,
+}
+
+impl GroupSync {
+    pub fn new<G, T>(token: T, group_key: G, desired: Vec<User>) -> Self
+    where
+        G: Into<String>,
+        T: Into<String>,
+    {
+        Self {
+            token: token.into(),
+            group_key: group_key.into(),
+            desired,
+            dry_run: false,
+        }
+    }
+
+    /// Only plan the changes when syncing, without applying them.
+    pub fn dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    /// Lists the group's members and plans the changes needed.
+    pub fn plan(&self, api: &API) -> Result<GroupPlan, Error> {
+        let list = ListUsers::new(self.token.as_str(), self.group_key.as_str());
+        let current = api.send(&list)?;
+        Ok(self.plan_against(&current.users))
+    }
+
+    pub async fn plan_async(&self, api: &API) -> Result<GroupPlan, Error> {
+        let list = ListUsers::new(self.token.as_str(), self.group_key.as_str());
+        let current = api.send_async(&list).await?;
+        Ok(self.plan_against(&current.users))
+    }
+
+    /// Plans the changes to turn `current` into the desired members.
+    pub fn plan_against(&self, current: &[User]) -> GroupPlan {
+        let existing: HashMap<Membership, &User> = current
+            .iter()
+            .map(|user| (membership(user), user))
+            .collect();
+        let desired: HashMap<Membership, &User> = self
+            .desired
+            .iter()
+            .map(|user| (membership(user), user))
+            .collect();
+
+        let mut removes = Vec::new();
+        let mut updates = Vec::new();
+        let mut adds = Vec::new();
+        let mut toggles = Vec::new();
+
+        for user in current {
+            if !desired.contains_key(&membership(user)) {
+                removes.push(GroupChange::Remove(user.clone()));
+            }
+        }
+
+        for (index, user) in self.desired.iter().enumerate() {
+            // Later duplicates of a membership are ignored.
+            if self.desired[..index]
+                .iter()
+                .any(|other| membership(other) == membership(user))
+            {
+                continue;
+            }
+
+            let enabled = match existing.get(&membership(user)) {
+                None => {
+                    adds.push(GroupChange::Add(user.clone()));
+                    true
+                }
+                Some(current) if current.memo != user.memo => {
+                    updates.push(GroupChange::Update {
+                        from: (*current).clone(),
+                        to: user.clone(),
+                    });
+                    true
+                }
+                Some(current) => !current.disabled,
+            };
+
+            match (enabled, user.disabled) {
+                (true, true) => toggles.push(GroupChange::Disable(user.clone())),
+                (false, false) => toggles.push(GroupChange::Enable(user.clone())),
+                _ => {}
+            }
+        }
+
+        let mut changes = adds;
+        changes.extend(updates);
+        changes.extend(toggles);
+        changes.extend(removes);
+
+        GroupPlan { changes }
+    }
+
+    /// Applies every change of `plan`, carrying on past failures.
+    pub fn apply(&self, api: &API, plan: &GroupPlan) -> Vec<ChangeResult> {
+        plan.changes
+            .iter()
+            .map(|change| {
+                let result = match change {
+                    GroupChange::Add(user) => api.send(&self.add(user)).map(drop),
+                    GroupChange::Remove(user) => api.send(&self.remove(user)).map(drop),
+                    GroupChange::Update { from, to } => self.update(api, from, to),
+                    GroupChange::Enable(user) => api.send(&self.toggle(user, true)).map(drop),
+                    GroupChange::Disable(user) => api.send(&self.toggle(user, false)).map(drop),
+                };
+
+                ChangeResult {
+                    change: change.clone(),
+                    result,
+                }
+            })
+            .collect()
+    }
+
+    pub async fn apply_async(&self, api: &API, plan: &GroupPlan) -> Vec<ChangeResult> {
+        let mut results = Vec::with_capacity(plan.changes.len());
+
+        for change in &plan.changes {
+            let result = match change {
+                GroupChange::Add(user) => api.send_async(&self.add(user)).await.map(drop),
+                GroupChange::Remove(user) => api.send_async(&self.remove(user)).await.map(drop),
+                GroupChange::Update { from, to } => self.update_async(api, from, to).await,
+                GroupChange::Enable(user) => {
+                    api.send_async(&self.toggle(user, true)).await.map(drop)
+                }
+                GroupChange::Disable(user) => {
+                    api.send_async(&self.toggle(user, false)).await.map(drop)
+                }
+            };
+
+            results.push(ChangeResult {
+                change: change.clone(),
+                result,
+            });
+        }
+
+        results
+    }
+
+    /// Plans the changes and applies them, unless this is a dry run.
+    pub fn sync(&self, api: &API) -> Result<SyncReport, Error> {
+        let plan = self.plan(api)?;
+        let results = if self.dry_run {
+            Vec::new()
+        } else {
+            self.apply(api, &plan)
+        };
+
+        Ok(SyncReport { plan, results })
+    }
+
+    pub async fn sync_async(&self, api: &API) -> Result<SyncReport, Error> {
+        let plan = self.plan_async(api).await?;
+        let results = if self.dry_run {
+            Vec::new()
+        } else {
+            self.apply_async(api, &plan).await
+        };
+
+        Ok(SyncReport { plan, results })
+    }
+
+    /// Replaces `from` with `to`, putting `from` back if `to` can't be added.
+    fn update(&self, api: &API, from: &User, to: &User) -> Result<(), Error> {
+        api.send(&self.remove(from))?;
+
+        let added = api.send(&self.add(to));
+        if let Err(e) = added {
+            let restored = api.send(&self.add(from)).and_then(|_| {
+                if from.disabled {
+                    api.send(&self.toggle(from, false))?;
+                }
+                Ok(())
+            });
+            return Err(not_updated(e, restored));
+        }
+
+        Ok(())
+    }
+
+    async fn update_async(&self, api: &API, from: &User, to: &User) -> Result<(), Error> {
+        api.send_async(&self.remove(from)).await?;
+
+        let added = api.send_async(&self.add(to)).await;
+        if let Err(e) = added {
+            let mut restored = api.send_async(&self.add(from)).await.map(drop);
+            if restored.is_ok() && from.disabled {
+                restored = api.send_async(&self.toggle(from, false)).await.map(drop);
+            }
+            return Err(not_updated(e, restored));
+        }
+
+        Ok(())
+    }
+
+    fn add(&self, user: &User) -> AddUser {
+        AddUser::new(self.token.as_str(), self.group_key.as_str(), user)
+    }
+
+    fn remove(&self, user: &User) -> RemoveUser {
+        let mut remove = RemoveUser::new(
+            self.token.as_str(),
+            self.group_key.as_str(),
+            user.user.as_str(),
+        );
+        if let Some(ref device) = user.device {
+            remove.set_device(device.as_str());
+        }
+        remove
+    }
+
+    fn toggle(&self, user: &User, enable: bool) -> ToggleUser {
+        let mut toggle = ToggleUser::new(
+            self.token.as_str(),
+            self.group_key.as_str(),
+            user.user.as_str(),
+            enable,
+        );
+        if let Some(ref device) = user.device {
+            toggle.set_device(device.as_str());
+        }
+        toggle
+    }
+}
+
+impl GroupPlan {
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+}
+
+impl SyncReport {
+    /// Whether every planned change was applied.
+    pub fn is_success(&self) -> bool {
+        self.results.len() == self.plan.changes.len()
+            && self.results.iter().all(|result| result.result.is_ok())
+    }
+}
+
+/// One change per line, e.g. `+ uQiRzpo4DXghDmr9QzzfQu27cmVRsG (device: phone, memo: Alice)`.
+impl fmt::Display for GroupPlan {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for change in &self.changes {
+            match change {
+                GroupChange::Add(user) => writeln!(f, "+ {}", describe(user))?,
+                GroupChange::Remove(user) => writeln!(f, "- {}", describe(user))?,
+                GroupChange::Update { from, to } => {
+                    writeln!(f, "~ {} -> {}", describe(from), describe(to))?
+                }
+                GroupChange::Enable(user) => writeln!(f, "enable {}", describe(user))?,
+                GroupChange::Disable(user) => writeln!(f, "disable {}", describe(user))?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// The error for an update whose add failed, noting if the original member couldn't be restored
+/// either.
+fn not_updated(error: Error, restored: Result<(), Error>) -> Error {
+    match restored {
+        Ok(()) => error,
+        Err(restore) => Error::with_chain(
+            error,
+            format!("member was removed and could not be restored: {}", restore),
+        ),
+    }
+}
+
+/// A user's membership of a group: their key and device.
+type Membership<'a> = (&'a str, Option<&'a str>);
+
+fn membership(user: &User) -> Membership<'_> {
+    (user.user.as_str(), user.device.as_deref())
+}
+
+fn describe(user: &User) -> String {
+    let details: Vec<String> = user
+        .device
+        .iter()
+        .map(|device| format!("device: {}", device))
+        .chain(user.memo.iter().map(|memo| format!("memo: {}", memo)))
+        .collect();
+
+    if details.is_empty() {
+        user.user.clone()
+    } else {
+        format!("{} ({})", user.user, details.join(", "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn user(key: &str, memo: Option<&str>, disabled: bool) -> User {
+        let mut user = User::new(key);
+        if let Some(memo) = memo {
+            user.set_memo(memo);
+        }
+        user.disabled = disabled;
+        user
+    }
+
+    #[test]
+    fn plans_every_kind_of_change() {
+        let current = vec![
+            user("kept", None, false),
+            user("removed", None, false),
+            user("renamed", Some("old"), false),
+            user("enabled", None, true),
+            user("disabled", None, false),
+        ];
+        let desired = vec![
+            user("kept", None, false),
+            user("renamed", Some("new"), true),
+            user("enabled", None, false),
+            user("disabled", None, true),
+            user("added", None, false),
+        ];
+
+        let plan = GroupSync::new("token", "group", desired).plan_against(&current);
+
+        assert_eq!(
+            plan.changes,
+            [
+                GroupChange::Add(user("added", None, false)),
+                GroupChange::Update {
+                    from: user("renamed", Some("old"), false),
+                    to: user("renamed", Some("new"), true),
+                },
+                GroupChange::Disable(user("renamed", Some("new"), true)),
+                GroupChange::Enable(user("enabled", None, false)),
+                GroupChange::Disable(user("disabled", None, true)),
+                GroupChange::Remove(user("removed", None, false)),
+            ]
+        );
+    }
+
+    #[test]
+    fn matches_memberships_by_device() {
+        let on = |device: &str, memo: &str| {
+            let mut user = user("alice", Some(memo), false);
+            user.set_device(device);
+            user
+        };
+        let current = vec![on("phone", "Alice"), on("laptop", "Alice")];
+        let desired = vec![on("phone", "Alice"), on("tablet", "Alice")];
+
+        let plan = GroupSync::new("token", "group", desired).plan_against(&current);
+
+        assert_eq!(
+            plan.changes,
+            [
+                GroupChange::Add(on("tablet", "Alice")),
+                GroupChange::Remove(on("laptop", "Alice")),
+            ]
+        );
+        let remove = GroupSync::new("token", "group", vec![]).remove(&on("laptop", "Alice"));
+        assert_eq!(remove.device.as_deref(), Some("laptop"));
+    }
+
+    #[test]
+    fn nothing_to_do_when_in_sync() {
+        let users = vec![user("a", Some("memo"), false), user("b", None, true)];
+
+        let plan = GroupSync::new("token", "group", users.clone()).plan_against(&users);
+
+        assert!(plan.is_empty());
+    }
+
+    #[test]
+    fn displays_plan() {
+        let plan = GroupPlan {
+            changes: vec![
+                GroupChange::Add(user("a", Some("Alice"), false)),
+                GroupChange::Remove(user("b", None, false)),
+                GroupChange::Disable(user("c", None, true)),
+            ],
+        };
+
+        assert_eq!(plan.to_string(), "+ a (memo: Alice)\n- b\ndisable c\n");
+    }
+}
@@ -0,0 +1,75 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::error::Error;
+
+/// Reads the records of the JSON lines log at `path`.
+///
+/// A crash can leave the last line half written, so a final line without a newline is skipped.
+/// Any other line that can't be read is an error, rather than losing the records after it.
+pub(crate) fn read<T: DeserializeOwned>(path: &Path) -> Result<Vec<T>, Error> {
+    let log = fs::read_to_string(path)?;
+    let complete = match log.rfind('\n') {
+        Some(end) => &log[..=end],
+        None => "",
+    };
+
+    complete
+        .lines()
+        .map(|line| serde_json::from_str(line).map_err(From::from))
+        .collect()
+}
+
+/// Durably appends `record` to a log.
+pub(crate) fn append<T: Serialize>(file: &mut File, record: &T) -> Result<(), Error> {
+    let mut line = serde_json::to_string(record)?;
+    line.push('\n');
+
+    file.write_all(line.as_bytes())?;
+    file.sync_data()?;
+
+    Ok(())
+}
+
+/// Writes `records` to a fresh log and atomically replaces the one at `path` with it, so a crash
+/// leaves either the old log or the new one. Returns the new log, opened for appending.
+pub(crate) fn rewrite<I, T>(path: &Path, records: I) -> Result<File, Error>
+where
+    I: IntoIterator<Item = T>,
+    T: Serialize,
+{
+    let mut temporary = path.as_os_str().to_owned();
+    temporary.push(".tmp");
+    let temporary = PathBuf::from(temporary);
+
+    let mut file = create_private(&temporary)?;
+    for record in records {
+        let mut line = serde_json::to_string(&record)?;
+        line.push('\n');
+        file.write_all(line.as_bytes())?;
+    }
+    file.sync_data()?;
+
+    fs::rename(&temporary, path)?;
+
+    Ok(OpenOptions::new().append(true).open(path)?)
+}
+
+/// Creates or truncates a file only its owner may read, as the files written hold credentials.
+pub(crate) fn create_private(path: &Path) -> Result<File, Error> {
+    let mut options = OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+
+        options.mode(0o600);
+    }
+
+    Ok(options.open(path)?)
+}
@@ -45,15 +45,62 @@
 //! }
 //! ```
 
+#[macro_use]
+mod redact;
+
+mod background;
+mod bulk_license;
+mod bulk_verify;
 mod client;
+mod clock;
+mod csv;
+mod dedup;
 mod deserializers;
+mod digest;
 mod error;
+mod escalation;
+mod glance_updater;
+mod group_import;
+mod group_sync;
+mod jsonl;
+mod open_client_session;
+mod outbox;
+mod progress;
+mod rate_limit;
 pub mod requests;
+mod router;
+mod schedule;
+mod seen_store;
 mod types;
+mod validation;
 
+pub use self::background::{BackgroundSender, OverflowPolicy};
+pub use self::bulk_license::{BulkLicense, LicenseOutcome, LicenseReport, LicenseResult};
+pub use self::bulk_verify::{BulkVerifier, KeyReport, KeyStatus, VerificationReport};
 pub use self::client::API;
+pub use self::clock::{Clock, ManualClock, SystemClock};
+pub use self::dedup::Deduplicator;
+pub use self::digest::Digest;
 pub use self::error::{Error, ErrorKind};
-pub use self::types::{OperatingSystem, Priority, Sound, User, UserType};
+pub use self::escalation::{
+    EscalationEvent, EscalationFailure, EscalationPolicy, EscalationTier, Timeline,
+};
+pub use self::glance_updater::GlanceUpdater;
+pub use self::group_import::{GroupImport, ImportReport, NotDisabled, RejectedRow};
+pub use self::group_sync::{ChangeResult, GroupChange, GroupPlan, GroupSync, SyncReport};
+pub use self::open_client_session::{Credentials, OpenClientSession, ProcessReport};
+pub use self::outbox::{FailedEntry, Outbox, OutboxWorker};
+pub use self::progress::{Progress, ProgressIter};
+pub use self::rate_limit::{Quota, RateLimitMode, RateLimiter};
+pub use self::redact::{SanitizedRequest, TransportError, Unredacted, REDACTED};
+pub use self::router::{Action, ActionResult, Forward, Router, Rule};
+pub use self::schedule::{Layer, Override, Rotation, Schedule};
+pub use self::seen_store::SeenStore;
+pub use self::types::{
+    DeviceOs, EmergencyOptions, EmergencyOptionsBuilder, Message, OperatingSystem, Priority,
+    Recipients, Sound, TimestampExt, User, UserType,
+};
+pub use self::validation::Violation;
 
 #[cfg(test)]
 mod test {
@@ -0,0 +1,419 @@
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::client::API;
+use crate::error::{Error, ErrorKind};
+use crate::jsonl::create_private;
+use crate::requests::open_client::{DeleteMessages, DownloadMessages, Login, RegisterDevice};
+use crate::seen_store::SeenStore;
+use crate::types::Message;
+use crate::validation::MAX_DEVICE_NAME_LENGTH;
+
+/// How many alternative names to try when a device name is taken.
+const MAX_RENAMES: usize = 9;
+
+/// An Open Client device whose credentials are kept in a file.
+///
+/// [open](#method.open) reuses the device saved in the file if there is one. Otherwise it logs
+/// in, registers a device and saves its credentials, readable only by their owner. If the device
+/// name is already taken, `-2`, `-3` and so on are appended to it until one is free.
+///
+/// Use [process](#method.process) rather than [fetch_new](#method.fetch_new) when messages must
+/// not be lost if the consumer stops before it has handled them.
+///
+/// ```rust,no_run
+/// use pushover::{OpenClientSession, API};
+/// use pushover::requests::open_client::Login;
+///
+/// let login = Login::new("email@example.com", "password");
+/// let session = OpenClientSession::open(API::new(), "pushover.json", &login, "alerts-bot")
+///     .expect("Error opening session");
+///
+/// for message in session.fetch_new().expect("Error fetching messages") {
+///     println!("{}", message.message);
+/// }
+/// ```
+pub struct OpenClientSession {
+    api: API,
+    path: PathBuf,
+    credentials: Credentials,
+}
+
+/// What an Open Client device needs to download its messages.
+#[derive(Clone, Deserialize, PartialEq, Serialize)]
+pub struct Credentials {
+    pub user_id: String,
+    pub secret: String,
+    pub device_id: String,
+    pub device_name: String,
+}
+
+/// Outcome of [process](struct.OpenClientSession.html#method.process).
+#[derive(Debug)]
+pub struct ProcessReport<E> {
+    /// Messages passed to the handler successfully.
+    pub handled: usize,
+    /// Messages skipped because they were handled before.
+    pub duplicates: usize,
+    /// Highest message id deleted from Pushover, if any.
+    pub deleted_through: Option<u32>,
+    /// The message the handler failed on, with its error. It and the messages after it are
+    /// downloaded again next time.
+    pub failed: Option<(Message, E)>,
+}
+
+debug_redacted!(Credentials {
+    user_id,
+    secret: secret,
+    device_id,
+    device_name,
+});
+
+impl OpenClientSession {
+    /// Reuses the device saved at `path`, or logs in and registers one named `device_name`.
+    pub fn open<P, N>(api: API, path: P, login: &Login, device_name: N) -> Result<Self, Error>
+    where
+        P: Into<PathBuf>,
+        N: Into<String>,
+    {
+        let path = path.into();
+        if path.exists() {
+            return Self::load(api, path);
+        }
+
+        let account = api.send(login)?;
+        let device_name = device_name.into();
+        let mut attempt = 0;
+
+        let device = loop {
+            let name = candidate(&device_name, attempt);
+            match api.send(&RegisterDevice::new(account.secret.as_str(), name.as_str())) {
+                Ok(device) => break (device.id, name),
+                Err(ref e) if is_name_taken(e) && attempt < MAX_RENAMES => attempt += 1,
+                Err(e) => return Err(e),
+            }
+        };
+
+        Self::save(api, path, account.id, account.secret, device)
+    }
+
+    pub async fn open_async<P, N>(
+        api: API,
+        path: P,
+        login: &Login,
+        device_name: N,
+    ) -> Result<Self, Error>
+    where
+        P: Into<PathBuf>,
+        N: Into<String>,
+    {
+        let path = path.into();
+        if path.exists() {
+            return Self::load(api, path);
+        }
+
+        let account = api.send_async(login).await?;
+        let device_name = device_name.into();
+        let mut attempt = 0;
+
+        let device = loop {
+            let name = candidate(&device_name, attempt);
+            let register = RegisterDevice::new(account.secret.as_str(), name.as_str());
+            match api.send_async(&register).await {
+                Ok(device) => break (device.id, name),
+                Err(ref e) if is_name_taken(e) && attempt < MAX_RENAMES => attempt += 1,
+                Err(e) => return Err(e),
+            }
+        };
+
+        Self::save(api, path, account.id, account.secret, device)
+    }
+
+    /// Uses the device saved at `path`.
+    pub fn load<P: Into<PathBuf>>(api: API, path: P) -> Result<Self, Error> {
+        let path = path.into();
+        let credentials = serde_json::from_slice(&fs::read(&path)?)?;
+
+        Ok(Self {
+            api,
+            path,
+            credentials,
+        })
+    }
+
+    pub fn credentials(&self) -> &Credentials {
+        &self.credentials
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub(crate) fn api(&self) -> &API {
+        &self.api
+    }
+
+    /// Downloads the messages waiting for the device, then deletes them from Pushover.
+    pub fn fetch_new(&self) -> Result<Vec<Message>, Error> {
+        let messages = self.api.send(&self.download())?.messages;
+        if let Some(highest) = messages.iter().map(|message| message.id).max() {
+            self.api.send(&self.delete(highest))?;
+        }
+
+        Ok(messages)
+    }
+
+    pub async fn fetch_new_async(&self) -> Result<Vec<Message>, Error> {
+        let messages = self.api.send_async(&self.download()).await?.messages;
+        if let Some(highest) = messages.iter().map(|message| message.id).max() {
+            self.api.send_async(&self.delete(highest)).await?;
+        }
+
+        Ok(messages)
+    }
+
+    /// Downloads the messages waiting for the device and passes each to `handler`, oldest first,
+    /// deleting them from Pushover only once they have been handled.
+    ///
+    /// Handled messages are recorded in `seen` before anything is deleted, so a message that is
+    /// downloaded again after a crash or a failed deletion is skipped rather than handled twice.
+    /// Processing stops at the first message the handler fails on.
+    pub fn process<F, E>(&self, seen: &mut SeenStore, handler: F) -> Result<ProcessReport<E>, Error>
+    where
+        F: FnMut(&Message) -> Result<(), E>,
+    {
+        let messages = self.api.send(&self.download())?.messages;
+        let report = handle(messages, seen, handler)?;
+
+        if let Some(highest) = report.deleted_through {
+            self.api.send(&self.delete(highest))?;
+            seen.prune(highest)?;
+        }
+
+        Ok(report)
+    }
+
+    pub async fn process_async<F, E>(
+        &self,
+        seen: &mut SeenStore,
+        handler: F,
+    ) -> Result<ProcessReport<E>, Error>
+    where
+        F: FnMut(&Message) -> Result<(), E>,
+    {
+        let messages = self.api.send_async(&self.download()).await?.messages;
+        let report = handle(messages, seen, handler)?;
+
+        if let Some(highest) = report.deleted_through {
+            self.api.send_async(&self.delete(highest)).await?;
+            seen.prune(highest)?;
+        }
+
+        Ok(report)
+    }
+
+    fn save(
+        api: API,
+        path: PathBuf,
+        user_id: String,
+        secret: String,
+        (device_id, device_name): (String, String),
+    ) -> Result<Self, Error> {
+        let credentials = Credentials {
+            user_id,
+            secret,
+            device_id,
+            device_name,
+        };
+
+        let mut file = create_private(&path)?;
+        file.write_all(&serde_json::to_vec_pretty(&credentials)?)?;
+
+        Ok(Self {
+            api,
+            path,
+            credentials,
+        })
+    }
+
+    fn download(&self) -> DownloadMessages {
+        DownloadMessages::new(
+            self.credentials.secret.as_str(),
+            self.credentials.device_id.as_str(),
+        )
+    }
+
+    fn delete(&self, highest: u32) -> DeleteMessages {
+        DeleteMessages::new(
+            self.credentials.secret.as_str(),
+            self.credentials.device_id.as_str(),
+            highest,
+        )
+    }
+}
+
+/// Passes each message not seen before to `handler`, recording the ones it handles.
+fn handle<F, E>(
+    mut messages: Vec<Message>,
+    seen: &mut SeenStore,
+    mut handler: F,
+) -> Result<ProcessReport<E>, Error>
+where
+    F: FnMut(&Message) -> Result<(), E>,
+{
+    messages.sort_by_key(|message| message.id);
+
+    let mut report = ProcessReport {
+        handled: 0,
+        duplicates: 0,
+        deleted_through: None,
+        failed: None,
+    };
+
+    for message in messages {
+        if seen.contains(message.umid) {
+            report.duplicates += 1;
+        } else {
+            if let Err(e) = handler(&message) {
+                report.failed = Some((message, e));
+                break;
+            }
+            seen.insert(&message)?;
+            report.handled += 1;
+        }
+
+        report.deleted_through = Some(message.id);
+    }
+
+    Ok(report)
+}
+
+/// `name` for the first attempt, then `name-2`, `name-3`… within Pushover's length limit.
+fn candidate(name: &str, attempt: usize) -> String {
+    if attempt == 0 {
+        return name.to_owned();
+    }
+
+    let suffix = format!("-{}", attempt + 1);
+    let keep = MAX_DEVICE_NAME_LENGTH.saturating_sub(suffix.len());
+    let base: String = name.chars().take(keep).collect();
+
+    base + &suffix
+}
+
+fn is_name_taken(error: &Error) -> bool {
+    matches!(error.kind(), ErrorKind::DeviceNameTaken(..))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::SystemTime;
+
+    use crate::types::Priority;
+
+    fn message(id: u32, umid: u32) -> Message {
+        Message {
+            id,
+            umid,
+            title: None,
+            message: format!("message {}", id),
+            app: String::from("app"),
+            aid: 1,
+            icon: String::from("icon"),
+            date: SystemTime::UNIX_EPOCH,
+            priority: Priority::Normal,
+            sound: None,
+            url: None,
+            url_title: None,
+            acked: 0,
+            receipt: None,
+            html: None,
+        }
+    }
+
+    #[test]
+    fn handles_up_to_first_failure() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("seen.jsonl");
+        let mut seen = SeenStore::open(&path).unwrap();
+
+        let report = handle(
+            vec![message(3, 30), message(1, 10), message(2, 20)],
+            &mut seen,
+            |message| if message.id == 3 { Err("boom") } else { Ok(()) },
+        )
+        .unwrap();
+
+        assert_eq!(report.handled, 2);
+        assert_eq!(report.deleted_through, Some(2));
+        assert_eq!(
+            report.failed.map(|(message, e)| (message.id, e)),
+            Some((3, "boom"))
+        );
+        assert!(seen.contains(10) && seen.contains(20) && !seen.contains(30));
+    }
+
+    #[test]
+    fn skips_redelivered_messages() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("seen.jsonl");
+        let mut seen = SeenStore::open(&path).unwrap();
+        handle(vec![message(1, 10)], &mut seen, |_| Ok::<(), ()>(())).unwrap();
+
+        // Reopening replays the store, as after a crash before deletion.
+        let mut seen = SeenStore::open(&path).unwrap();
+        let mut handled = Vec::new();
+        let report = handle(vec![message(1, 10), message(2, 20)], &mut seen, |message| {
+            handled.push(message.id);
+            Ok::<(), ()>(())
+        })
+        .unwrap();
+
+        assert_eq!(handled, [2]);
+        assert_eq!(report.duplicates, 1);
+        assert_eq!(report.deleted_through, Some(2));
+
+        seen.prune(1).unwrap();
+        assert_eq!(SeenStore::open(&path).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn renames_within_length_limit() {
+        assert_eq!(candidate("alerts-bot", 0), "alerts-bot");
+        assert_eq!(candidate("alerts-bot", 1), "alerts-bot-2");
+        assert_eq!(
+            candidate("a-very-long-device-name-x", 9),
+            "a-very-long-device-nam-10"
+        );
+    }
+
+    #[test]
+    fn saves_credentials_privately() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("session.json");
+
+        let saved = OpenClientSession::save(
+            API::new(),
+            path.clone(),
+            String::from("user"),
+            String::from("secret"),
+            (String::from("device"), String::from("alerts-bot")),
+        )
+        .unwrap();
+        let loaded = OpenClientSession::load(API::new(), path.clone()).unwrap();
+
+        assert_eq!(loaded.credentials(), saved.credentials());
+        assert!(!format!("{:?}", loaded.credentials()).contains("\"secret\""));
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+
+            let mode = fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+    }
+}
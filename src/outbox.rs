@@ -0,0 +1,489 @@
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, SystemTime};
+
+use serde::{Deserialize, Serialize};
+
+use crate::client::API;
+use crate::error::{Error, ErrorKind};
+use crate::jsonl::{self, append};
+use crate::requests::message::SendMessage;
+use crate::requests::Request;
+
+const DEFAULT_RETRY: Duration = Duration::from_secs(5);
+const MAX_RETRY: Duration = Duration::from_secs(300);
+
+/// Durable queue of messages waiting to be delivered.
+///
+/// Messages are appended to a local file and survive restarts until an
+/// [OutboxWorker](struct.OutboxWorker.html) has delivered them and Pushover has accepted them.
+/// Messages without a timestamp are stamped when queued, so they show the time of the original
+/// event rather than the time they were finally delivered.
+///
+/// ```rust,no_run
+/// use std::sync::Arc;
+/// use pushover::{Outbox, OutboxWorker, API};
+/// use pushover::requests::message::SendMessage;
+///
+/// let outbox = Arc::new(Outbox::open("/var/lib/app/outbox.jsonl").expect("Error opening outbox"));
+/// let worker = OutboxWorker::spawn(API::new(), outbox.clone());
+///
+/// outbox
+///     .enqueue(SendMessage::new("token", "user_key", "hello"))
+///     .expect("Error queueing message");
+/// ```
+pub struct Outbox {
+    path: PathBuf,
+    state: Mutex<State>,
+    changed: Condvar,
+}
+
+struct State {
+    file: File,
+    next_id: u64,
+    pending: BTreeMap<u64, SendMessage>,
+    failed: BTreeMap<u64, FailedEntry>,
+}
+
+/// A message Pushover rejected, kept aside instead of being retried.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct FailedEntry {
+    pub message: SendMessage,
+    pub errors: Vec<String>,
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(tag = "op", rename_all = "lowercase")]
+enum Record {
+    Enqueue { id: u64, message: Box<SendMessage> },
+    Done { id: u64 },
+    Failed { id: u64, errors: Vec<String> },
+}
+
+impl Outbox {
+    /// Opens the outbox at `path`, creating it if needed and replaying the entries still pending.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let path = path.as_ref().to_owned();
+        let mut pending = BTreeMap::new();
+        let mut failed = BTreeMap::new();
+        let mut next_id = 1;
+
+        if path.exists() {
+            for record in jsonl::read(&path)? {
+                match record {
+                    Record::Enqueue { id, message } => {
+                        next_id = next_id.max(id + 1);
+                        pending.insert(id, *message);
+                    }
+                    Record::Done { id } => {
+                        pending.remove(&id);
+                    }
+                    Record::Failed { id, errors } => {
+                        if let Some(message) = pending.remove(&id) {
+                            failed.insert(id, FailedEntry { message, errors });
+                        }
+                    }
+                }
+            }
+        }
+
+        let file = write_log(&path, &pending, &failed)?;
+
+        Ok(Self {
+            path,
+            state: Mutex::new(State {
+                file,
+                next_id,
+                pending,
+                failed,
+            }),
+            changed: Condvar::new(),
+        })
+    }
+
+    /// Durably queues a message, returning its id in the outbox.
+    ///
+    /// Fails with [ErrorKind::SplitRequired](enum.ErrorKind.html#variant.SplitRequired) for a
+    /// message that would be split into parts, as it could never be delivered.
+    pub fn enqueue(&self, mut message: SendMessage) -> Result<u64, Error> {
+        message.check_single()?;
+        if message.timestamp.is_none() {
+            message.set_timestamp(SystemTime::now());
+        }
+
+        let mut state = self.state.lock().unwrap();
+        let id = state.next_id;
+
+        append(
+            &mut state.file,
+            &Record::Enqueue {
+                id,
+                message: Box::new(message.clone()),
+            },
+        )?;
+        state.next_id += 1;
+        state.pending.insert(id, message);
+
+        self.changed.notify_all();
+
+        Ok(id)
+    }
+
+    /// Messages waiting to be delivered, oldest first.
+    pub fn pending(&self) -> Vec<(u64, SendMessage)> {
+        let state = self.state.lock().unwrap();
+
+        state
+            .pending
+            .iter()
+            .map(|(id, message)| (*id, message.clone()))
+            .collect()
+    }
+
+    /// Messages Pushover rejected.
+    pub fn failed(&self) -> Vec<(u64, FailedEntry)> {
+        let state = self.state.lock().unwrap();
+
+        state
+            .failed
+            .iter()
+            .map(|(id, entry)| (*id, entry.clone()))
+            .collect()
+    }
+
+    /// Records that Pushover accepted the message.
+    pub fn mark_done(&self, id: u64) -> Result<(), Error> {
+        let mut state = self.state.lock().unwrap();
+
+        if state.pending.remove(&id).is_some() {
+            append(&mut state.file, &Record::Done { id })?;
+        }
+
+        Ok(())
+    }
+
+    /// Records that Pushover rejected the message, so it isn't retried.
+    pub fn mark_failed(&self, id: u64, errors: Vec<String>) -> Result<(), Error> {
+        let mut state = self.state.lock().unwrap();
+
+        if let Some(message) = state.pending.remove(&id) {
+            append(
+                &mut state.file,
+                &Record::Failed {
+                    id,
+                    errors: errors.clone(),
+                },
+            )?;
+            state.failed.insert(id, FailedEntry { message, errors });
+        }
+
+        Ok(())
+    }
+
+    /// Rewrites the file with only the entries still pending or failed.
+    pub fn compact(&self) -> Result<(), Error> {
+        let mut state = self.state.lock().unwrap();
+
+        state.file = write_log(&self.path, &state.pending, &state.failed)?;
+
+        Ok(())
+    }
+
+    /// Waits until `timeout` passes or the worker is stopped, returning early when a message is
+    /// queued if `until_queued` is set.
+    fn wait(&self, timeout: Duration, until_queued: bool, stopped: &AtomicBool) {
+        let state = self.state.lock().unwrap();
+
+        // Checked under the lock, so a stop between here and waiting can't be missed.
+        if stopped.load(Ordering::SeqCst) || (until_queued && !state.pending.is_empty()) {
+            return;
+        }
+
+        let _ = self.changed.wait_timeout(state, timeout).unwrap();
+    }
+
+    fn wake(&self) {
+        let _state = self.state.lock().unwrap();
+
+        self.changed.notify_all();
+    }
+}
+
+/// Rewrites the log with only the entries still pending or failed.
+fn write_log(
+    path: &Path,
+    pending: &BTreeMap<u64, SendMessage>,
+    failed: &BTreeMap<u64, FailedEntry>,
+) -> Result<File, Error> {
+    let mut entries: Vec<(u64, &SendMessage, Option<&Vec<String>>)> = pending
+        .iter()
+        .map(|(id, message)| (*id, message, None))
+        .chain(
+            failed
+                .iter()
+                .map(|(id, entry)| (*id, &entry.message, Some(&entry.errors))),
+        )
+        .collect();
+    entries.sort_by_key(|(id, _, _)| *id);
+
+    let records = entries.into_iter().flat_map(|(id, message, errors)| {
+        let enqueue = Record::Enqueue {
+            id,
+            message: Box::new(message.clone()),
+        };
+        let failed = errors.map(|errors| Record::Failed {
+            id,
+            errors: errors.clone(),
+        });
+
+        std::iter::once(enqueue).chain(failed)
+    });
+
+    jsonl::rewrite(path, records)
+}
+
+/// Background thread delivering the messages of an [Outbox](struct.Outbox.html).
+///
+/// Messages are sent oldest first. When a send fails for a reason other than Pushover rejecting
+/// the message, delivery pauses and is retried with exponential backoff. Stops when dropped.
+pub struct OutboxWorker {
+    outbox: Arc<Outbox>,
+    stopped: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl OutboxWorker {
+    pub fn spawn(api: API, outbox: Arc<Outbox>) -> Self {
+        Self::spawn_with_retry(api, outbox, DEFAULT_RETRY)
+    }
+
+    /// Like [spawn](#method.spawn), waiting `retry` after the first failed delivery.
+    pub fn spawn_with_retry(api: API, outbox: Arc<Outbox>, retry: Duration) -> Self {
+        let stopped = Arc::new(AtomicBool::new(false));
+
+        let handle = {
+            let outbox = outbox.clone();
+            let stopped = stopped.clone();
+
+            thread::spawn(move || {
+                let mut backoff = retry;
+
+                while !stopped.load(Ordering::SeqCst) {
+                    if deliver(&api, &outbox) {
+                        backoff = retry;
+                        outbox.wait(retry, true, &stopped);
+                    } else {
+                        outbox.wait(backoff, false, &stopped);
+                        backoff = (backoff * 2).min(MAX_RETRY.max(retry));
+                    }
+                }
+            })
+        };
+
+        Self {
+            outbox,
+            stopped,
+            handle: Some(handle),
+        }
+    }
+
+    /// Stops the worker after the delivery in progress, if any.
+    pub fn stop(mut self) {
+        self.shutdown();
+    }
+
+    fn shutdown(&mut self) {
+        self.stopped.store(true, Ordering::SeqCst);
+        self.outbox.wake();
+
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+impl Drop for OutboxWorker {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+/// Sends the pending messages in order, returning `false` if delivery has to be retried later.
+///
+/// A message that can never be delivered is marked as failed, so it doesn't hold up the ones
+/// queued after it.
+fn deliver(api: &API, outbox: &Outbox) -> bool {
+    for (id, message) in outbox.pending() {
+        let recorded = match api.send(&message) {
+            Ok(_) => outbox.mark_done(id),
+            Err(ref e) if is_transient(e) => return false,
+            Err(Error(ErrorKind::PushoverError { errors, .. }, _))
+            | Err(Error(ErrorKind::InvalidRecipient { errors, .. }, _)) => {
+                outbox.mark_failed(id, errors)
+            }
+            Err(Error(ErrorKind::InvalidRequest(violations), _)) => {
+                outbox.mark_failed(id, violations.iter().map(ToString::to_string).collect())
+            }
+            Err(e) => outbox.mark_failed(id, vec![e.to_string()]),
+        };
+
+        if recorded.is_err() {
+            return false;
+        }
+    }
+
+    true
+}
+
+/// Whether sending again later may succeed: the request didn't reach Pushover, Pushover had a
+/// server error, or a limit was hit.
+fn is_transient(err: &Error) -> bool {
+    match err.kind() {
+        ErrorKind::Reqqest(e) => !e.is_decode(),
+        ErrorKind::ServerError(..)
+        | ErrorKind::QuotaExceeded { .. }
+        | ErrorKind::RateLimited(..) => true,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::requests::message::Overflow;
+    use std::fs::{self, OpenOptions};
+    use std::io::Write;
+    use std::time::UNIX_EPOCH;
+
+    #[test]
+    fn replays_pending_after_reopen() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("outbox.jsonl");
+
+        {
+            let outbox = Outbox::open(&path).unwrap();
+            let first = outbox
+                .enqueue(SendMessage::new("token", "user", "first"))
+                .unwrap();
+            outbox
+                .enqueue(SendMessage::new("token", "user", "second"))
+                .unwrap();
+            outbox.mark_done(first).unwrap();
+        }
+
+        let outbox = Outbox::open(&path).unwrap();
+        let pending = outbox.pending();
+
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].0, 2);
+        assert_eq!(pending[0].1.message, "second");
+        assert_eq!(outbox.enqueue(SendMessage::new("t", "u", "m")).unwrap(), 3);
+    }
+
+    #[test]
+    fn keeps_original_timestamp() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("outbox.jsonl");
+        let timestamp = UNIX_EPOCH + Duration::from_secs(1_600_000_000);
+
+        let mut message = SendMessage::new("token", "user", "message");
+        message.set_timestamp(timestamp);
+
+        Outbox::open(&path).unwrap().enqueue(message).unwrap();
+        Outbox::open(&path)
+            .unwrap()
+            .enqueue(SendMessage::new("token", "user", "message"))
+            .unwrap();
+
+        let pending = Outbox::open(&path).unwrap().pending();
+
+        assert_eq!(pending[0].1.timestamp, Some(timestamp));
+        assert!(pending[1].1.timestamp.is_some());
+    }
+
+    #[test]
+    fn ignores_partially_written_record() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("outbox.jsonl");
+
+        Outbox::open(&path)
+            .unwrap()
+            .enqueue(SendMessage::new("token", "user", "message"))
+            .unwrap();
+        OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .unwrap()
+            .write_all(b"{\"op\":\"enq")
+            .unwrap();
+
+        let outbox = Outbox::open(&path).unwrap();
+
+        assert_eq!(outbox.pending().len(), 1);
+    }
+
+    #[test]
+    fn refuses_log_with_corrupt_record() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("outbox.jsonl");
+
+        {
+            let outbox = Outbox::open(&path).unwrap();
+            outbox
+                .enqueue(SendMessage::new("token", "user", "first"))
+                .unwrap();
+            OpenOptions::new()
+                .append(true)
+                .open(&path)
+                .unwrap()
+                .write_all(b"not json\n")
+                .unwrap();
+            outbox
+                .enqueue(SendMessage::new("token", "user", "second"))
+                .unwrap();
+        }
+        let log = fs::read_to_string(&path).unwrap();
+
+        assert!(Outbox::open(&path).is_err());
+        assert_eq!(fs::read_to_string(&path).unwrap(), log);
+    }
+
+    #[test]
+    fn refuses_message_that_would_be_split() {
+        let dir = tempfile::tempdir().unwrap();
+        let outbox = Outbox::open(dir.path().join("outbox.jsonl")).unwrap();
+        let mut message = SendMessage::new("token", "user", "m".repeat(2000));
+        message.set_overflow(Overflow::Split);
+
+        match outbox.enqueue(message) {
+            Err(Error(ErrorKind::SplitRequired(2), _)) => {}
+            other => panic!("Expected SplitRequired, got {:?}", other),
+        }
+        assert!(outbox.pending().is_empty());
+    }
+
+    #[test]
+    fn failed_entries_are_not_pending() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("outbox.jsonl");
+
+        {
+            let outbox = Outbox::open(&path).unwrap();
+            let id = outbox
+                .enqueue(SendMessage::new("token", "user", "message"))
+                .unwrap();
+            outbox
+                .mark_failed(id, vec![String::from("user is invalid")])
+                .unwrap();
+        }
+
+        let outbox = Outbox::open(&path).unwrap();
+
+        assert!(outbox.pending().is_empty());
+        assert_eq!(outbox.failed()[0].1.errors, vec!["user is invalid"]);
+    }
+}
@@ -0,0 +1,354 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+use crate::client::API;
+use crate::clock::{Clock, SystemClock};
+use crate::error::Error;
+use crate::glance_updater::GlanceUpdater;
+use crate::requests::glance::Glance;
+use crate::requests::message::{SendMessage, SendMessageResponse};
+use crate::types::Priority;
+
+const DEFAULT_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Shows a job's progress on a glance, and sends a `High` priority message when it ends.
+///
+/// The glance shows the percentage complete, `done/total` as its text and the estimated time
+/// left as its subtext. Updates go through a [GlanceUpdater](struct.GlanceUpdater.html), so they
+/// are sent at most once per minimum interval (a minute by default).
+///
+/// ```rust,no_run
+/// use pushover::{Progress, API};
+/// use pushover::requests::glance::Glance;
+///
+/// let mut glance = Glance::new("token", "uQiRzpo4DXghDmr9QzzfQu27cmVRsG");
+/// glance.set_title("Backfill");
+///
+/// let rows = vec![1, 2, 3];
+/// let progress = Progress::new(API::new(), glance, rows.len() as u64);
+///
+/// for row in progress.wrap(rows) {
+///     println!("{}", row);
+/// }
+/// ```
+pub struct Progress {
+    updater: GlanceUpdater,
+    total: u64,
+    done: AtomicU64,
+    clock: Arc<dyn Clock>,
+    started: SystemTime,
+}
+
+/// Iterator that reports each item it yields to a [Progress](struct.Progress.html), and finishes
+/// it once exhausted.
+pub struct ProgressIter<I> {
+    inner: I,
+    progress: Progress,
+    finished: Option<Result<SendMessageResponse, Error>>,
+}
+
+impl Progress {
+    /// `glance` names the user and device to update; its title, if any, also titles the final
+    /// message.
+    pub fn new(api: API, glance: Glance, total: u64) -> Self {
+        let clock: Arc<dyn Clock> = Arc::new(SystemClock);
+
+        Self {
+            updater: GlanceUpdater::new(api, glance, DEFAULT_INTERVAL).shared_clock(clock.clone()),
+            total,
+            done: AtomicU64::new(0),
+            started: clock.now(),
+            clock,
+        }
+    }
+
+    /// Send glance updates at most once per `min_interval`.
+    pub fn min_interval(mut self, min_interval: Duration) -> Self {
+        self.updater.set_min_interval(min_interval);
+        self
+    }
+
+    /// Use `clock` for throttling and estimates. The job is counted as started now.
+    pub fn clock<C: Clock + 'static>(mut self, clock: C) -> Self {
+        self.clock = Arc::new(clock);
+        self.updater = self.updater.shared_clock(self.clock.clone());
+        self.started = self.clock.now();
+        self
+    }
+
+    /// Counts `n` more units as done and updates the glance if an update is due.
+    pub fn inc(&self, n: u64) -> Result<(), Error> {
+        let done = self.done.fetch_add(n, Ordering::SeqCst) + n;
+        self.updater
+            .update(|glance| self.show(glance, done))
+            .map(drop)
+    }
+
+    /// Sets the number of units done and updates the glance if an update is due.
+    pub fn set(&self, done: u64) -> Result<(), Error> {
+        self.done.store(done, Ordering::SeqCst);
+        self.updater
+            .update(|glance| self.show(glance, done))
+            .map(drop)
+    }
+
+    pub async fn inc_async(&self, n: u64) -> Result<(), Error> {
+        let done = self.done.fetch_add(n, Ordering::SeqCst) + n;
+        self.updater
+            .update_async(|glance| self.show(glance, done))
+            .await
+            .map(drop)
+    }
+
+    pub async fn set_async(&self, done: u64) -> Result<(), Error> {
+        self.done.store(done, Ordering::SeqCst);
+        self.updater
+            .update_async(|glance| self.show(glance, done))
+            .await
+            .map(drop)
+    }
+
+    /// Shows the job as complete and sends the completion message.
+    ///
+    /// The final glance update is best effort; only the message's outcome is returned.
+    pub fn finish(&self) -> Result<SendMessageResponse, Error> {
+        let message = self.complete();
+        let _ = self.updater.flush();
+        self.updater.api().send(&message)
+    }
+
+    /// Shows the job as failed and sends a failure message with `reason`.
+    ///
+    /// The final glance update is best effort; only the message's outcome is returned.
+    pub fn fail(&self, reason: &str) -> Result<SendMessageResponse, Error> {
+        let message = self.failed(reason);
+        let _ = self.updater.flush();
+        self.updater.api().send(&message)
+    }
+
+    pub async fn finish_async(&self) -> Result<SendMessageResponse, Error> {
+        let message = self.complete();
+        let _ = self.updater.flush_async().await;
+        self.updater.api().send_async(&message).await
+    }
+
+    pub async fn fail_async(&self, reason: &str) -> Result<SendMessageResponse, Error> {
+        let message = self.failed(reason);
+        let _ = self.updater.flush_async().await;
+        self.updater.api().send_async(&message).await
+    }
+
+    /// Reports each item of `iter` as one unit done, finishing once it is exhausted.
+    ///
+    /// Glance update failures don't interrupt the iteration.
+    pub fn wrap<I: IntoIterator>(self, iter: I) -> ProgressIter<I::IntoIter> {
+        ProgressIter {
+            inner: iter.into_iter(),
+            progress: self,
+            finished: None,
+        }
+    }
+
+    /// Units done so far.
+    pub fn done(&self) -> u64 {
+        self.done.load(Ordering::SeqCst)
+    }
+
+    pub fn percent(&self) -> u8 {
+        percent(self.done(), self.total)
+    }
+
+    /// Estimated time left, from the average pace so far.
+    pub fn eta(&self) -> Option<Duration> {
+        eta(self.elapsed(), self.done(), self.total)
+    }
+
+    fn elapsed(&self) -> Duration {
+        self.clock
+            .now()
+            .duration_since(self.started)
+            .unwrap_or_default()
+    }
+
+    fn show(&self, glance: &mut Glance, done: u64) {
+        glance.set_percent(percent(done, self.total));
+        glance.set_text(format!("{}/{}", done, self.total));
+        match eta(self.elapsed(), done, self.total) {
+            Some(eta) => glance.set_subtext(format!("ETA {}", format_duration(eta))),
+            None => glance.subtext = None,
+        }
+    }
+
+    /// Updates the desired glance for the end of the job, returning the message to send.
+    fn end(&self, subtext: String, text: String) -> SendMessage {
+        let done = self.done();
+        let _ = self.updater.change(|glance| {
+            glance.set_percent(percent(done, self.total));
+            glance.set_text(format!("{}/{}", done, self.total));
+            glance.set_subtext(subtext);
+        });
+
+        let glance = self.updater.desired();
+        let mut message = SendMessage::new(glance.token, glance.user_key, text);
+        message.set_priority(Priority::High);
+        if let Some(title) = glance.title {
+            message.set_title(title);
+        }
+        if let Some(device) = glance.device {
+            message.add_device(device);
+        }
+        message
+    }
+
+    fn complete(&self) -> SendMessage {
+        let elapsed = format_duration(self.elapsed());
+
+        self.end(
+            format!("Done in {}", elapsed),
+            format!("Completed {}/{} in {}", self.done(), self.total, elapsed),
+        )
+    }
+
+    fn failed(&self, reason: &str) -> SendMessage {
+        let elapsed = format_duration(self.elapsed());
+
+        self.end(
+            format!("Failed after {}", elapsed),
+            format!(
+                "Failed at {}/{} after {}: {}",
+                self.done(),
+                self.total,
+                elapsed,
+                reason
+            ),
+        )
+    }
+}
+
+impl<I> ProgressIter<I> {
+    pub fn progress(&self) -> &Progress {
+        &self.progress
+    }
+
+    /// Outcome of the completion message, once the iterator has been exhausted.
+    pub fn finished(&self) -> Option<&Result<SendMessageResponse, Error>> {
+        self.finished.as_ref()
+    }
+}
+
+impl<I: Iterator> Iterator for ProgressIter<I> {
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<I::Item> {
+        match self.inner.next() {
+            Some(item) => {
+                let _ = self.progress.inc(1);
+                Some(item)
+            }
+            None => {
+                if self.finished.is_none() {
+                    self.finished = Some(self.progress.finish());
+                }
+                None
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+fn percent(done: u64, total: u64) -> u8 {
+    if total == 0 {
+        return 100;
+    }
+
+    (done.min(total) * 100 / total) as u8
+}
+
+fn eta(elapsed: Duration, done: u64, total: u64) -> Option<Duration> {
+    if done == 0 {
+        return None;
+    }
+
+    let left = total.saturating_sub(done);
+    let per_unit = elapsed.as_secs_f64() / done as f64;
+    Some(Duration::from_secs_f64(per_unit * left as f64))
+}
+
+/// e.g. `1h 5m`, `3m 20s` or `45s`.
+fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+
+    match (secs / 3600, secs % 3600 / 60, secs % 60) {
+        (0, 0, s) => format!("{}s", s),
+        (0, m, s) => format!("{}m {}s", m, s),
+        (h, m, _) => format!("{}h {}m", h, m),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::ManualClock;
+
+    #[test]
+    fn shows_percent_and_eta() {
+        let clock = ManualClock::new(SystemTime::UNIX_EPOCH);
+        let progress = Progress::new(
+            API::new(),
+            Glance::new("token", "uQiRzpo4DXghDmr9QzzfQu27cmVRsG"),
+            200,
+        )
+        .clock(clock.clone());
+        let mut glance = Glance::new("token", "user");
+
+        progress.show(&mut glance, 0);
+        assert_eq!(glance.percent, Some(0));
+        assert!(glance.subtext.is_none());
+
+        clock.advance(Duration::from_secs(50));
+        progress.show(&mut glance, 50);
+        assert_eq!(glance.percent, Some(25));
+        assert_eq!(glance.text.as_deref(), Some("50/200"));
+        assert_eq!(glance.subtext.as_deref(), Some("ETA 2m 30s"));
+    }
+
+    #[test]
+    fn clamps_percent() {
+        assert_eq!(percent(5, 0), 100);
+        assert_eq!(percent(1, 3), 33);
+        assert_eq!(percent(7, 5), 100);
+    }
+
+    #[test]
+    fn formats_durations() {
+        assert_eq!(format_duration(Duration::from_secs(45)), "45s");
+        assert_eq!(format_duration(Duration::from_secs(200)), "3m 20s");
+        assert_eq!(format_duration(Duration::from_secs(3900)), "1h 5m");
+    }
+
+    #[test]
+    fn final_message_is_high_priority() {
+        let mut glance = Glance::new("token", "uQiRzpo4DXghDmr9QzzfQu27cmVRsG");
+        glance.set_title("Backfill");
+        glance.set_device("phone");
+        let clock = ManualClock::new(SystemTime::UNIX_EPOCH);
+        let progress = Progress::new(API::new(), glance, 10).clock(clock.clone());
+        progress.done.store(4, Ordering::SeqCst);
+        clock.advance(Duration::from_secs(90));
+
+        let message = progress.failed("disk full");
+
+        assert_eq!(message.priority, Some(Priority::High));
+        assert_eq!(message.title.as_deref(), Some("Backfill"));
+        assert_eq!(message.devices, ["phone"]);
+        assert_eq!(message.message, "Failed at 4/10 after 1m 30s: disk full");
+        assert_eq!(
+            progress.updater.desired().subtext.as_deref(),
+            Some("Failed after 1m 30s")
+        );
+    }
+}
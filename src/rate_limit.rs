@@ -0,0 +1,298 @@
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+use reqwest::header::HeaderMap;
+
+use crate::clock::{Clock, SystemClock};
+use crate::error::{Error, ErrorKind};
+use crate::requests::message::LimitsResponse;
+use crate::types::{from_unix_seconds, Priority};
+
+const LIMIT_HEADER: &str = "X-Limit-App-Limit";
+const REMAINING_HEADER: &str = "X-Limit-App-Remaining";
+const RESET_HEADER: &str = "X-Limit-App-Reset";
+
+/// What a [RateLimiter](struct.RateLimiter.html) does with a message sent too soon.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RateLimitMode {
+    /// Wait until the message can be sent.
+    Delay,
+    /// Fail with [ErrorKind::RateLimited](enum.ErrorKind.html#variant.RateLimited).
+    Reject,
+}
+
+/// An app's monthly message quota, as last reported by Pushover.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Quota {
+    pub limit: u32,
+    pub remaining: u32,
+    pub reset: SystemTime,
+}
+
+/// Token bucket limiting how fast [API](struct.API.html) sends messages.
+///
+/// Only messages count against the bucket; other requests are sent immediately. The limiter also
+/// tracks the app's quota from the `X-Limit-App-*` headers Pushover returns with each message and
+/// from [Limits](requests/message/struct.Limits.html) responses. Once the remaining quota drops
+/// below the [emergency reserve](#method.emergency_reserve), only emergency-priority messages are
+/// sent, so that pages still get through.
+///
+/// ```rust
+/// use pushover::{RateLimitMode, RateLimiter, API};
+///
+/// let api = API::new().rate_limiter(
+///     RateLimiter::new(2.0, 5)
+///         .mode(RateLimitMode::Reject)
+///         .emergency_reserve(100),
+/// );
+/// ```
+pub struct RateLimiter {
+    per_second: f64,
+    burst: f64,
+    mode: RateLimitMode,
+    emergency_reserve: Option<u32>,
+    clock: Arc<dyn Clock>,
+    state: Mutex<State>,
+}
+
+struct State {
+    tokens: f64,
+    updated: Option<SystemTime>,
+    quota: Option<Quota>,
+}
+
+impl RateLimiter {
+    /// Allow `per_second` messages per second on average, and up to `burst` at once.
+    ///
+    /// # Panics
+    ///
+    /// If `per_second` isn't a finite number greater than zero, or `burst` is zero.
+    pub fn new(per_second: f64, burst: u32) -> Self {
+        assert!(
+            per_second.is_finite() && per_second > 0.0,
+            "rate must be a positive number of messages per second, got {}",
+            per_second
+        );
+        assert!(burst > 0, "burst must be at least 1");
+
+        Self {
+            per_second,
+            burst: f64::from(burst),
+            mode: RateLimitMode::Delay,
+            emergency_reserve: None,
+            clock: Arc::new(SystemClock),
+            state: Mutex::new(State {
+                tokens: f64::from(burst),
+                updated: None,
+                quota: None,
+            }),
+        }
+    }
+
+    pub fn mode(mut self, mode: RateLimitMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Reject non-emergency messages with
+    /// [ErrorKind::QuotaReserved](enum.ErrorKind.html#variant.QuotaReserved) once fewer than
+    /// `threshold` messages remain in the quota.
+    pub fn emergency_reserve(mut self, threshold: u32) -> Self {
+        self.emergency_reserve = Some(threshold);
+        self
+    }
+
+    pub fn clock<C: Clock + 'static>(mut self, clock: C) -> Self {
+        self.clock = Arc::new(clock);
+        self
+    }
+
+    pub fn quota(&self) -> Option<Quota> {
+        self.state.lock().unwrap().quota
+    }
+
+    pub fn update_quota(&self, quota: Quota) {
+        self.state.lock().unwrap().quota = Some(quota);
+    }
+
+    pub fn update_from_limits(&self, limits: &LimitsResponse) {
+        self.update_quota(Quota {
+            limit: limits.limit,
+            remaining: limits.remaining,
+            reset: limits.reset,
+        });
+    }
+
+    pub(crate) fn update_from_headers(&self, headers: &HeaderMap) {
+        let header = |name: &str| {
+            headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.parse::<u64>().ok())
+        };
+
+        if let (Some(limit), Some(remaining), Some(reset)) = (
+            header(LIMIT_HEADER),
+            header(REMAINING_HEADER),
+            header(RESET_HEADER),
+        ) {
+            self.update_quota(Quota {
+                limit: limit as u32,
+                remaining: remaining as u32,
+                reset: from_unix_seconds(reset),
+            });
+        }
+    }
+
+    /// Takes a token, returning how long to wait before making the call it paces.
+    ///
+    /// Fails with [ErrorKind::RateLimited](enum.ErrorKind.html#variant.RateLimited) instead of
+    /// waiting when the limiter is in [Reject](enum.RateLimitMode.html#variant.Reject) mode. The
+    /// quota and its emergency reserve are left out, so this can pace calls that aren't messages.
+    pub fn acquire(&self) -> Result<Duration, Error> {
+        let now = self.clock.now();
+        let mut state = self.state.lock().unwrap();
+
+        self.take(&mut state, now)
+    }
+
+    /// Takes a token for a message, returning how long to wait before sending it.
+    pub(crate) fn acquire_message(&self, priority: Priority) -> Result<Duration, Error> {
+        let now = self.clock.now();
+        let mut state = self.state.lock().unwrap();
+
+        if let (Some(threshold), Some(quota)) = (self.emergency_reserve, state.quota) {
+            let reset = quota.reset <= now;
+
+            if !reset && quota.remaining < threshold && priority != Priority::Emergency {
+                return Err(ErrorKind::QuotaReserved(quota.remaining).into());
+            }
+        }
+
+        self.take(&mut state, now)
+    }
+
+    fn take(&self, state: &mut State, now: SystemTime) -> Result<Duration, Error> {
+        if let Some(updated) = state.updated {
+            let elapsed = now.duration_since(updated).unwrap_or_default();
+
+            state.tokens = (state.tokens + elapsed.as_secs_f64() * self.per_second).min(self.burst);
+        }
+        state.updated = Some(now);
+
+        if state.tokens >= 1.0 {
+            state.tokens -= 1.0;
+
+            return Ok(Duration::from_secs(0));
+        }
+
+        let wait = Duration::from_secs_f64((1.0 - state.tokens) / self.per_second);
+
+        match self.mode {
+            RateLimitMode::Reject => Err(ErrorKind::RateLimited(wait).into()),
+            RateLimitMode::Delay => {
+                // Reserve the token now so that concurrent senders queue up behind this one.
+                state.tokens -= 1.0;
+
+                Ok(wait)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::ManualClock;
+    use reqwest::header::HeaderValue;
+    use std::time::UNIX_EPOCH;
+
+    fn clock() -> ManualClock {
+        ManualClock::new(UNIX_EPOCH + Duration::from_secs(1_600_000_000))
+    }
+
+    #[test]
+    #[should_panic(expected = "rate must be a positive number")]
+    fn new_rejects_zero_rate() {
+        RateLimiter::new(0.0, 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "rate must be a positive number")]
+    fn new_rejects_nan_rate() {
+        RateLimiter::new(f64::NAN, 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "burst must be at least 1")]
+    fn new_rejects_zero_burst() {
+        RateLimiter::new(1.0, 0);
+    }
+
+    #[test]
+    fn allows_burst_then_rejects() {
+        let clock = clock();
+        let limiter = RateLimiter::new(1.0, 2)
+            .mode(RateLimitMode::Reject)
+            .clock(clock.clone());
+
+        assert_eq!(
+            limiter.acquire_message(Priority::Normal).unwrap(),
+            Duration::from_secs(0)
+        );
+        assert_eq!(
+            limiter.acquire_message(Priority::Normal).unwrap(),
+            Duration::from_secs(0)
+        );
+
+        match limiter.acquire_message(Priority::Normal) {
+            Err(Error(ErrorKind::RateLimited(wait), _)) => assert_eq!(wait, Duration::from_secs(1)),
+            _ => panic!("Expected RateLimited"),
+        }
+
+        clock.advance(Duration::from_secs(1));
+        assert!(limiter.acquire_message(Priority::Normal).is_ok());
+    }
+
+    #[test]
+    fn delays_queue_up() {
+        let limiter = RateLimiter::new(2.0, 1).clock(clock());
+
+        assert_eq!(
+            limiter.acquire_message(Priority::Normal).unwrap(),
+            Duration::from_secs(0)
+        );
+        assert_eq!(
+            limiter.acquire_message(Priority::Normal).unwrap(),
+            Duration::from_millis(500)
+        );
+        assert_eq!(
+            limiter.acquire_message(Priority::Normal).unwrap(),
+            Duration::from_secs(1)
+        );
+    }
+
+    #[test]
+    fn reserves_quota_for_emergencies() {
+        let clock = clock();
+        let limiter = RateLimiter::new(10.0, 10)
+            .emergency_reserve(5)
+            .clock(clock.clone());
+
+        let mut headers = HeaderMap::new();
+        headers.insert(LIMIT_HEADER, HeaderValue::from_static("10000"));
+        headers.insert(REMAINING_HEADER, HeaderValue::from_static("4"));
+        headers.insert(RESET_HEADER, HeaderValue::from_static("1600003600"));
+        limiter.update_from_headers(&headers);
+
+        match limiter.acquire_message(Priority::High) {
+            Err(Error(ErrorKind::QuotaReserved(remaining), _)) => assert_eq!(remaining, 4),
+            _ => panic!("Expected QuotaReserved"),
+        }
+        assert!(limiter.acquire_message(Priority::Emergency).is_ok());
+        assert!(limiter.acquire().is_ok());
+
+        clock.advance(Duration::from_secs(3600));
+        assert!(limiter.acquire_message(Priority::High).is_ok());
+    }
+}
@@ -0,0 +1,281 @@
+use std::cell::Cell;
+use std::error::Error as StdError;
+use std::fmt;
+
+use reqwest::Method;
+use url::form_urlencoded;
+use url::Url;
+
+/// Placeholder written in place of credentials.
+pub const REDACTED: &str = "REDACTED";
+
+/// Parameter names whose values are credentials.
+pub(crate) const SENSITIVE_PARAMETERS: &[&str] = &["token", "secret", "password"];
+
+thread_local! {
+    static REVEAL: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Formats the wrapped value with its credentials in clear text.
+///
+/// Requests redact their app token, password or device secret from `Debug` output by default.
+/// Wrapping a value in `Unredacted` is an explicit opt-in to reveal them:
+///
+/// ```rust
+/// use pushover::Unredacted;
+/// use pushover::requests::open_client::Login;
+///
+/// let req = Login::new("email@email.com", "password");
+///
+/// assert!(!format!("{:?}", req).contains("\"password\""));
+/// assert!(format!("{:?}", Unredacted(&req)).contains("\"password\""));
+/// ```
+pub struct Unredacted<'a, T: ?Sized>(pub &'a T);
+
+impl<T: fmt::Debug + ?Sized> fmt::Debug for Unredacted<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        struct Reset(bool);
+
+        impl Drop for Reset {
+            fn drop(&mut self) {
+                REVEAL.with(|reveal| reveal.set(self.0));
+            }
+        }
+
+        let _reset = Reset(REVEAL.with(|reveal| reveal.replace(true)));
+
+        self.0.fmt(f)
+    }
+}
+
+/// `Debug` view of a credential, redacted unless formatted within [Unredacted](struct.Unredacted.html).
+pub(crate) struct Secret<'a>(pub &'a str);
+
+impl fmt::Debug for Secret<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if REVEAL.with(Cell::get) {
+            fmt::Debug::fmt(self.0, f)
+        } else {
+            f.write_str(REDACTED)
+        }
+    }
+}
+
+macro_rules! redacted_field {
+    ($value:expr) => {
+        $value
+    };
+    ($value:expr, secret) => {
+        &crate::redact::Secret($value)
+    };
+}
+
+/// Implements `Debug` for a struct, redacting the fields marked as `secret`.
+///
+/// Every field must be listed, so a field added later can't silently go missing from the output.
+macro_rules! debug_redacted {
+    ($name:ident { $($field:ident $(: $kind:ident)?),* $(,)? }) => {
+        impl ::std::fmt::Debug for $name {
+            fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+                let $name { $($field),* } = self;
+
+                f.debug_struct(stringify!($name))
+                    $(.field(stringify!($field), redacted_field!($field $(, $kind)?)))*
+                    .finish()
+            }
+        }
+    };
+}
+
+/// Final URL and body of a request with credentials redacted, for troubleshooting.
+///
+/// Returned by [API::sanitized](struct.API.html#method.sanitized).
+#[derive(Clone, Debug, PartialEq)]
+pub struct SanitizedRequest {
+    pub method: Method,
+    pub url: String,
+    pub body: Option<String>,
+}
+
+impl SanitizedRequest {
+    pub(crate) fn new(method: Method, url: &Url, body: Option<Vec<(&str, &str)>>) -> Self {
+        let url = redact_url(url);
+
+        let body = body.map(|params| {
+            form_urlencoded::Serializer::new(String::new())
+                .extend_pairs(
+                    params
+                        .into_iter()
+                        .map(|(key, value)| redact_pair(key, value)),
+                )
+                .finish()
+        });
+
+        Self {
+            method,
+            url: url.to_string(),
+            body,
+        }
+    }
+}
+
+impl fmt::Display for SanitizedRequest {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {}", self.method, self.url)?;
+
+        if let Some(ref body) = self.body {
+            write!(f, "\n\n{}", body)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// An error from the HTTP client, with the credentials in the request URL redacted from its
+/// message.
+///
+/// Wrapped by [ErrorKind::Reqqest](enum.ErrorKind.html#variant.Reqqest).
+pub struct TransportError {
+    error: reqwest::Error,
+    message: String,
+}
+
+impl TransportError {
+    /// The error as returned by `reqwest`. Its `Display` and `Debug` output include the full
+    /// request URL, credentials included.
+    pub fn unredacted(&self) -> &reqwest::Error {
+        &self.error
+    }
+
+    pub fn is_timeout(&self) -> bool {
+        self.error.is_timeout()
+    }
+
+    /// Whether the response arrived but its body couldn't be read.
+    pub fn is_decode(&self) -> bool {
+        self.error.is_decode()
+    }
+}
+
+impl From<reqwest::Error> for TransportError {
+    fn from(error: reqwest::Error) -> Self {
+        let mut message = error.to_string();
+
+        if let Some(url) = error.url() {
+            message = message.replace(url.as_str(), redact_url(url).as_str());
+        }
+
+        Self { error, message }
+    }
+}
+
+impl fmt::Display for TransportError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl fmt::Debug for TransportError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("TransportError")
+            .field("message", &self.message)
+            .finish()
+    }
+}
+
+impl StdError for TransportError {
+    // Skips the `reqwest` error itself, whose message has the URL in it.
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        self.error.source()
+    }
+}
+
+fn redact_url(url: &Url) -> Url {
+    let mut url = url.clone();
+
+    if url.query().is_some() {
+        let pairs: Vec<(String, String)> = url
+            .query_pairs()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect();
+
+        url.query_pairs_mut()
+            .clear()
+            .extend_pairs(pairs.iter().map(|(key, value)| redact_pair(key, value)));
+    }
+
+    url
+}
+
+fn redact_pair<'a>(key: &'a str, value: &'a str) -> (&'a str, &'a str) {
+    if SENSITIVE_PARAMETERS.contains(&key) {
+        (key, REDACTED)
+    } else {
+        (key, value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Credentials {
+        user: String,
+        token: String,
+    }
+
+    debug_redacted!(Credentials {
+        user,
+        token: secret,
+    });
+
+    #[test]
+    fn debug_redacts_secrets() {
+        let credentials = Credentials {
+            user: String::from("user_key"),
+            token: String::from("app_token"),
+        };
+
+        assert_eq!(
+            format!("{:?}", credentials),
+            "Credentials { user: \"user_key\", token: REDACTED }"
+        );
+    }
+
+    #[test]
+    fn unredacted_reveals_secrets() {
+        let credentials = Credentials {
+            user: String::from("user_key"),
+            token: String::from("app_token"),
+        };
+
+        assert_eq!(
+            format!("{:?}", Unredacted(&credentials)),
+            "Credentials { user: \"user_key\", token: \"app_token\" }"
+        );
+        assert_eq!(
+            format!("{:?}", credentials),
+            "Credentials { user: \"user_key\", token: REDACTED }"
+        );
+    }
+
+    #[test]
+    fn sanitized_request_redacts_url_and_body() {
+        let url =
+            Url::parse("https://api.pushover.net/1/messages.json?token=abc&user=def").unwrap();
+        let req = SanitizedRequest::new(
+            Method::POST,
+            &url,
+            Some(vec![("email", "email@email.com"), ("password", "pass")]),
+        );
+
+        assert_eq!(
+            req.url,
+            "https://api.pushover.net/1/messages.json?token=REDACTED&user=def"
+        );
+        assert_eq!(
+            req.body.as_deref(),
+            Some("email=email%40email.com&password=REDACTED")
+        );
+    }
+}
@@ -1,9 +1,10 @@
 use std::fmt;
 
-use crate::error::ErrorKind;
+use crate::error::{Error, ErrorKind};
+use crate::types::Priority;
 use reqwest::Method;
 use serde::de::{DeserializeOwned, Deserializer};
-use serde::{Deserialize, Serialize};
+use serde::Deserialize;
 use url::form_urlencoded::Serializer;
 use url::{Url, UrlQuery};
 
@@ -11,18 +12,7 @@ use std::fmt::Debug;
 
 pub trait RawResponse: DeserializeOwned + 'static {
     fn get_error(&self) -> Option<crate::error::ErrorKind> {
-        if self.status() != 1 {
-            Some(crate::error::ErrorKind::PushoverError {
-                status: self.status(),
-                request: self.request().to_string(),
-                errors: self
-                    .errors()
-                    .clone()
-                    .expect("Expected error array from Pushover API"),
-            })
-        } else {
-            None
-        }
+        pushover_error(self)
     }
 
     fn status(&self) -> i32;
@@ -32,6 +22,46 @@ pub trait RawResponse: DeserializeOwned + 'static {
     fn errors(&self) -> &Option<Vec<String>>;
 }
 
+/// The generic error for a response whose status isn't 1.
+pub(crate) fn pushover_error<R: RawResponse>(raw: &R) -> Option<ErrorKind> {
+    if raw.status() != 1 {
+        Some(ErrorKind::PushoverError {
+            status: raw.status(),
+            request: raw.request().to_string(),
+            errors: raw
+                .errors()
+                .clone()
+                .expect("Expected error array from Pushover API"),
+        })
+    } else {
+        None
+    }
+}
+
+/// The error for a response whose status isn't 1, naming the recipient field Pushover rejected,
+/// `user` or `device`, if the response has one.
+pub(crate) fn recipient_error<R: RawResponse>(
+    raw: &R,
+    user: &Option<String>,
+    device: &Option<String>,
+) -> Option<ErrorKind> {
+    let field = match (user, device) {
+        (Some(_), _) => "user",
+        (None, Some(_)) => "device",
+        (None, None) => return pushover_error(raw),
+    };
+
+    if raw.status() != 1 {
+        Some(ErrorKind::InvalidRecipient {
+            field: field.to_owned(),
+            errors: raw.errors().clone().unwrap_or_default(),
+            request: raw.request().to_string(),
+        })
+    } else {
+        None
+    }
+}
+
 macro_rules! raw_response_basic_getters {
     () => {
         fn status(&self) -> i32 {
@@ -61,6 +91,21 @@ pub trait Request {
     fn get_form_parameters(&self) -> Option<Vec<(&str, &str)>> {
         None
     }
+
+    fn validate(&self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// Fails if the request can't be sent as a single call, whether or not validation is enabled.
+    fn check_single(&self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// Priority of the message this request sends, for requests that count against the app's
+    /// message quota.
+    fn message_priority(&self) -> Option<Priority> {
+        None
+    }
 }
 
 #[derive(Debug)]
@@ -107,6 +152,7 @@ where
 #[cfg(test)]
 mod tests {
     use super::*;
+    use serde::Serialize;
 
     #[derive(Debug, PartialEq)]
     pub struct TestRequest {}
@@ -0,0 +1,104 @@
+use crate::error::Error;
+use crate::requests::glance::Glance;
+
+/// Chaining builder for [Glance](struct.Glance.html).
+///
+/// ```rust
+/// use pushover::requests::glance::Glance;
+///
+/// let glance = Glance::builder("token", "uQiRzpo4DXghDmr9QzzfQu27cmVRsG")
+///     .title("Deploy")
+///     .percent(40)
+///     .build()
+///     .expect("Invalid glance");
+/// ```
+#[derive(Clone, Debug)]
+pub struct GlanceBuilder {
+    glance: Glance,
+}
+
+impl GlanceBuilder {
+    pub(crate) fn new(glance: Glance) -> Self {
+        Self { glance }
+    }
+
+    pub fn device<T: Into<String>>(mut self, device: T) -> Self {
+        self.glance.set_device(device);
+        self
+    }
+
+    pub fn title<T: Into<String>>(mut self, title: T) -> Self {
+        self.glance.set_title(title);
+        self
+    }
+
+    pub fn text<T: Into<String>>(mut self, text: T) -> Self {
+        self.glance.set_text(text);
+        self
+    }
+
+    pub fn subtext<T: Into<String>>(mut self, subtext: T) -> Self {
+        self.glance.set_subtext(subtext);
+        self
+    }
+
+    pub fn count(mut self, count: i32) -> Self {
+        self.glance.set_count(count);
+        self
+    }
+
+    pub fn percent(mut self, percent: u8) -> Self {
+        self.glance.set_percent(percent);
+        self
+    }
+
+    /// Returns the glance, or every field that breaks Pushover's limits.
+    pub fn build(self) -> Result<Glance, Error> {
+        self.glance.validate()?;
+
+        Ok(self.glance)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ErrorKind;
+
+    const USER_KEY: &str = "uQiRzpo4DXghDmr9QzzfQu27cmVRsG";
+
+    #[test]
+    fn build_sets_fields() {
+        let glance = Glance::builder("token", USER_KEY)
+            .device("device")
+            .title("title")
+            .text("text")
+            .subtext("subtext")
+            .count(3)
+            .percent(50)
+            .build()
+            .unwrap();
+
+        let mut expected = Glance::new("token", USER_KEY);
+        expected.set_device("device");
+        expected.set_title("title");
+        expected.set_text("text");
+        expected.set_subtext("subtext");
+        expected.set_count(3);
+        expected.set_percent(50);
+
+        assert_eq!(glance, expected);
+    }
+
+    #[test]
+    fn build_rejects_empty_glance() {
+        let result = Glance::builder("token", USER_KEY).device("device").build();
+
+        match result.expect_err("Expected error") {
+            Error(ErrorKind::InvalidRequest(violations), _) => {
+                assert_eq!(violations.len(), 1);
+            }
+            _ => panic!("Did not receive InvalidRequest"),
+        }
+    }
+}
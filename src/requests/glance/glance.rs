@@ -1,12 +1,15 @@
 use reqwest::Method;
 use url::Url;
 
+use crate::error::Error;
 use crate::requests::base::{add_optional_param, RawBasicResponse, Request};
+use crate::requests::glance::GlanceBuilder;
+use crate::validation::{Validator, MAX_GLANCE_FIELD_LENGTH, MAX_GLANCE_PERCENT};
 
 /// Send a Glance request
 ///
 /// Return type is `String` which is the request parameter (https://pushover.net/api#response).
-#[derive(Clone, Debug, PartialEq, PartialOrd)]
+#[derive(Clone, PartialEq, PartialOrd)]
 pub struct Glance {
     pub token: String,
     pub user_key: String,
@@ -18,6 +21,17 @@ pub struct Glance {
     pub percent: Option<u8>,
 }
 
+debug_redacted!(Glance {
+    token: secret,
+    user_key,
+    device,
+    title,
+    text,
+    subtext,
+    count,
+    percent,
+});
+
 impl Glance {
     pub fn new<T, U>(token: T, user_key: U) -> Self
     where
@@ -36,6 +50,15 @@ impl Glance {
         }
     }
 
+    /// Start a [GlanceBuilder](struct.GlanceBuilder.html).
+    pub fn builder<T, U>(token: T, user_key: U) -> GlanceBuilder
+    where
+        T: Into<String>,
+        U: Into<String>,
+    {
+        GlanceBuilder::new(Self::new(token, user_key))
+    }
+
     pub fn set_device<T: Into<String>>(&mut self, device: T) {
         self.device = Some(device.into());
     }
@@ -59,6 +82,37 @@ impl Glance {
     pub fn set_percent(&mut self, percent: u8) {
         self.percent = Some(percent);
     }
+
+    /// Check the glance against Pushover's limits, reporting every violation.
+    pub fn validate(&self) -> Result<(), Error> {
+        let mut validator = Validator::new();
+
+        validator.key("user", &self.user_key);
+        if let Some(ref device) = self.device {
+            validator.device_name("device", device);
+        }
+        validator.check(
+            self.title.is_some()
+                || self.text.is_some()
+                || self.subtext.is_some()
+                || self.count.is_some()
+                || self.percent.is_some(),
+            "glance",
+            "requires at least one of title, text, subtext, count or percent",
+        );
+        validator.optional_max_length("title", &self.title, MAX_GLANCE_FIELD_LENGTH);
+        validator.optional_max_length("text", &self.text, MAX_GLANCE_FIELD_LENGTH);
+        validator.optional_max_length("subtext", &self.subtext, MAX_GLANCE_FIELD_LENGTH);
+        if let Some(percent) = self.percent {
+            validator.check(
+                percent <= MAX_GLANCE_PERCENT,
+                "percent",
+                format!("must be between 0 and {}", MAX_GLANCE_PERCENT),
+            );
+        }
+
+        validator.finish()
+    }
 }
 
 impl Request for Glance {
@@ -86,6 +140,10 @@ impl Request for Glance {
     fn map(raw: Self::RawResponseType) -> Self::ResponseType {
         raw.request
     }
+
+    fn validate(&self) -> Result<(), Error> {
+        Glance::validate(self)
+    }
 }
 
 #[cfg(test)]
@@ -109,10 +167,10 @@ mod tests {
             Some(&[
                 ("token", &req.token),
                 ("user", &req.user_key),
-                ("device", &req.device.as_ref().unwrap()),
-                ("title", &req.title.as_ref().unwrap()),
-                ("text", &req.text.as_ref().unwrap()),
-                ("subtext", &req.subtext.as_ref().unwrap()),
+                ("device", req.device.as_ref().unwrap()),
+                ("title", req.title.as_ref().unwrap()),
+                ("text", req.text.as_ref().unwrap()),
+                ("subtext", req.subtext.as_ref().unwrap()),
                 ("count", &req.count.unwrap().to_string()),
                 ("percent", &req.percent.unwrap().to_string()),
             ]),
@@ -129,4 +187,22 @@ mod tests {
             Some(&[("token", &req.token), ("user", &req.user_key)]),
         );
     }
+
+    #[test]
+    fn validate_reports_every_violation() {
+        let mut req = Glance::new("glance_token", "uQiRzpo4DXghDmr9QzzfQu27cmVRsG");
+        req.set_title("t".repeat(101));
+        req.set_text("text");
+        req.set_subtext("s".repeat(101));
+        req.set_percent(101);
+
+        match req.validate().expect_err("Expected error") {
+            crate::Error(crate::ErrorKind::InvalidRequest(violations), _) => {
+                let fields: Vec<&str> = violations.iter().map(|v| v.field).collect();
+
+                assert_eq!(fields, vec!["title", "subtext", "percent"]);
+            }
+            _ => panic!("Did not receive InvalidRequest"),
+        }
+    }
 }
@@ -1,4 +1,7 @@
 //! https://pushover.net/api/glances
+mod builder;
+#[allow(clippy::module_inception)]
 mod glance;
 
+pub use self::builder::GlanceBuilder;
 pub use self::glance::Glance;
\ No newline at end of file
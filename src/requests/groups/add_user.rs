@@ -1,19 +1,27 @@
 use reqwest::Method;
 use url::Url;
 
+use crate::error::Error;
 use crate::requests::base::{add_optional_param, RawBasicResponse, Request};
 use crate::types::User;
+use crate::validation::{Validator, MAX_MEMO_LENGTH};
 
 /// Add a user to a group
 ///
 /// Return type is `String` which is the request parameter (https://pushover.net/api#response).
-#[derive(Clone, Debug, PartialEq, PartialOrd)]
+#[derive(Clone, PartialEq, PartialOrd)]
 pub struct AddUser {
     pub token: String,
     pub group_key: String,
     pub user: User,
 }
 
+debug_redacted!(AddUser {
+    token: secret,
+    group_key,
+    user,
+});
+
 impl AddUser {
     pub fn new<R, T>(token: T, group_key: R, user: &User) -> Self
     where
@@ -26,6 +34,20 @@ impl AddUser {
             user: user.clone(),
         }
     }
+
+    /// Check the group key and user against Pushover's limits, reporting every violation.
+    pub fn validate(&self) -> Result<(), Error> {
+        let mut validator = Validator::new();
+
+        validator.key("group", &self.group_key);
+        validator.key("user", &self.user.user);
+        if let Some(ref device) = self.user.device {
+            validator.device_name("device", device);
+        }
+        validator.optional_max_length("memo", &self.user.memo, MAX_MEMO_LENGTH);
+
+        validator.finish()
+    }
 }
 
 impl Request for AddUser {
@@ -53,6 +75,10 @@ impl Request for AddUser {
     fn map(raw: Self::RawResponseType) -> Self::ResponseType {
         raw.request
     }
+
+    fn validate(&self) -> Result<(), Error> {
+        AddUser::validate(self)
+    }
 }
 
 #[cfg(test)]
@@ -92,4 +118,22 @@ mod tests {
             Some(&[("token", &req.token), ("user", &req.user.user)]),
         );
     }
+
+    #[test]
+    fn validate_reports_every_violation() {
+        let mut user = User::new("not a key");
+        user.set_device("not a device");
+        user.set_memo("m".repeat(201));
+
+        let req = AddUser::new("add_token", "gznej3rKEVAvPUxu9vvNnqpmZpokzF", &user);
+
+        match req.validate().expect_err("Expected error") {
+            crate::Error(crate::ErrorKind::InvalidRequest(violations), _) => {
+                let fields: Vec<&str> = violations.iter().map(|v| v.field).collect();
+
+                assert_eq!(fields, vec!["user", "device", "memo"]);
+            }
+            _ => panic!("Did not receive InvalidRequest"),
+        }
+    }
 }
@@ -1,19 +1,30 @@
+use std::borrow::Cow;
+use std::io::Write;
+
 use reqwest::Method;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use url::Url;
 
+use crate::csv;
+use crate::error::Error;
 use crate::requests::base::{RawResponse, Request};
 use crate::types::User;
+use crate::validation::Validator;
 
 /// Retrieve users of a group
 ///
 /// Return type is [ListUsersResponse](struct.ListUsersResponse.html)
-#[derive(Clone, Debug, PartialEq, PartialOrd)]
+#[derive(Clone, PartialEq, PartialOrd)]
 pub struct ListUsers {
     pub token: String,
     pub group_key: String,
 }
 
+debug_redacted!(ListUsers {
+    token: secret,
+    group_key,
+});
+
 impl ListUsers {
     pub fn new<R, T>(token: T, group_key: R) -> Self
     where
@@ -25,6 +36,15 @@ impl ListUsers {
             group_key: group_key.into(),
         }
     }
+
+    /// Check the group key, reporting every violation.
+    pub fn validate(&self) -> Result<(), Error> {
+        let mut validator = Validator::new();
+
+        validator.key("group", &self.group_key);
+
+        validator.finish()
+    }
 }
 
 impl Request for ListUsers {
@@ -52,6 +72,10 @@ impl Request for ListUsers {
             users: raw.users.unwrap(),
         }
     }
+
+    fn validate(&self) -> Result<(), Error> {
+        ListUsers::validate(self)
+    }
 }
 
 #[derive(Clone, Debug, PartialEq, PartialOrd)]
@@ -62,6 +86,51 @@ pub struct ListUsersResponse {
     pub users: Vec<User>,
 }
 
+/// Columns of a group exported with [ListUsersResponse::write_csv](#method.write_csv).
+pub(crate) const CSV_HEADER: [&str; 5] = ["group", "user", "device", "memo", "disabled"];
+
+/// Group as exported with [ListUsersResponse::write_json](#method.write_json).
+#[derive(Deserialize, Serialize)]
+pub(crate) struct GroupExport<'a> {
+    pub name: Cow<'a, str>,
+    pub users: Cow<'a, [User]>,
+}
+
+impl ListUsersResponse {
+    /// Write the group's members as CSV, one row per user under a
+    /// `group,user,device,memo,disabled` header.
+    pub fn write_csv<W: Write>(&self, mut writer: W) -> Result<(), Error> {
+        csv::write_row(&mut writer, &CSV_HEADER)?;
+
+        for user in &self.users {
+            csv::write_row(
+                &mut writer,
+                &[
+                    &self.name,
+                    &user.user,
+                    user.device.as_deref().unwrap_or(""),
+                    user.memo.as_deref().unwrap_or(""),
+                    if user.disabled { "true" } else { "false" },
+                ],
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// Write the group's name and members as JSON.
+    pub fn write_json<W: Write>(&self, writer: W) -> Result<(), Error> {
+        let export = GroupExport {
+            name: self.name.as_str().into(),
+            users: self.users.as_slice().into(),
+        };
+
+        serde_json::to_writer_pretty(writer, &export)?;
+
+        Ok(())
+    }
+}
+
 #[derive(Deserialize)]
 pub struct RawListUsersResponse {
     pub status: i32,
@@ -80,6 +149,46 @@ mod tests {
     use super::*;
     use crate::test::assert_req_url;
 
+    fn group() -> ListUsersResponse {
+        let mut alice = User::new("uQiRzpo4DXghDmr9QzzfQu27cmVRsG");
+        alice.set_device("phone");
+        alice.set_memo("Alice, on call");
+        let mut bob = User::new("gznej3rKEVAvPUxu9vvNnqpmZpokzF");
+        bob.disabled = true;
+
+        ListUsersResponse {
+            request: String::from("request"),
+            name: String::from("ops"),
+            users: vec![alice, bob],
+        }
+    }
+
+    #[test]
+    fn writes_csv() {
+        let mut out = Vec::new();
+        group().write_csv(&mut out).unwrap();
+
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "group,user,device,memo,disabled\r\n\
+             ops,uQiRzpo4DXghDmr9QzzfQu27cmVRsG,phone,\"Alice, on call\",false\r\n\
+             ops,gznej3rKEVAvPUxu9vvNnqpmZpokzF,,,true\r\n"
+        );
+    }
+
+    #[test]
+    fn writes_json() {
+        let mut out = Vec::new();
+        group().write_json(&mut out).unwrap();
+
+        let json: serde_json::Value = serde_json::from_slice(&out).unwrap();
+
+        assert_eq!(json["name"], "ops");
+        assert_eq!(json["users"][0]["memo"], "Alice, on call");
+        assert_eq!(json["users"][1]["device"], serde_json::Value::Null);
+        assert_eq!(json["users"][1]["disabled"], true);
+    }
+
     #[test]
     fn get_url() {
         let req = ListUsers::new("get_token", "get_group_key");
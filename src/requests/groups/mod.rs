@@ -6,6 +6,7 @@ mod rename;
 mod toggle_user;
 
 pub use self::add_user::AddUser;
+pub(crate) use self::list_users::{GroupExport, CSV_HEADER};
 pub use self::list_users::{ListUsers, ListUsersResponse};
 pub use self::remove_user::RemoveUser;
 pub use self::rename::Rename;
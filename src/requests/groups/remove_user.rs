@@ -1,18 +1,29 @@
 use reqwest::Method;
 use url::Url;
 
-use crate::requests::base::{RawBasicResponse, Request};
+use crate::error::Error;
+use crate::requests::base::{add_optional_param, RawBasicResponse, Request};
+use crate::validation::Validator;
 
 /// Remove a user from a group
 ///
 /// Return type is `String` which is the request parameter (https://pushover.net/api#response).
-#[derive(Clone, Debug, PartialEq, PartialOrd)]
+#[derive(Clone, PartialEq, PartialOrd)]
 pub struct RemoveUser {
     pub token: String,
     pub group_key: String,
     pub user_key: String,
+    /// Only the user's membership with this device, when they are in the group more than once.
+    pub device: Option<String>,
 }
 
+debug_redacted!(RemoveUser {
+    token: secret,
+    group_key,
+    user_key,
+    device,
+});
+
 impl RemoveUser {
     pub fn new<G, T, U>(token: T, group_key: G, user_key: U) -> Self
     where
@@ -24,8 +35,26 @@ impl RemoveUser {
             token: token.into(),
             group_key: group_key.into(),
             user_key: user_key.into(),
+            device: None,
         }
     }
+
+    pub fn set_device<D: Into<String>>(&mut self, device: D) {
+        self.device = Some(device.into());
+    }
+
+    /// Check the group and user keys and the device name, reporting every violation.
+    pub fn validate(&self) -> Result<(), Error> {
+        let mut validator = Validator::new();
+
+        validator.key("group", &self.group_key);
+        validator.key("user", &self.user_key);
+        if let Some(ref device) = self.device {
+            validator.device_name("device", device);
+        }
+
+        validator.finish()
+    }
 }
 
 impl Request for RemoveUser {
@@ -42,6 +71,7 @@ impl Request for RemoveUser {
         let mut params = url.query_pairs_mut();
         params.append_pair("token", &self.token);
         params.append_pair("user", &self.user_key);
+        add_optional_param(&mut params, "device", &self.device);
     }
 
     fn get_method(&self) -> Method {
@@ -51,6 +81,10 @@ impl Request for RemoveUser {
     fn map(raw: Self::RawResponseType) -> Self::ResponseType {
         raw.request
     }
+
+    fn validate(&self) -> Result<(), Error> {
+        RemoveUser::validate(self)
+    }
 }
 
 #[cfg(test)]
@@ -68,4 +102,20 @@ mod tests {
             Some(&[("token", &req.token), ("user", &req.user_key)]),
         );
     }
+
+    #[test]
+    fn get_url_with_device() {
+        let mut req = RemoveUser::new("remove_token", "remove_group_key", "remove_user_key");
+        req.set_device("phone");
+
+        assert_req_url(
+            &req,
+            &format!("groups/{}/delete_user.json", req.group_key),
+            Some(&[
+                ("token", &req.token),
+                ("user", &req.user_key),
+                ("device", "phone"),
+            ]),
+        );
+    }
 }
@@ -1,18 +1,26 @@
 use reqwest::Method;
 use url::Url;
 
+use crate::error::Error;
 use crate::requests::base::{RawBasicResponse, Request};
+use crate::validation::Validator;
 
 /// Rename a group
 ///
 /// Return type is `String` which is the request parameter (https://pushover.net/api#response).
-#[derive(Clone, Debug, PartialEq, PartialOrd)]
+#[derive(Clone, PartialEq, PartialOrd)]
 pub struct Rename {
     pub token: String,
     pub group_key: String,
     pub name: String,
 }
 
+debug_redacted!(Rename {
+    token: secret,
+    group_key,
+    name,
+});
+
 impl Rename {
     pub fn new<G, N, T>(token: T, group_key: G, name: N) -> Self
     where
@@ -26,6 +34,16 @@ impl Rename {
             name: name.into(),
         }
     }
+
+    /// Check the group key and name, reporting every violation.
+    pub fn validate(&self) -> Result<(), Error> {
+        let mut validator = Validator::new();
+
+        validator.key("group", &self.group_key);
+        validator.not_empty("name", &self.name);
+
+        validator.finish()
+    }
 }
 
 impl Request for Rename {
@@ -51,6 +69,10 @@ impl Request for Rename {
     fn map(raw: Self::RawResponseType) -> Self::ResponseType {
         raw.request
     }
+
+    fn validate(&self) -> Result<(), Error> {
+        Rename::validate(self)
+    }
 }
 
 #[cfg(test)]
@@ -8,13 +8,19 @@ use crate::types::{OperatingSystem, UserType};
 /// Assign a license
 ///
 /// Return type is [CheckCreditsResponse](struct.CheckCreditsResponse.html).
-#[derive(Clone, Debug, PartialEq, PartialOrd)]
+#[derive(Clone, PartialEq, PartialOrd)]
 pub struct Assign {
     pub token: String,
     pub os: Option<OperatingSystem>,
     pub user_type: UserType,
 }
 
+debug_redacted!(Assign {
+    token: secret,
+    os,
+    user_type,
+});
+
 impl Assign {
     pub fn new<T>(token: T, user_type: UserType) -> Self
     where
@@ -7,11 +7,15 @@ use crate::requests::base::{RawResponse, Request};
 /// Check license credits
 ///
 /// Return type is [CheckCreditsResponse](struct.CheckCreditsResponse.html).
-#[derive(Clone, Debug, PartialEq, PartialOrd)]
+#[derive(Clone, PartialEq, PartialOrd)]
 pub struct CheckCredits {
     pub token: String,
 }
 
+debug_redacted!(CheckCredits {
+    token: secret,
+});
+
 impl CheckCredits {
     pub fn new<T>(token: T) -> Self
     where
@@ -0,0 +1,152 @@
+use std::time::SystemTime;
+
+use crate::error::Error;
+use crate::requests::message::{Overflow, SendMessage};
+use crate::types::{EmergencyOptions, Priority, Sound};
+
+/// Chaining builder for [SendMessage](struct.SendMessage.html).
+///
+/// ```rust
+/// use pushover::{Priority, Sound};
+/// use pushover::requests::message::SendMessage;
+///
+/// let msg = SendMessage::builder("token", "uQiRzpo4DXghDmr9QzzfQu27cmVRsG", "hello")
+///     .title("Build")
+///     .priority(Priority::High)
+///     .sound(Sound::Siren)
+///     .device("phone")
+///     .build()
+///     .expect("Invalid message");
+/// ```
+#[derive(Clone, Debug)]
+pub struct SendMessageBuilder {
+    message: SendMessage,
+}
+
+impl SendMessageBuilder {
+    pub(crate) fn new(message: SendMessage) -> Self {
+        Self { message }
+    }
+
+    pub fn device<T: Into<String>>(mut self, device: T) -> Self {
+        self.message.add_device(device);
+        self
+    }
+
+    pub fn title<T: Into<String>>(mut self, title: T) -> Self {
+        self.message.set_title(title);
+        self
+    }
+
+    pub fn url<T: Into<String>>(mut self, url: T) -> Self {
+        self.message.set_url(url);
+        self
+    }
+
+    pub fn url_title<T: Into<String>>(mut self, title: T) -> Self {
+        self.message.set_url_title(title);
+        self
+    }
+
+    pub fn timestamp<T: Into<SystemTime>>(mut self, timestamp: T) -> Self {
+        self.message.set_timestamp(timestamp);
+        self
+    }
+
+    pub fn priority(mut self, priority: Priority) -> Self {
+        self.message.set_priority(priority);
+        self
+    }
+
+    pub fn emergency(mut self, options: EmergencyOptions) -> Self {
+        self.message.set_emergency(options);
+        self
+    }
+
+    pub fn sound(mut self, sound: Sound) -> Self {
+        self.message.set_sound(sound);
+        self
+    }
+
+    pub fn html(mut self, html: bool) -> Self {
+        self.message.set_html(html);
+        self
+    }
+
+    pub fn overflow(mut self, overflow: Overflow) -> Self {
+        self.message.set_overflow(overflow);
+        self
+    }
+
+    /// Returns the message, or every field that breaks Pushover's limits or doesn't fit with the
+    /// rest of the message.
+    pub fn build(self) -> Result<SendMessage, Error> {
+        self.message.validate()?;
+
+        Ok(self.message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ErrorKind;
+
+    const USER_KEY: &str = "uQiRzpo4DXghDmr9QzzfQu27cmVRsG";
+
+    #[test]
+    fn build_sets_fields() {
+        let msg = SendMessage::builder("token", USER_KEY, "message")
+            .title("title")
+            .url("https://example.com")
+            .url_title("example")
+            .priority(Priority::High)
+            .sound(Sound::Bike)
+            .device("device_1")
+            .device("device_2")
+            .build()
+            .unwrap();
+
+        let mut expected = SendMessage::new("token", USER_KEY, "message");
+        expected.set_title("title");
+        expected.set_url("https://example.com");
+        expected.set_url_title("example");
+        expected.set_priority(Priority::High);
+        expected.set_sound(Sound::Bike);
+        expected.add_device("device_1");
+        expected.add_device("device_2");
+
+        assert_eq!(msg, expected);
+    }
+
+    #[test]
+    fn build_rejects_url_title_without_url() {
+        let result = SendMessage::builder("token", USER_KEY, "message")
+            .url_title("example")
+            .build();
+
+        match result.expect_err("Expected error") {
+            Error(ErrorKind::InvalidRequest(violations), _) => {
+                assert_eq!(violations.len(), 1);
+                assert_eq!(violations[0].field, "url_title");
+            }
+            _ => panic!("Did not receive InvalidRequest"),
+        }
+    }
+
+    #[test]
+    fn build_rejects_emergency_without_retry_and_expire() {
+        let result = SendMessage::builder("token", USER_KEY, "message")
+            .priority(Priority::Emergency)
+            .build();
+
+        match result.expect_err("Expected error") {
+            Error(ErrorKind::InvalidRequest(violations), _) => {
+                let fields: Vec<&str> = violations.iter().map(|v| v.field).collect();
+
+                assert_eq!(fields, vec!["priority"]);
+            }
+            _ => panic!("Did not receive InvalidRequest"),
+        }
+    }
+}
@@ -1,17 +1,24 @@
+use std::time::SystemTime;
+
 use reqwest::Method;
 use serde::Deserialize;
 use url::Url;
 
 use crate::requests::base::{RawResponse, Request};
+use crate::types::from_unix_seconds;
 
 /// Get limitations
 ///
 /// Return type is [LimitsResponse](struct.LimitsResponse.html).
-#[derive(Clone, Debug, PartialEq, PartialOrd)]
+#[derive(Clone, PartialEq, PartialOrd)]
 pub struct Limits {
     pub token: String,
 }
 
+debug_redacted!(Limits {
+    token: secret,
+});
+
 impl Limits {
     pub fn new<T>(token: T) -> Self
     where
@@ -46,7 +53,7 @@ impl Request for Limits {
             request: raw.request,
             limit: raw.limit.unwrap(),
             remaining: raw.remaining.unwrap(),
-            reset: raw.reset.unwrap(),
+            reset: from_unix_seconds(raw.reset.unwrap()),
         }
     }
 }
@@ -57,7 +64,7 @@ pub struct LimitsResponse {
     pub request: String,
     pub limit: u32,
     pub remaining: u32,
-    pub reset: u32,
+    pub reset: SystemTime,
 }
 
 #[derive(Debug, Deserialize)]
@@ -67,7 +74,7 @@ pub struct RawLimitsResponse {
     pub errors: Option<Vec<String>>,
     pub limit: Option<u32>,
     pub remaining: Option<u32>,
-    pub reset: Option<u32>,
+    pub reset: Option<u64>,
 }
 
 impl RawResponse for RawLimitsResponse {
@@ -1,6 +1,10 @@
 //! https://pushover.net/api
+mod builder;
 mod limits;
+mod overflow;
 mod send_message;
 
+pub use self::builder::SendMessageBuilder;
 pub use self::limits::{Limits, LimitsResponse};
-pub use self::send_message::{SendMessage, SendMessageResponse};
\ No newline at end of file
+pub use self::overflow::Overflow;
+pub use self::send_message::{RecipientResult, SendMessage, SendMessageResponse};
\ No newline at end of file
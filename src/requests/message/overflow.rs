@@ -0,0 +1,144 @@
+use std::borrow::Cow;
+
+use serde::{Deserialize, Serialize};
+
+use crate::validation::MAX_MESSAGE_LENGTH;
+
+/// What to do with a message longer than Pushover's 1024 character limit.
+///
+/// Set with [SendMessage::set_overflow](struct.SendMessage.html#method.set_overflow).
+#[derive(Clone, Debug, Deserialize, PartialEq, PartialOrd, Serialize)]
+pub enum Overflow {
+    /// Cut the message at a character boundary and end it with `suffix`, e.g. `"…"` or
+    /// `"… (see URL)"`.
+    Truncate { suffix: String },
+    /// Send the message as numbered continuation messages ending in `(1/3)`, `(2/3)`, ... Use
+    /// [API::send_parts](../../struct.API.html#method.send_parts) to send all of them;
+    /// `API::send` rejects a message that needs more than one part with
+    /// [ErrorKind::SplitRequired](../../enum.ErrorKind.html#variant.SplitRequired).
+    Split,
+}
+
+impl Overflow {
+    /// Truncate with an ellipsis.
+    pub fn truncate() -> Self {
+        Overflow::Truncate {
+            suffix: String::from("…"),
+        }
+    }
+
+    pub fn truncate_with<S: Into<String>>(suffix: S) -> Self {
+        Overflow::Truncate {
+            suffix: suffix.into(),
+        }
+    }
+}
+
+pub(crate) fn truncate<'a>(message: &'a str, suffix: &str) -> Cow<'a, str> {
+    if message.chars().count() <= MAX_MESSAGE_LENGTH {
+        return Cow::Borrowed(message);
+    }
+
+    let keep = MAX_MESSAGE_LENGTH.saturating_sub(suffix.chars().count());
+    let mut truncated: String = message.chars().take(keep).collect();
+    truncated.push_str(suffix);
+
+    Cow::Owned(truncated)
+}
+
+pub(crate) fn split(message: &str) -> Vec<String> {
+    let chars: Vec<char> = message.chars().collect();
+
+    if chars.len() <= MAX_MESSAGE_LENGTH {
+        return vec![message.to_owned()];
+    }
+
+    // The numbering takes more room as the number of parts grows, so retry until it fits.
+    let mut count = 2;
+    loop {
+        let numbering = format!(" ({}/{})", count, count).chars().count();
+        let chunks = chunk(&chars, MAX_MESSAGE_LENGTH - numbering);
+
+        if chunks.len() <= count {
+            let total = chunks.len();
+
+            return chunks
+                .into_iter()
+                .enumerate()
+                .map(|(i, chunk)| format!("{} ({}/{})", chunk, i + 1, total))
+                .collect();
+        }
+
+        count = chunks.len();
+    }
+}
+
+/// Splits into chunks of at most `size` characters, preferring to break after whitespace in the
+/// second half of a chunk.
+fn chunk(chars: &[char], size: usize) -> Vec<String> {
+    let mut chunks = Vec::new();
+    let mut rest = chars;
+
+    while rest.len() > size {
+        let end = rest[size / 2..size]
+            .iter()
+            .rposition(|c| c.is_whitespace())
+            .map_or(size, |i| size / 2 + i + 1);
+
+        chunks.push(rest[..end].iter().collect::<String>().trim_end().to_owned());
+        rest = &rest[end..];
+    }
+
+    chunks.push(rest.iter().collect());
+    chunks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn truncate_leaves_short_message() {
+        assert_eq!(truncate("short", "…"), "short");
+    }
+
+    #[test]
+    fn truncate_at_character_boundary() {
+        let message = "é".repeat(2000);
+        let truncated = truncate(&message, "… (see URL)");
+
+        assert_eq!(truncated.chars().count(), MAX_MESSAGE_LENGTH);
+        assert!(truncated.ends_with("é… (see URL)"));
+    }
+
+    #[test]
+    fn split_numbers_parts() {
+        let message = "word ".repeat(500);
+        let parts = split(&message);
+
+        assert_eq!(parts.len(), 3);
+        assert!(parts[0].ends_with("word (1/3)"));
+        assert!(parts[2].ends_with(" (3/3)"));
+        assert!(parts
+            .iter()
+            .all(|p| p.chars().count() <= MAX_MESSAGE_LENGTH));
+    }
+
+    #[test]
+    fn split_without_whitespace() {
+        let message = "ü".repeat(3000);
+        let parts = split(&message);
+
+        assert_eq!(parts.len(), 3);
+        assert!(parts
+            .iter()
+            .all(|p| p.chars().count() <= MAX_MESSAGE_LENGTH));
+        assert_eq!(
+            parts
+                .iter()
+                .map(|p| p.chars().filter(|c| *c == 'ü').count())
+                .sum::<usize>(),
+            3000
+        );
+    }
+}
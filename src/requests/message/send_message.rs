@@ -1,48 +1,93 @@
+use std::borrow::Cow;
+use std::time::SystemTime;
+
 use reqwest::Method;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use url::Url;
 
-use crate::requests::base::{add_optional_param, RawResponse, Request};
-use crate::types::{Priority, Sound};
+use crate::error::{Error, ErrorKind};
+use crate::requests::base::{add_optional_param, recipient_error, RawResponse, Request};
+use crate::requests::message::overflow::{self, Overflow};
+use crate::requests::message::SendMessageBuilder;
+use crate::types::{EmergencyOptions, Priority, Recipients, Sound, TimestampExt};
+use crate::validation::{
+    Validator, MAX_MESSAGE_LENGTH, MAX_RECIPIENTS, MAX_TITLE_LENGTH, MAX_URL_LENGTH,
+    MAX_URL_TITLE_LENGTH,
+};
 
 /// Send a message
 ///
 /// Return type is [SendMessageResponse](struct.SendMessageResponse.html).
-#[derive(Clone, Debug, PartialEq, PartialOrd)]
+#[derive(Clone, Deserialize, PartialEq, PartialOrd, Serialize)]
 pub struct SendMessage {
     pub token: String,
-    pub user_key: String,
+    pub recipients: Recipients,
     pub message: String,
     pub devices: Vec<String>,
     pub title: Option<String>,
     pub url: Option<String>,
     pub url_title: Option<String>,
     pub priority: Option<Priority>,
-    pub timestamp: Option<String>,
+    pub emergency: Option<EmergencyOptions>,
+    pub timestamp: Option<SystemTime>,
     pub sound: Option<Sound>,
+    pub overflow: Option<Overflow>,
+    #[serde(default)]
+    pub html: bool,
 }
 
+debug_redacted!(SendMessage {
+    token: secret,
+    recipients,
+    message,
+    devices,
+    title,
+    url,
+    url_title,
+    priority,
+    emergency,
+    timestamp,
+    sound,
+    overflow,
+    html,
+});
+
 impl SendMessage {
+    /// `user_key` is a single user or group key, a comma-separated list of keys or
+    /// [Recipients](../../struct.Recipients.html).
     pub fn new<M, T, U>(token: T, user_key: U, message: M) -> Self
     where
         T: Into<String>,
-        U: Into<String>,
+        U: Into<Recipients>,
         M: Into<String>,
     {
         Self {
             token: token.into(),
-            user_key: user_key.into(),
+            recipients: user_key.into(),
             message: message.into(),
             devices: Vec::new(),
             title: None,
             url: None,
             url_title: None,
             priority: None,
+            emergency: None,
             timestamp: None,
             sound: None,
+            overflow: None,
+            html: false,
         }
     }
 
+    /// Start a [SendMessageBuilder](struct.SendMessageBuilder.html).
+    pub fn builder<M, T, U>(token: T, user_key: U, message: M) -> SendMessageBuilder
+    where
+        T: Into<String>,
+        U: Into<Recipients>,
+        M: Into<String>,
+    {
+        SendMessageBuilder::new(Self::new(token, user_key, message))
+    }
+
     pub fn add_device<T: Into<String>>(&mut self, device: T) {
         self.devices.push(device.into());
     }
@@ -59,17 +104,139 @@ impl SendMessage {
         self.url_title = Some(title.into());
     }
 
-    pub fn set_timestamp<T: Into<String>>(&mut self, timestamp: T) {
+    /// Time of the event the message is about, shown instead of the time Pushover received it.
+    /// Accepts anything that converts into a `SystemTime`, such as a `chrono::DateTime`.
+    pub fn set_timestamp<T: Into<SystemTime>>(&mut self, timestamp: T) {
         self.timestamp = Some(timestamp.into());
     }
 
+    /// Emergency priority is set with [set_emergency](#method.set_emergency) instead.
     pub fn set_priority(&mut self, priority: Priority) {
         self.priority = Some(priority);
     }
 
+    /// Send with emergency priority, repeating the message as described by `options`.
+    pub fn set_emergency(&mut self, options: EmergencyOptions) {
+        self.priority = Some(Priority::Emergency);
+        self.emergency = Some(options);
+    }
+
     pub fn set_sound(&mut self, sound: Sound) {
         self.sound = Some(sound);
     }
+
+    /// Format the message with Pushover's HTML subset (`<b>`, `<i>`, `<u>`, `<font color>` and
+    /// `<a href>`).
+    pub fn set_html(&mut self, html: bool) {
+        self.html = html;
+    }
+
+    /// Handle messages longer than Pushover's limit instead of having them rejected.
+    pub fn set_overflow(&mut self, overflow: Overflow) {
+        self.overflow = Some(overflow);
+    }
+
+    /// The messages to send for this one once its [Overflow](enum.Overflow.html) policy is
+    /// applied. Each part keeps the title, priority, sound and other fields of the original.
+    pub fn parts(&self) -> Vec<SendMessage> {
+        match self.overflow {
+            Some(Overflow::Split) => overflow::split(&self.message)
+                .into_iter()
+                .map(|message| SendMessage {
+                    message,
+                    overflow: None,
+                    ..self.clone()
+                })
+                .collect(),
+            _ => vec![self.clone()],
+        }
+    }
+
+    /// Copies of this message for each group of at most 50 recipients.
+    pub fn batches(&self) -> Vec<SendMessage> {
+        self.recipients
+            .batches()
+            .into_iter()
+            .map(|recipients| SendMessage {
+                recipients,
+                ..self.clone()
+            })
+            .collect()
+    }
+
+    pub(crate) fn for_recipient(&self, recipient: &str) -> SendMessage {
+        SendMessage {
+            recipients: Recipients::from(recipient),
+            ..self.clone()
+        }
+    }
+
+    /// The message as sent, after truncation.
+    fn wire_message(&self) -> Cow<'_, str> {
+        match self.overflow {
+            Some(Overflow::Truncate { ref suffix }) => overflow::truncate(&self.message, suffix),
+            _ => Cow::Borrowed(&self.message),
+        }
+    }
+
+    /// Check the message against Pushover's limits, reporting every violation.
+    pub fn validate(&self) -> Result<(), Error> {
+        let mut validator = Validator::new();
+
+        validator.check(
+            !self.recipients.is_empty(),
+            "user",
+            "must have at least one recipient",
+        );
+        validator.check(
+            self.recipients.len() <= MAX_RECIPIENTS,
+            "user",
+            format!(
+                "must be at most {} recipients, got {}",
+                MAX_RECIPIENTS,
+                self.recipients.len()
+            ),
+        );
+        for recipient in self.recipients.iter() {
+            validator.key("user", recipient);
+        }
+
+        validator.not_empty("message", &self.message);
+        match self.overflow {
+            Some(Overflow::Split) => {
+                for part in overflow::split(&self.message) {
+                    validator.max_length("message", &part, MAX_MESSAGE_LENGTH);
+                }
+            }
+            _ => validator.max_length("message", &self.wire_message(), MAX_MESSAGE_LENGTH),
+        }
+        validator.optional_max_length("title", &self.title, MAX_TITLE_LENGTH);
+        validator.optional_max_length("url", &self.url, MAX_URL_LENGTH);
+        validator.optional_max_length("url_title", &self.url_title, MAX_URL_TITLE_LENGTH);
+        validator.check(
+            self.url_title.is_none() || self.url.is_some(),
+            "url_title",
+            "requires url",
+        );
+
+        for device in &self.devices {
+            validator.device_name("device", device);
+        }
+
+        let emergency = self.priority == Some(Priority::Emergency);
+        validator.check(
+            !emergency || self.emergency.is_some(),
+            "priority",
+            "emergency priority requires emergency options",
+        );
+        validator.check(
+            emergency || self.emergency.is_none(),
+            "emergency",
+            "requires emergency priority",
+        );
+
+        validator.finish()
+    }
 }
 
 impl Request for SendMessage {
@@ -82,32 +249,37 @@ impl Request for SendMessage {
         let mut params = url.query_pairs_mut();
 
         params.append_pair("token", &self.token);
-        params.append_pair("user", &self.user_key);
-        params.append_pair("message", &self.message);
+        params.append_pair("user", &self.recipients.to_string());
+        params.append_pair("message", &self.wire_message());
         add_optional_param(&mut params, "title", &self.title);
         add_optional_param(&mut params, "url", &self.url);
         add_optional_param(&mut params, "url_title", &self.url_title);
-        add_optional_param(&mut params, "timestamp", &self.timestamp);
+        add_optional_param(
+            &mut params,
+            "timestamp",
+            &self.timestamp.map(|timestamp| timestamp.unix_seconds()),
+        );
         add_optional_param(&mut params, "sound", &self.sound);
 
+        if self.html {
+            params.append_pair("html", "1");
+        }
+
         if !self.devices.is_empty() {
             let list = self.devices.join(",");
 
             params.append_pair("device", &list);
         }
 
-        if let Some(ref value) = self.priority {
-            params.append_pair("priority", &value.to_string());
-
-            if let Priority::Emergency {
-                retry,
-                expire,
-                ref callback_url,
-            } = *value
-            {
-                params.append_pair("retry", &retry.to_string());
-                params.append_pair("expire", &expire.to_string());
-                add_optional_param(&mut params, "callback", callback_url);
+        add_optional_param(&mut params, "priority", &self.priority);
+
+        if let (Some(Priority::Emergency), Some(options)) = (self.priority, &self.emergency) {
+            params.append_pair("retry", &options.retry().as_secs().to_string());
+            params.append_pair("expire", &options.expire().as_secs().to_string());
+            add_optional_param(&mut params, "callback", &options.callback_url());
+
+            if !options.tags().is_empty() {
+                params.append_pair("tags", &options.tags().join(","));
             }
         }
     }
@@ -122,6 +294,24 @@ impl Request for SendMessage {
             receipt: raw.receipt,
         }
     }
+
+    fn validate(&self) -> Result<(), Error> {
+        SendMessage::validate(self)
+    }
+
+    fn check_single(&self) -> Result<(), Error> {
+        match self.overflow {
+            Some(Overflow::Split) => match overflow::split(&self.message).len() {
+                1 => Ok(()),
+                parts => Err(ErrorKind::SplitRequired(parts).into()),
+            },
+            _ => Ok(()),
+        }
+    }
+
+    fn message_priority(&self) -> Option<Priority> {
+        Some(self.priority.unwrap_or(Priority::Normal))
+    }
 }
 
 #[derive(Clone, Debug, PartialEq, PartialOrd)]
@@ -131,22 +321,41 @@ pub struct SendMessageResponse {
     pub request: String,
 }
 
+/// Outcome for one recipient of [API::send_batched](../../struct.API.html#method.send_batched).
+#[derive(Debug)]
+pub struct RecipientResult {
+    pub recipient: String,
+    /// Response of the call that covered the recipient, shared with the rest of its batch.
+    pub result: Result<SendMessageResponse, Error>,
+}
+
 #[derive(Deserialize)]
 pub struct RawSendMessageResponse {
     pub status: i32,
     pub request: String,
     pub errors: Option<Vec<String>>,
     pub receipt: Option<String>,
+    /// Set, to `invalid`, when Pushover rejected the user key.
+    pub user: Option<String>,
+    /// Set, to `invalid`, when Pushover rejected the device name.
+    pub device: Option<String>,
 }
 
 impl RawResponse for RawSendMessageResponse {
     raw_response_basic_getters!();
+
+    fn get_error(&self) -> Option<ErrorKind> {
+        recipient_error(self, &self.user, &self.device)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::test::assert_req_url;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    const USER_KEY: &str = "uQiRzpo4DXghDmr9QzzfQu27cmVRsG";
 
     #[test]
     fn get_url_with_all_fields() {
@@ -155,22 +364,24 @@ mod tests {
         req.set_title("send title");
         req.set_url("send url");
         req.set_url_title("send url title");
-        req.set_timestamp("send timestamp");
+        req.set_timestamp(UNIX_EPOCH + Duration::from_secs(1_600_000_000));
         req.set_priority(Priority::Normal);
         req.set_sound(Sound::Pushover);
+        req.set_html(true);
 
         assert_req_url(
             &req,
             "messages.json",
             Some(&[
                 ("token", &req.token),
-                ("user", &req.user_key),
+                ("user", &req.recipients.to_string()),
                 ("message", &req.message),
                 ("title", req.title.as_ref().unwrap()),
                 ("url", req.url.as_ref().unwrap()),
                 ("url_title", req.url_title.as_ref().unwrap()),
-                ("timestamp", req.timestamp.as_ref().unwrap()),
+                ("timestamp", "1600000000"),
                 ("sound", &req.sound.as_ref().unwrap().to_string()),
+                ("html", "1"),
                 ("device", &req.devices[0]),
                 ("priority", &req.priority.as_ref().unwrap().to_string()),
             ]),
@@ -186,7 +397,7 @@ mod tests {
             "messages.json",
             Some(&[
                 ("token", &req.token),
-                ("user", &req.user_key),
+                ("user", &req.recipients.to_string()),
                 ("message", &req.message),
             ]),
         );
@@ -206,7 +417,7 @@ mod tests {
             "messages.json",
             Some(&[
                 ("token", &req.token),
-                ("user", &req.user_key),
+                ("user", &req.recipients.to_string()),
                 ("message", &req.message),
                 ("device", &list),
             ]),
@@ -216,23 +427,27 @@ mod tests {
     #[test]
     fn get_url_with_emergency_priority_with_callback() {
         let mut req = SendMessage::new("send_token", "send user", "send message");
-        req.set_priority(Priority::Emergency {
-            retry: 10,
-            expire: 20,
-            callback_url: Some(String::from("emergency url")),
-        });
+        req.set_emergency(
+            EmergencyOptions::builder(Duration::from_secs(60), Duration::from_secs(3600))
+                .callback_url(Url::parse("https://example.com/emergency").unwrap())
+                .tag("tag_1")
+                .tag("tag_2")
+                .build()
+                .unwrap(),
+        );
 
         assert_req_url(
             &req,
             "messages.json",
             Some(&[
                 ("token", &req.token),
-                ("user", &req.user_key),
+                ("user", &req.recipients.to_string()),
                 ("message", &req.message),
                 ("priority", &req.priority.as_ref().unwrap().to_string()),
-                ("retry", "10"),
-                ("expire", "20"),
-                ("callback", "emergency url"),
+                ("retry", "60"),
+                ("expire", "3600"),
+                ("callback", "https://example.com/emergency"),
+                ("tags", "tag_1,tag_2"),
             ]),
         );
     }
@@ -240,23 +455,150 @@ mod tests {
     #[test]
     fn get_url_with_emergency_priority_without_callback() {
         let mut req = SendMessage::new("send_token", "send user", "send message");
-        req.set_priority(Priority::Emergency {
-            retry: 10,
-            expire: 20,
-            callback_url: None,
-        });
+        req.set_emergency(
+            EmergencyOptions::builder(Duration::from_secs(60), Duration::from_secs(3600))
+                .build()
+                .unwrap(),
+        );
 
         assert_req_url(
             &req,
             "messages.json",
             Some(&[
                 ("token", &req.token),
-                ("user", &req.user_key),
+                ("user", &req.recipients.to_string()),
                 ("message", &req.message),
                 ("priority", &req.priority.as_ref().unwrap().to_string()),
-                ("retry", "10"),
-                ("expire", "20"),
+                ("retry", "60"),
+                ("expire", "3600"),
+            ]),
+        );
+    }
+
+    #[test]
+    fn validate_accepts_message_within_limits() {
+        let mut req = SendMessage::new("send_token", USER_KEY, "send message");
+        req.add_device("device-1");
+        req.set_title("send title");
+        req.set_emergency(
+            EmergencyOptions::builder(Duration::from_secs(30), Duration::from_secs(10800))
+                .build()
+                .unwrap(),
+        );
+
+        assert!(req.validate().is_ok());
+    }
+
+    #[test]
+    fn validate_reports_every_violation() {
+        let recipients = vec![USER_KEY; 51].join(",");
+        let mut req = SendMessage::new("send_token", recipients, "m".repeat(1025));
+        req.set_title("t".repeat(251));
+        req.set_url("u".repeat(513));
+        req.set_url_title("u".repeat(101));
+        req.set_priority(Priority::Emergency);
+
+        match req.validate().expect_err("Expected error") {
+            crate::Error(ErrorKind::InvalidRequest(violations), _) => {
+                let fields: Vec<&str> = violations.iter().map(|v| v.field).collect();
+
+                assert_eq!(
+                    fields,
+                    vec!["user", "message", "title", "url", "url_title", "priority"]
+                );
+            }
+            _ => panic!("Did not receive InvalidRequest"),
+        }
+    }
+
+    #[test]
+    fn get_url_with_truncated_message() {
+        let mut req = SendMessage::new("send_token", "send user", "m".repeat(1100));
+        req.set_overflow(Overflow::truncate_with("..."));
+
+        let expected = format!("{}...", "m".repeat(1021));
+
+        assert_req_url(
+            &req,
+            "messages.json",
+            Some(&[
+                ("token", &req.token),
+                ("user", &req.recipients.to_string()),
+                ("message", &expected),
             ]),
         );
     }
+
+    #[test]
+    fn parts_keep_fields_consistent() {
+        let mut req = SendMessage::new("send_token", USER_KEY, "m".repeat(2000));
+        req.set_title("send title");
+        req.set_priority(Priority::High);
+        req.set_sound(Sound::Siren);
+        req.set_overflow(Overflow::Split);
+
+        let parts = req.parts();
+
+        assert_eq!(parts.len(), 2);
+        for part in &parts {
+            assert_eq!(part.title, req.title);
+            assert_eq!(part.priority, req.priority);
+            assert_eq!(part.sound, req.sound);
+            assert!(part.validate().is_ok());
+        }
+        assert!(parts[0].message.ends_with(" (1/2)"));
+        assert!(parts[1].message.ends_with(" (2/2)"));
+    }
+
+    #[test]
+    fn split_message_validates_each_part() {
+        let mut req = SendMessage::new("send_token", USER_KEY, "m".repeat(3000));
+        req.set_overflow(Overflow::Split);
+
+        assert!(req.validate().is_ok());
+    }
+
+    #[test]
+    fn split_message_must_be_sent_in_parts() {
+        let mut req = SendMessage::new("send_token", USER_KEY, "m".repeat(3000));
+        req.set_overflow(Overflow::Split);
+
+        match req.check_single().expect_err("Expected error") {
+            crate::Error(ErrorKind::SplitRequired(3), _) => {}
+            e => panic!("Did not receive SplitRequired: {}", e),
+        }
+
+        req.message = "m".repeat(100);
+        assert!(req.check_single().is_ok());
+    }
+
+    #[test]
+    fn rejected_recipient_maps_to_invalid_recipient() {
+        let error = |body: &str| {
+            serde_json::from_str::<RawSendMessageResponse>(body)
+                .unwrap()
+                .get_error()
+        };
+
+        match error(
+            r#"{"status":0,"user":"invalid","errors":["user identifier is invalid"],"request":"r"}"#,
+        ) {
+            Some(ErrorKind::InvalidRecipient { field, errors, .. }) => {
+                assert_eq!(field, "user");
+                assert_eq!(errors, ["user identifier is invalid"]);
+            }
+            other => panic!("Expected InvalidRecipient, got {:?}", other),
+        }
+        assert!(matches!(
+            error(r#"{"status":0,"device":"invalid","errors":["device name is invalid"],"request":"r"}"#),
+            Some(ErrorKind::InvalidRecipient { ref field, .. }) if field == "device"
+        ));
+        assert!(matches!(
+            error(
+                r#"{"status":0,"token":"invalid","errors":["application token is invalid"],"request":"r"}"#
+            ),
+            Some(ErrorKind::PushoverError { .. })
+        ));
+        assert!(error(r#"{"status":1,"request":"r"}"#).is_none());
+    }
 }
@@ -6,12 +6,17 @@ use crate::requests::base::{RawBasicResponse, Request};
 /// Acknowledge an emergency-priority message
 ///
 /// Return type is `String` which is the request parameter (https://pushover.net/api#response).
-#[derive(Clone, Debug, PartialEq, PartialOrd)]
+#[derive(Clone, PartialEq, PartialOrd)]
 pub struct Acknowledge {
     pub secret: String,
     pub receipt: String,
 }
 
+debug_redacted!(Acknowledge {
+    secret: secret,
+    receipt,
+});
+
 impl Acknowledge {
     pub fn new<S, R>(secret: S, receipt: R) -> Self
     where
@@ -6,13 +6,19 @@ use crate::requests::base::{RawBasicResponse, Request};
 /// Delete Messages
 ///
 /// Return type is `String` which is the request parameter (https://pushover.net/api#response).
-#[derive(Clone, Debug, PartialEq, PartialOrd)]
+#[derive(Clone, PartialEq, PartialOrd)]
 pub struct DeleteMessages {
     pub device_id: String,
     pub secret: String,
     pub message: u32,
 }
 
+debug_redacted!(DeleteMessages {
+    device_id,
+    secret: secret,
+    message,
+});
+
 impl DeleteMessages {
     pub fn new<D, S>(secret: S, device_id: D, message: u32) -> Self
     where
@@ -8,12 +8,17 @@ use crate::types::Message;
 /// Download messages
 ///
 /// Return type is [DownloadMessagesResponse](struct.DownloadMessagesResponse.html).
-#[derive(Clone, Debug, PartialEq, PartialOrd)]
+#[derive(Clone, PartialEq, PartialOrd)]
 pub struct DownloadMessages {
     pub secret: String,
     pub device_id: String,
 }
 
+debug_redacted!(DownloadMessages {
+    secret: secret,
+    device_id,
+});
+
 impl DownloadMessages {
     pub fn new<D, S>(secret: S, device_id: D) -> Self
     where
@@ -74,6 +79,7 @@ impl RawResponse for RawDownloadMessagesResponse {
 mod tests {
     use super::*;
     use crate::test::assert_req_url;
+    use std::time::{Duration, UNIX_EPOCH};
 
     #[test]
     fn get_url() {
@@ -85,4 +91,20 @@ mod tests {
             Some(&[("secret", &req.secret), ("device_id", &req.device_id)]),
         );
     }
+
+    #[test]
+    fn deserialize_message_date() {
+        let raw: RawDownloadMessagesResponse = serde_json::from_str(
+            r#"{"status":1,"request":"request","messages":[{"id":1,"umid":2,"message":"hello",
+                "app":"app","aid":3,"icon":"icon","date":1600000000,"priority":0,"acked":0}]}"#,
+        )
+        .unwrap();
+
+        let response = DownloadMessages::map(raw);
+
+        assert_eq!(
+            response.messages[0].date,
+            UNIX_EPOCH + Duration::from_secs(1_600_000_000)
+        );
+    }
 }
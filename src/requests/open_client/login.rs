@@ -7,12 +7,17 @@ use crate::requests::base::{RawResponse, Request};
 /// Login user
 ///
 /// Return type is [LoginResponse](struct.LoginResponse.html).
-#[derive(Clone, Debug, PartialEq, PartialOrd)]
+#[derive(Clone, PartialEq, PartialOrd)]
 pub struct Login {
     pub email: String,
     pub password: String,
 }
 
+debug_redacted!(Login {
+    email,
+    password: secret,
+});
+
 impl Login {
     pub fn new<E, P>(email: E, password: P) -> Self
     where
@@ -55,13 +60,19 @@ impl Request for Login {
 }
 
 /// Return type for [Login](struct.Login.html)
-#[derive(Clone, Debug, PartialEq, PartialOrd)]
+#[derive(Clone, PartialEq, PartialOrd)]
 pub struct LoginResponse {
     pub id: String,
     pub secret: String,
     pub request: String,
 }
 
+debug_redacted!(LoginResponse {
+    id,
+    secret: secret,
+    request,
+});
+
 #[derive(Deserialize)]
 pub struct RawLoginResponse {
     pub status: i32,
@@ -99,4 +110,14 @@ mod tests {
             req.get_form_parameters()
         );
     }
+
+    #[test]
+    fn debug_redacts_password() {
+        let req = Login::new("email@email.com", "Password!@%d");
+
+        assert_eq!(
+            format!("{:?}", req),
+            "Login { email: \"email@email.com\", password: REDACTED }"
+        );
+    }
 }
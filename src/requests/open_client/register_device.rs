@@ -1,18 +1,30 @@
+use std::collections::BTreeMap;
+
 use reqwest::Method;
 use serde::Deserialize;
 use url::Url;
 
-use crate::requests::base::{RawResponse, Request};
+use crate::error::{Error, ErrorKind};
+use crate::requests::base::{pushover_error, RawResponse, Request};
+use crate::types::DeviceOs;
+use crate::validation::Validator;
 
 /// Register desktop device
 ///
 /// Return type is [RegisterDeviceResponse](struct.RegisterDeviceResponse.html).
-#[derive(Clone, Debug, PartialEq, PartialOrd)]
+#[derive(Clone, PartialEq, PartialOrd)]
 pub struct RegisterDevice {
     pub secret: String,
     pub name: String,
+    pub os: DeviceOs,
 }
 
+debug_redacted!(RegisterDevice {
+    secret: secret,
+    name,
+    os,
+});
+
 impl RegisterDevice {
     pub fn new<N, S>(secret: S, name: N) -> Self
     where
@@ -22,8 +34,23 @@ impl RegisterDevice {
         Self {
             secret: secret.into(),
             name: name.into(),
+            os: DeviceOs::Open,
         }
     }
+
+    pub fn set_os(&mut self, os: DeviceOs) {
+        self.os = os;
+    }
+
+    /// Check the secret and device name, reporting every violation.
+    pub fn validate(&self) -> Result<(), Error> {
+        let mut validator = Validator::new();
+
+        validator.not_empty("secret", &self.secret);
+        validator.device_name("name", &self.name);
+
+        validator.finish()
+    }
 }
 
 impl Request for RegisterDevice {
@@ -49,9 +76,13 @@ impl Request for RegisterDevice {
         Some(vec![
             ("secret", &self.secret),
             ("name", &self.name),
-            ("os", "O"),
+            ("os", self.os.as_str()),
         ])
     }
+
+    fn validate(&self) -> Result<(), Error> {
+        RegisterDevice::validate(self)
+    }
 }
 
 /// Return type for [RegisterDevice](struct.RegisterDevice.html)
@@ -61,16 +92,80 @@ pub struct RegisterDeviceResponse {
     pub id: String,
 }
 
+/// Pushover's reason for rejecting a device name that another device already uses.
+const NAME_TAKEN: &str = "has already been taken";
+
 #[derive(Deserialize)]
+#[serde(from = "WireRegisterDeviceResponse")]
 pub struct RawRegisterDeviceResponse {
     pub status: i32,
     pub request: String,
     pub id: Option<String>,
+    /// Why Pushover rejected the device name, if it did.
+    pub name_errors: Vec<String>,
     pub errors: Option<Vec<String>>,
 }
 
+/// Pushover reports registration errors by field, e.g. `{"name":["has already been taken"]}`,
+/// rather than as the usual array.
+#[derive(Deserialize)]
+struct WireRegisterDeviceResponse {
+    status: i32,
+    request: String,
+    id: Option<String>,
+    errors: Option<WireErrors>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum WireErrors {
+    Messages(Vec<String>),
+    Fields(BTreeMap<String, Vec<String>>),
+}
+
+impl From<WireRegisterDeviceResponse> for RawRegisterDeviceResponse {
+    fn from(wire: WireRegisterDeviceResponse) -> Self {
+        let (name_errors, errors) = match wire.errors {
+            Some(WireErrors::Fields(fields)) => {
+                let name_errors = fields.get("name").cloned().unwrap_or_default();
+                let errors = fields
+                    .iter()
+                    .flat_map(|(field, reasons)| {
+                        reasons
+                            .iter()
+                            .map(move |reason| format!("{} {}", field, reason))
+                    })
+                    .collect();
+
+                (name_errors, Some(errors))
+            }
+            Some(WireErrors::Messages(errors)) => (Vec::new(), Some(errors)),
+            None => (Vec::new(), None),
+        };
+
+        Self {
+            status: wire.status,
+            request: wire.request,
+            id: wire.id,
+            name_errors,
+            errors,
+        }
+    }
+}
+
 impl RawResponse for RawRegisterDeviceResponse {
     raw_response_basic_getters!();
+
+    fn get_error(&self) -> Option<ErrorKind> {
+        match self.name_errors.first() {
+            Some(reason) if self.status != 1 => Some(if reason == NAME_TAKEN {
+                ErrorKind::DeviceNameTaken(reason.clone())
+            } else {
+                ErrorKind::InvalidDeviceName(reason.clone())
+            }),
+            _ => pushover_error(self),
+        }
+    }
 }
 
 #[cfg(test)]
@@ -98,4 +193,62 @@ mod tests {
             req.get_form_parameters()
         );
     }
+
+    #[test]
+    fn get_form_parameters_with_os() {
+        let mut req = RegisterDevice::new("reg_secret", "reg_name");
+        req.set_os(DeviceOs::Open);
+
+        assert_eq!(
+            req.get_form_parameters().unwrap().last(),
+            Some(&("os", "O"))
+        );
+    }
+
+    #[test]
+    fn validate_device_name() {
+        assert!(RegisterDevice::new("reg_secret", "headless_01-a")
+            .validate()
+            .is_ok());
+
+        for name in &["", "has space", "dot.name", "a_name_longer_than_25_chars"] {
+            match RegisterDevice::new("reg_secret", *name).validate() {
+                Err(Error(ErrorKind::InvalidRequest(violations), _)) => {
+                    assert_eq!(violations[0].field, "name");
+                }
+                other => panic!("Expected InvalidRequest for {:?}, got {:?}", name, other),
+            }
+        }
+    }
+
+    #[test]
+    fn name_errors_map_to_variants() {
+        let error = |body: &str| {
+            serde_json::from_str::<RawRegisterDeviceResponse>(body)
+                .unwrap()
+                .get_error()
+        };
+
+        match error(r#"{"errors":{"name":["has already been taken"]},"status":0,"request":"r"}"#) {
+            Some(ErrorKind::DeviceNameTaken(reason)) => {
+                assert_eq!(reason, "has already been taken")
+            }
+            other => panic!("Expected DeviceNameTaken, got {:?}", other),
+        }
+        match error(r#"{"errors":{"name":["is invalid"]},"status":0,"request":"r"}"#) {
+            Some(ErrorKind::InvalidDeviceName(reason)) => assert_eq!(reason, "is invalid"),
+            other => panic!("Expected InvalidDeviceName, got {:?}", other),
+        }
+        match error(r#"{"errors":{"secret":["is invalid"]},"status":0,"request":"r"}"#) {
+            Some(ErrorKind::PushoverError { errors, .. }) => {
+                assert_eq!(errors, ["secret is invalid"])
+            }
+            other => panic!("Expected PushoverError, got {:?}", other),
+        }
+        assert!(matches!(
+            error(r#"{"errors":["secret is invalid"],"status":0,"request":"r"}"#),
+            Some(ErrorKind::PushoverError { .. })
+        ));
+        assert!(error(r#"{"status":1,"request":"r","id":"device"}"#).is_none());
+    }
 }
@@ -6,12 +6,17 @@ use crate::requests::base::{RawBasicResponse, Request};
 /// Cancel an emergency priority notification
 ///
 /// Return type is `String` which is the request parameter (https://pushover.net/api#response).
-#[derive(Clone, Debug, PartialEq, PartialOrd)]
+#[derive(Clone, PartialEq, PartialOrd)]
 pub struct CancelEmergency {
     pub token: String,
     pub receipt: String,
 }
 
+debug_redacted!(CancelEmergency {
+    token: secret,
+    receipt,
+});
+
 impl CancelEmergency {
     pub fn new<R, T>(token: T, receipt: R) -> Self
     where
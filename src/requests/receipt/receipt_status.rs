@@ -1,18 +1,26 @@
+use std::time::SystemTime;
+
 use reqwest::Method;
 use serde::Deserialize;
 use url::Url;
 
 use crate::requests::base::{RawResponse, Request};
+use crate::types::{from_optional_unix_seconds, from_unix_seconds};
 
 /// Retrieve status of emergency notification
 ///
 /// Return type is [ReceiptStatusResponse](struct.ReceiptStatusResponse.html).
-#[derive(Clone, Debug, PartialEq, PartialOrd)]
+#[derive(Clone, PartialEq, PartialOrd)]
 pub struct ReceiptStatus {
     pub token: String,
     pub receipt: String,
 }
 
+debug_redacted!(ReceiptStatus {
+    token: secret,
+    receipt,
+});
+
 impl ReceiptStatus {
     pub fn new<R, T>(token: T, receipt: R) -> Self
     where
@@ -47,14 +55,14 @@ impl Request for ReceiptStatus {
     fn map(raw: Self::RawResponseType) -> Self::ResponseType {
         Self::ResponseType {
             called_back: raw.called_back.unwrap(),
-            called_back_at: raw.called_back_at.unwrap(),
+            called_back_at: from_optional_unix_seconds(raw.called_back_at.unwrap()),
             acknowledged: raw.acknowledged.unwrap(),
-            acknowledged_at: raw.acknowledged_at.unwrap(),
+            acknowledged_at: from_optional_unix_seconds(raw.acknowledged_at.unwrap()),
             acknowledged_by: raw.acknowledged_by.unwrap(),
             acknowledged_by_device: raw.acknowledged_by_device.unwrap(),
-            last_delivered_at: raw.last_delivered_at.unwrap(),
+            last_delivered_at: from_optional_unix_seconds(raw.last_delivered_at.unwrap()),
             expired: raw.expired.unwrap(),
-            expires_at: raw.expires_at.unwrap(),
+            expires_at: from_unix_seconds(raw.expires_at.unwrap()),
             request: raw.request,
         }
     }
@@ -64,14 +72,14 @@ impl Request for ReceiptStatus {
 #[derive(Clone, Debug, PartialEq, PartialOrd)]
 pub struct ReceiptStatusResponse {
     pub called_back: u8,
-    pub called_back_at: u32,
+    pub called_back_at: Option<SystemTime>,
     pub acknowledged: u8,
-    pub acknowledged_at: u32,
+    pub acknowledged_at: Option<SystemTime>,
     pub acknowledged_by: String,
     pub acknowledged_by_device: String,
-    pub last_delivered_at: u32,
+    pub last_delivered_at: Option<SystemTime>,
     pub expired: u8,
-    pub expires_at: u32,
+    pub expires_at: SystemTime,
     pub request: String,
 }
 
@@ -81,14 +89,14 @@ pub struct RawReceiptStatusResponse {
     pub request: String,
     pub errors: Option<Vec<String>>,
     pub called_back: Option<u8>,
-    pub called_back_at: Option<u32>,
+    pub called_back_at: Option<u64>,
     pub acknowledged: Option<u8>,
-    pub acknowledged_at: Option<u32>,
+    pub acknowledged_at: Option<u64>,
     pub acknowledged_by: Option<String>,
     pub acknowledged_by_device: Option<String>,
-    pub last_delivered_at: Option<u32>,
+    pub last_delivered_at: Option<u64>,
     pub expired: Option<u8>,
-    pub expires_at: Option<u32>,
+    pub expires_at: Option<u64>,
 }
 
 impl RawResponse for RawReceiptStatusResponse {
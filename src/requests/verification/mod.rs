@@ -1,4 +1,5 @@
 //! https://pushover.net/api#verification
+#[allow(clippy::module_inception)]
 mod verification;
 
 pub use self::verification::{Verification, VerificationResponse};
\ No newline at end of file
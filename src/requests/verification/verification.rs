@@ -2,18 +2,25 @@ use reqwest::Method;
 use serde::Deserialize;
 use url::Url;
 
-use crate::requests::base::{add_optional_param, RawResponse, Request};
+use crate::error::ErrorKind;
+use crate::requests::base::{add_optional_param, recipient_error, RawResponse, Request};
 
 /// Verify user/group
 ///
 /// Return type is [VerificationResponse](struct.VerificationResponse.html).
-#[derive(Clone, Debug, PartialEq, PartialOrd)]
+#[derive(Clone, PartialEq, PartialOrd)]
 pub struct Verification {
     pub token: String,
     pub user: String,
     pub device: Option<String>,
 }
 
+debug_redacted!(Verification {
+    token: secret,
+    user,
+    device,
+});
+
 impl Verification {
     pub fn new<R, T>(token: T, user: R) -> Self
     where
@@ -55,6 +62,8 @@ impl Request for Verification {
     fn map(raw: Self::RawResponseType) -> Self::ResponseType {
         Self::ResponseType {
             devices: raw.devices.unwrap(),
+            group: raw.group == Some(1),
+            licenses: raw.licenses.unwrap_or_default(),
             request: raw.request,
         }
     }
@@ -63,6 +72,10 @@ impl Request for Verification {
 #[derive(Clone, Debug, PartialEq, PartialOrd)]
 pub struct VerificationResponse {
     pub devices: Vec<String>,
+    /// Whether the key is a group key rather than a user key.
+    pub group: bool,
+    /// Platforms the user holds a license for, e.g. `Android`, `iOS` or `Desktop`.
+    pub licenses: Vec<String>,
     pub request: String,
 }
 
@@ -72,10 +85,20 @@ pub struct RawVerificationResponse {
     pub request: String,
     pub errors: Option<Vec<String>>,
     pub devices: Option<Vec<String>>,
+    pub group: Option<u8>,
+    pub licenses: Option<Vec<String>>,
+    /// Set, to `invalid`, when Pushover rejected the user key.
+    pub user: Option<String>,
+    /// Set, to `invalid`, when Pushover rejected the device name.
+    pub device: Option<String>,
 }
 
 impl RawResponse for RawVerificationResponse {
     raw_response_basic_getters!();
+
+    fn get_error(&self) -> Option<ErrorKind> {
+        recipient_error(self, &self.user, &self.device)
+    }
 }
 
 #[cfg(test)]
@@ -94,11 +117,27 @@ mod tests {
             Some(&[
                 ("token", &req.token),
                 ("user", &req.user),
-                ("device", &req.device.as_ref().unwrap()),
+                ("device", req.device.as_ref().unwrap()),
             ]),
         );
     }
 
+    #[test]
+    fn rejected_device_maps_to_invalid_recipient() {
+        let raw: RawVerificationResponse = serde_json::from_str(
+            r#"{"status":0,"device":"invalid","errors":["device name is not valid for user"],"request":"r"}"#,
+        )
+        .unwrap();
+
+        match raw.get_error() {
+            Some(ErrorKind::InvalidRecipient { field, errors, .. }) => {
+                assert_eq!(field, "device");
+                assert_eq!(errors, ["device name is not valid for user"]);
+            }
+            other => panic!("Expected InvalidRecipient, got {:?}", other),
+        }
+    }
+
     #[test]
     fn get_url_with_mandatory_fields() {
         let req = Verification::new("ver_token", "ver user");
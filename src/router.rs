@@ -0,0 +1,590 @@
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use regex::Regex;
+use serde::Deserialize;
+
+use crate::error::{Error, ErrorKind};
+use crate::open_client_session::OpenClientSession;
+use crate::requests::message::SendMessage;
+use crate::requests::open_client::Acknowledge;
+use crate::types::{Message, Priority};
+
+/// Routes Open Client messages to actions by the first rule they match.
+///
+/// A rule matches on any of the message's `app`, `aid`, priority, `receipt`, and title or
+/// message regex; conditions left out match every message. Unless a rule sets `continue`, the
+/// first rule to match is the only one applied.
+///
+/// ```rust,no_run
+/// use pushover::requests::open_client::Login;
+/// use pushover::{OpenClientSession, Router, API};
+///
+/// let router = Router::from_toml(r#"
+///     [[rules]]
+///     name = "production alerts"
+///     app = "Grafana"
+///     priority = [1, 2]
+///     title = "(?i)prod"
+///     actions = [
+///         { type = "command", program = "/usr/local/bin/page", args = ["--team", "db"] },
+///         { type = "acknowledge" },
+///     ]
+///
+///     [[rules]]
+///     name = "everything else"
+///     actions = [{ type = "append", path = "/var/log/pushover.jsonl" }]
+/// "#).unwrap();
+///
+/// let login = Login::new("email@example.com", "password");
+/// let session = OpenClientSession::open(API::new(), "pushover.json", &login, "router")
+///     .expect("Error opening session");
+///
+/// for message in session.fetch_new().expect("Error fetching messages") {
+///     for result in router.route(&session, &message) {
+///         if let Err(e) = result.result {
+///             eprintln!("{}: {}", result.rule, e);
+///         }
+///     }
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct Router {
+    rules: Vec<Rule>,
+}
+
+/// Conditions a message must meet, and what to do with it if it does.
+#[derive(Clone, Debug)]
+pub struct Rule {
+    pub name: String,
+    pub app: Option<String>,
+    pub aid: Option<u32>,
+    /// Priorities to match; empty matches any.
+    pub priorities: Vec<Priority>,
+    pub title: Option<Regex>,
+    pub message: Option<Regex>,
+    /// Whether the message must, or must not, have a receipt.
+    pub receipt: Option<bool>,
+    pub actions: Vec<Action>,
+    /// Keep matching later rules after this one.
+    pub then_continue: bool,
+}
+
+/// Something to do with a matching message.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Action {
+    /// Run `program`, writing the message to its stdin as JSON.
+    Command {
+        program: String,
+        args: Vec<String>,
+    },
+    /// Append the message to a file as a line of JSON.
+    Append {
+        path: PathBuf,
+    },
+    Forward(Forward),
+    /// Acknowledge the message, if it has a receipt that hasn't been acknowledged.
+    Acknowledge,
+}
+
+/// Send a copy of the message to another user. Emergency messages are forwarded as `High`.
+#[derive(Clone, PartialEq)]
+pub struct Forward {
+    pub token: String,
+    pub user: String,
+    pub device: Option<String>,
+}
+
+debug_redacted!(Forward {
+    token: secret,
+    user,
+    device,
+});
+
+/// Outcome of one action taken on a message.
+#[derive(Debug)]
+pub struct ActionResult {
+    pub rule: String,
+    pub action: Action,
+    pub result: Result<(), Error>,
+}
+
+#[derive(Deserialize)]
+struct RouterConfig {
+    rules: Vec<RuleConfig>,
+}
+
+#[derive(Deserialize)]
+struct RuleConfig {
+    name: String,
+    app: Option<String>,
+    aid: Option<u32>,
+    #[serde(default)]
+    priority: Vec<Priority>,
+    title: Option<String>,
+    message: Option<String>,
+    receipt: Option<bool>,
+    actions: Vec<ActionConfig>,
+    #[serde(default, rename = "continue")]
+    then_continue: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ActionConfig {
+    Command {
+        program: String,
+        #[serde(default)]
+        args: Vec<String>,
+    },
+    Append {
+        path: PathBuf,
+    },
+    Forward {
+        token: String,
+        user: String,
+        device: Option<String>,
+    },
+    Acknowledge,
+}
+
+impl Router {
+    pub fn new(rules: Vec<Rule>) -> Self {
+        Self { rules }
+    }
+
+    pub fn from_toml(config: &str) -> Result<Self, Error> {
+        Self::from_config(toml::from_str(config)?)
+    }
+
+    pub fn from_json(config: &str) -> Result<Self, Error> {
+        Self::from_config(serde_json::from_str(config)?)
+    }
+
+    /// Reads rules from a `.json` file, or a TOML file otherwise.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let config = fs::read_to_string(path)?;
+
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("json") => Self::from_json(&config),
+            _ => Self::from_toml(&config),
+        }
+    }
+
+    pub fn rules(&self) -> &[Rule] {
+        &self.rules
+    }
+
+    /// The rules `message` is routed to, in order.
+    pub fn matching(&self, message: &Message) -> Vec<&Rule> {
+        let mut matching = Vec::new();
+
+        for rule in &self.rules {
+            if rule.matches(message) {
+                matching.push(rule);
+                if !rule.then_continue {
+                    break;
+                }
+            }
+        }
+
+        matching
+    }
+
+    /// Takes every action of the rules `message` is routed to, carrying on past failures.
+    ///
+    /// `session` is the device that received the message, used to acknowledge it.
+    pub fn route(&self, session: &OpenClientSession, message: &Message) -> Vec<ActionResult> {
+        let mut results = Vec::new();
+
+        for rule in self.matching(message) {
+            for action in &rule.actions {
+                let result = match action {
+                    Action::Forward(forward) => {
+                        session.api().send(&forward.message(message)).map(drop)
+                    }
+                    Action::Acknowledge => match acknowledge(session, message) {
+                        Some(acknowledge) => session.api().send(&acknowledge).map(drop),
+                        None => Ok(()),
+                    },
+                    local => run_local(local, message),
+                };
+
+                results.push(ActionResult {
+                    rule: rule.name.clone(),
+                    action: action.clone(),
+                    result,
+                });
+            }
+        }
+
+        results
+    }
+
+    /// Like [route](#method.route), sending requests asynchronously. Commands and file appends
+    /// still block while they run.
+    pub async fn route_async(
+        &self,
+        session: &OpenClientSession,
+        message: &Message,
+    ) -> Vec<ActionResult> {
+        let mut results = Vec::new();
+
+        for rule in self.matching(message) {
+            for action in &rule.actions {
+                let result = match action {
+                    Action::Forward(forward) => session
+                        .api()
+                        .send_async(&forward.message(message))
+                        .await
+                        .map(drop),
+                    Action::Acknowledge => match acknowledge(session, message) {
+                        Some(acknowledge) => session.api().send_async(&acknowledge).await.map(drop),
+                        None => Ok(()),
+                    },
+                    local => run_local(local, message),
+                };
+
+                results.push(ActionResult {
+                    rule: rule.name.clone(),
+                    action: action.clone(),
+                    result,
+                });
+            }
+        }
+
+        results
+    }
+
+    fn from_config(config: RouterConfig) -> Result<Self, Error> {
+        let rules = config
+            .rules
+            .into_iter()
+            .map(RuleConfig::into_rule)
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self::new(rules))
+    }
+}
+
+impl Rule {
+    pub fn matches(&self, message: &Message) -> bool {
+        self.app.as_ref().map_or(true, |app| *app == message.app)
+            && self.aid.map_or(true, |aid| aid == message.aid)
+            && (self.priorities.is_empty() || self.priorities.contains(&message.priority))
+            && self.title.as_ref().map_or(true, |title| {
+                message
+                    .title
+                    .as_ref()
+                    .is_some_and(|text| title.is_match(text))
+            })
+            && self
+                .message
+                .as_ref()
+                .map_or(true, |pattern| pattern.is_match(&message.message))
+            && self
+                .receipt
+                .map_or(true, |receipt| receipt == message.receipt.is_some())
+    }
+}
+
+impl Forward {
+    fn message(&self, message: &Message) -> SendMessage {
+        let mut forwarded = SendMessage::new(
+            self.token.as_str(),
+            self.user.as_str(),
+            message.message.as_str(),
+        );
+        forwarded.set_title(message.title.as_deref().unwrap_or(&message.app));
+        forwarded.set_timestamp(message.date);
+        forwarded.set_html(message.html == Some(1));
+        forwarded.set_priority(match message.priority {
+            Priority::Emergency => Priority::High,
+            priority => priority,
+        });
+        if let Some(ref url) = message.url {
+            forwarded.set_url(url.as_str());
+        }
+        if let Some(ref url_title) = message.url_title {
+            forwarded.set_url_title(url_title.as_str());
+        }
+        if let Some(ref device) = self.device {
+            forwarded.add_device(device.as_str());
+        }
+
+        forwarded
+    }
+}
+
+impl RuleConfig {
+    fn into_rule(self) -> Result<Rule, Error> {
+        let name = self.name;
+        let regex = |field: &str, pattern: Option<String>| {
+            pattern
+                .map(|pattern| Regex::new(&pattern))
+                .transpose()
+                .map_err(|e| {
+                    ErrorKind::InvalidRules(format!(
+                        "rule '{}' has an invalid {}: {}",
+                        name, field, e
+                    ))
+                })
+        };
+
+        let title = regex("title", self.title)?;
+        let message = regex("message", self.message)?;
+        if self.actions.is_empty() {
+            return Err(ErrorKind::InvalidRules(format!("rule '{}' has no actions", name)).into());
+        }
+
+        let actions = self
+            .actions
+            .into_iter()
+            .map(|action| match action {
+                ActionConfig::Command { program, args } => Action::Command { program, args },
+                ActionConfig::Append { path } => Action::Append { path },
+                ActionConfig::Forward {
+                    token,
+                    user,
+                    device,
+                } => Action::Forward(Forward {
+                    token,
+                    user,
+                    device,
+                }),
+                ActionConfig::Acknowledge => Action::Acknowledge,
+            })
+            .collect();
+
+        Ok(Rule {
+            name,
+            app: self.app,
+            aid: self.aid,
+            priorities: self.priority,
+            title,
+            message,
+            receipt: self.receipt,
+            actions,
+            then_continue: self.then_continue,
+        })
+    }
+}
+
+/// The acknowledgement for `message`, if it has a receipt still waiting for one.
+fn acknowledge(session: &OpenClientSession, message: &Message) -> Option<Acknowledge> {
+    match message.receipt {
+        Some(ref receipt) if message.acked == 0 => Some(Acknowledge::new(
+            session.credentials().secret.as_str(),
+            receipt.as_str(),
+        )),
+        _ => None,
+    }
+}
+
+/// Runs a command or file action.
+fn run_local(action: &Action, message: &Message) -> Result<(), Error> {
+    let json = serde_json::to_string(message)?;
+
+    match action {
+        Action::Command { program, args } => {
+            let mut child = Command::new(program)
+                .args(args)
+                .stdin(Stdio::piped())
+                .spawn()?;
+            // Dropping stdin closes it, so the command sees the end of the message.
+            let written = child
+                .stdin
+                .take()
+                .expect("stdin is piped")
+                .write_all(json.as_bytes());
+            // A command may exit without reading all of its input; its exit status decides.
+            match written {
+                Ok(()) => {}
+                Err(ref e) if e.kind() == io::ErrorKind::BrokenPipe => {}
+                Err(e) => {
+                    // Reap the child rather than leave it running or as a zombie.
+                    let _ = child.kill();
+                    let _ = child.wait();
+                    return Err(e.into());
+                }
+            }
+
+            let status = child.wait()?;
+            if !status.success() {
+                return Err(ErrorKind::Msg(format!("'{}' exited with {}", program, status)).into());
+            }
+        }
+        Action::Append { path } => {
+            let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+            file.write_all(format!("{}\n", json).as_bytes())?;
+        }
+        Action::Forward(..) | Action::Acknowledge => {}
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::SystemTime;
+
+    fn message(app: &str, title: Option<&str>, priority: Priority) -> Message {
+        Message {
+            id: 1,
+            umid: 10,
+            title: title.map(str::to_owned),
+            message: String::from("disk usage at 95%"),
+            app: app.to_owned(),
+            aid: 7,
+            icon: String::from("icon"),
+            date: SystemTime::UNIX_EPOCH,
+            priority,
+            sound: None,
+            url: None,
+            url_title: None,
+            acked: 0,
+            receipt: None,
+            html: None,
+        }
+    }
+
+    fn router() -> Router {
+        Router::from_toml(
+            r#"
+            [[rules]]
+            name = "prod"
+            app = "Grafana"
+            priority = [1, 2]
+            title = "(?i)prod"
+            continue = true
+            actions = [{ type = "command", program = "true" }]
+
+            [[rules]]
+            name = "disk"
+            message = "disk usage"
+            receipt = false
+            actions = [
+                { type = "append", path = "/tmp/pushover.jsonl" },
+                { type = "forward", token = "token", user = "user", device = "phone" },
+            ]
+
+            [[rules]]
+            name = "rest"
+            actions = [{ type = "acknowledge" }]
+            "#,
+        )
+        .unwrap()
+    }
+
+    fn names(router: &Router, message: &Message) -> Vec<String> {
+        router
+            .matching(message)
+            .iter()
+            .map(|rule| rule.name.clone())
+            .collect()
+    }
+
+    #[test]
+    fn matches_rules_in_order() {
+        let router = router();
+
+        assert_eq!(
+            names(
+                &router,
+                &message("Grafana", Some("PROD db01"), Priority::High)
+            ),
+            ["prod", "disk"]
+        );
+        assert_eq!(
+            names(
+                &router,
+                &message("Grafana", Some("staging"), Priority::High)
+            ),
+            ["disk"]
+        );
+        assert_eq!(
+            names(&router, &message("Grafana", None, Priority::High)),
+            ["disk"]
+        );
+
+        let mut receipted = message("Other", None, Priority::Normal);
+        receipted.receipt = Some(String::from("receipt"));
+        assert_eq!(names(&router, &receipted), ["rest"]);
+    }
+
+    #[test]
+    fn loads_actions() {
+        let router = router();
+
+        assert_eq!(
+            router.rules()[1].actions,
+            [
+                Action::Append {
+                    path: PathBuf::from("/tmp/pushover.jsonl")
+                },
+                Action::Forward(Forward {
+                    token: String::from("token"),
+                    user: String::from("user"),
+                    device: Some(String::from("phone")),
+                }),
+            ]
+        );
+        assert!(!format!("{:?}", router.rules()[1].actions[1]).contains("\"token\""));
+    }
+
+    #[test]
+    fn rejects_invalid_rules() {
+        for config in &[
+            "[[rules]]\nname = \"bad\"\ntitle = \"(\"\nactions = [{ type = \"acknowledge\" }]",
+            "[[rules]]\nname = \"empty\"\nactions = []",
+        ] {
+            match Router::from_toml(config) {
+                Err(Error(ErrorKind::InvalidRules(_), _)) => {}
+                other => panic!("Expected InvalidRules, got {:?}", other.map(|_| ())),
+            }
+        }
+    }
+
+    #[test]
+    fn forwards_emergency_as_high() {
+        let forward = Forward {
+            token: String::from("token"),
+            user: String::from("user"),
+            device: None,
+        };
+
+        let forwarded = forward.message(&message("Grafana", None, Priority::Emergency));
+
+        assert_eq!(forwarded.priority, Some(Priority::High));
+        assert_eq!(forwarded.title.as_deref(), Some("Grafana"));
+        assert_eq!(forwarded.message, "disk usage at 95%");
+    }
+
+    #[test]
+    fn serializes_message_for_commands() {
+        let json = serde_json::to_value(message("Grafana", None, Priority::High)).unwrap();
+
+        assert_eq!(json["app"], "Grafana");
+        assert_eq!(json["priority"], 1);
+        assert_eq!(json["date"], 0);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn command_that_ignores_stdin_is_judged_by_exit_status() {
+        let mut message = message("Grafana", None, Priority::High);
+        // Larger than a pipe buffer, so writing fails once the command has exited.
+        message.message = "x".repeat(1 << 20);
+        let command = |program: &str| Action::Command {
+            program: String::from(program),
+            args: Vec::new(),
+        };
+
+        assert!(run_local(&command("true"), &message).is_ok());
+        assert!(run_local(&command("false"), &message).is_err());
+    }
+}
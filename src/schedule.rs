@@ -0,0 +1,613 @@
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::Deserialize;
+
+use crate::clock::{Clock, SystemClock};
+use crate::error::{Error, ErrorKind};
+use crate::requests::message::SendMessage;
+use crate::types::{Recipients, User};
+
+/// On-call rotations resolving who to notify at a given time.
+///
+/// A rotation is made of layers and overrides. Each layer hands off between its participants
+/// every `shift`, starting from the first participant at `start`; later layers take precedence
+/// over earlier ones while they are in effect. An override replaces whoever is on call between
+/// its `start` and `end`. Times are written as `YYYY-MM-DDTHH:MM[:SS]` in the schedule's
+/// `utc_offset`, and shifts as a number of hours, days or weeks such as `12h`, `1d` or `1w`.
+///
+/// ```rust
+/// use std::time::{Duration, UNIX_EPOCH};
+/// use pushover::{ManualClock, Schedule};
+///
+/// let schedule = Schedule::from_toml(r#"
+///     utc_offset = "+01:00"
+///
+///     [[rotations]]
+///     name = "primary"
+///
+///     [[rotations.layers]]
+///     start = "2024-01-01T09:00"
+///     shift = "1w"
+///     participants = [
+///         { user = "uQiRzpo4DXghDmr9QzzfQu27cmVRsG", device = "phone" },
+///         { user = "gznej3rKEVAvPUxu9vvNnqpmZpokzF" },
+///     ]
+///
+///     [[rotations.overrides]]
+///     start = "2024-01-10T18:00"
+///     end = "2024-01-11T09:00"
+///     user = { user = "azGDORePK8gMaC0QOYAMyEEuzJnyUi" }
+/// "#).unwrap();
+///
+/// // 2024-01-09T08:00 UTC, in the second week of the rotation.
+/// let at = UNIX_EPOCH + Duration::from_secs(1_704_787_200);
+/// let schedule = schedule.clock(ManualClock::new(at));
+///
+/// assert_eq!(schedule.on_call("primary").unwrap().user, "gznej3rKEVAvPUxu9vvNnqpmZpokzF");
+/// ```
+pub struct Schedule {
+    rotations: Vec<Rotation>,
+    clock: Arc<dyn Clock>,
+}
+
+/// Who is on call for one team or service.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Rotation {
+    pub name: String,
+    pub layers: Vec<Layer>,
+    pub overrides: Vec<Override>,
+}
+
+/// Participants taking turns, one `shift` each, from `start` until `end` if any.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Layer {
+    pub start: SystemTime,
+    pub end: Option<SystemTime>,
+    pub shift: Duration,
+    pub participants: Vec<User>,
+}
+
+/// A user standing in for the rotation between `start` and `end`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Override {
+    pub start: SystemTime,
+    pub end: SystemTime,
+    pub user: User,
+}
+
+#[derive(Deserialize)]
+struct ScheduleConfig {
+    #[serde(default)]
+    utc_offset: Option<String>,
+    rotations: Vec<RotationConfig>,
+}
+
+#[derive(Deserialize)]
+struct RotationConfig {
+    name: String,
+    #[serde(default)]
+    layers: Vec<LayerConfig>,
+    #[serde(default)]
+    overrides: Vec<OverrideConfig>,
+}
+
+#[derive(Deserialize)]
+struct LayerConfig {
+    start: String,
+    end: Option<String>,
+    shift: String,
+    participants: Vec<UserConfig>,
+}
+
+#[derive(Deserialize)]
+struct OverrideConfig {
+    start: String,
+    end: String,
+    user: UserConfig,
+}
+
+#[derive(Deserialize)]
+struct UserConfig {
+    user: String,
+    device: Option<String>,
+}
+
+impl Schedule {
+    pub fn new(rotations: Vec<Rotation>) -> Self {
+        Self {
+            rotations,
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    pub fn from_toml(config: &str) -> Result<Self, Error> {
+        Self::from_config(toml::from_str(config)?)
+    }
+
+    pub fn from_json(config: &str) -> Result<Self, Error> {
+        Self::from_config(serde_json::from_str(config)?)
+    }
+
+    /// Reads a schedule from a `.json` file, or a TOML file otherwise.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let config = fs::read_to_string(path)?;
+
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("json") => Self::from_json(&config),
+            _ => Self::from_toml(&config),
+        }
+    }
+
+    pub fn clock<C: Clock + 'static>(mut self, clock: C) -> Self {
+        self.clock = Arc::new(clock);
+        self
+    }
+
+    pub fn rotations(&self) -> &[Rotation] {
+        &self.rotations
+    }
+
+    pub fn rotation(&self, name: &str) -> Option<&Rotation> {
+        self.rotations.iter().find(|rotation| rotation.name == name)
+    }
+
+    /// Who is on call for `rotation` now.
+    pub fn on_call(&self, rotation: &str) -> Option<&User> {
+        self.on_call_at(rotation, self.clock.now())
+    }
+
+    pub fn on_call_at(&self, rotation: &str, at: SystemTime) -> Option<&User> {
+        self.rotation(rotation)?.on_call_at(at)
+    }
+
+    /// A copy of `message` addressed to whoever is on call for `rotation` now, and to their
+    /// device if they have one.
+    pub fn address(&self, rotation: &str, message: &SendMessage) -> Result<SendMessage, Error> {
+        let user = self
+            .on_call(rotation)
+            .ok_or_else(|| ErrorKind::NobodyOnCall(rotation.to_owned()))?;
+
+        let mut message = SendMessage {
+            recipients: Recipients::from(user.user.as_str()),
+            devices: Vec::new(),
+            ..message.clone()
+        };
+        if let Some(ref device) = user.device {
+            message.add_device(device.as_str());
+        }
+
+        Ok(message)
+    }
+
+    fn from_config(config: ScheduleConfig) -> Result<Self, Error> {
+        let offset = match config.utc_offset {
+            Some(ref offset) => parse_offset(offset)?,
+            None => 0,
+        };
+
+        let rotations = config
+            .rotations
+            .into_iter()
+            .map(|rotation| rotation.into_rotation(offset))
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self::new(rotations))
+    }
+}
+
+impl Rotation {
+    /// Who is on call at `at`: the active override if any, otherwise the last layer in effect.
+    pub fn on_call_at(&self, at: SystemTime) -> Option<&User> {
+        if let Some(active) = self
+            .overrides
+            .iter()
+            .rev()
+            .find(|o| o.start <= at && at < o.end)
+        {
+            return Some(&active.user);
+        }
+
+        self.layers
+            .iter()
+            .rev()
+            .find_map(|layer| layer.on_call_at(at))
+    }
+}
+
+impl Layer {
+    pub fn on_call_at(&self, at: SystemTime) -> Option<&User> {
+        if self.participants.is_empty() || self.end.is_some_and(|end| at >= end) {
+            return None;
+        }
+
+        let elapsed = at.duration_since(self.start).ok()?;
+        let shifts = elapsed.as_secs() / self.shift.as_secs().max(1);
+
+        self.participants
+            .get((shifts % self.participants.len() as u64) as usize)
+    }
+}
+
+impl RotationConfig {
+    fn into_rotation(self, offset: i64) -> Result<Rotation, Error> {
+        let name = self.name;
+        let invalid = |reason: String| ErrorKind::InvalidSchedule(format!("{}: {}", name, reason));
+
+        let layers = self
+            .layers
+            .into_iter()
+            .map(|layer| {
+                let start = parse_local_time(&layer.start, offset).map_err(&invalid)?;
+                let end = match layer.end {
+                    Some(ref end) => Some(parse_local_time(end, offset).map_err(&invalid)?),
+                    None => None,
+                };
+
+                if layer.participants.is_empty() {
+                    return Err(invalid(String::from("layer has no participants")));
+                }
+                if end.is_some_and(|end| end <= start) {
+                    return Err(invalid(String::from("layer ends before it starts")));
+                }
+
+                Ok(Layer {
+                    start,
+                    end,
+                    shift: parse_shift(&layer.shift).map_err(&invalid)?,
+                    participants: layer
+                        .participants
+                        .into_iter()
+                        .map(UserConfig::into_user)
+                        .collect(),
+                })
+            })
+            .collect::<Result<_, _>>()?;
+
+        let overrides = self
+            .overrides
+            .into_iter()
+            .map(|o| {
+                let start = parse_local_time(&o.start, offset).map_err(&invalid)?;
+                let end = parse_local_time(&o.end, offset).map_err(&invalid)?;
+
+                if end <= start {
+                    return Err(invalid(String::from("override ends before it starts")));
+                }
+
+                Ok(Override {
+                    start,
+                    end,
+                    user: o.user.into_user(),
+                })
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(Rotation {
+            name: name.clone(),
+            layers,
+            overrides,
+        })
+    }
+}
+
+impl UserConfig {
+    fn into_user(self) -> User {
+        let mut user = User::new(self.user);
+        if let Some(device) = self.device {
+            user.set_device(device);
+        }
+        user
+    }
+}
+
+/// Parses `+HH:MM`, `-HH:MM` or `Z` into seconds east of UTC.
+fn parse_offset(offset: &str) -> Result<i64, Error> {
+    let invalid = || ErrorKind::InvalidSchedule(format!("invalid utc_offset '{}'", offset));
+
+    if offset == "Z" {
+        return Ok(0);
+    }
+
+    let sign = match offset.chars().next() {
+        Some('+') => 1,
+        Some('-') => -1,
+        _ => return Err(invalid().into()),
+    };
+    let (hours, minutes) = offset[1..].split_once(':').ok_or_else(invalid)?;
+    let hours: i64 = hours.parse().map_err(|_| invalid())?;
+    let minutes: i64 = minutes.parse().map_err(|_| invalid())?;
+
+    if hours > 23 || minutes > 59 {
+        return Err(invalid().into());
+    }
+
+    Ok(sign * (hours * 3600 + minutes * 60))
+}
+
+/// Parses `YYYY-MM-DDTHH:MM[:SS]` in the zone `offset` seconds east of UTC.
+fn parse_local_time(time: &str, offset: i64) -> Result<SystemTime, String> {
+    let invalid = || format!("invalid time '{}'", time);
+
+    let (date, clock) = time.split_once('T').ok_or_else(invalid)?;
+    let date: Vec<i64> = parse_fields(date, '-').ok_or_else(invalid)?;
+    let clock: Vec<i64> = parse_fields(clock, ':').ok_or_else(invalid)?;
+
+    let (year, month, day) = match date[..] {
+        [year, month, day]
+            if (1..=12).contains(&month) && (1..=days_in_month(year, month)).contains(&day) =>
+        {
+            (year, month, day)
+        }
+        _ => return Err(invalid()),
+    };
+    let (hour, minute, second) = match clock[..] {
+        [hour, minute] => (hour, minute, 0),
+        [hour, minute, second] => (hour, minute, second),
+        _ => return Err(invalid()),
+    };
+    if !(0..=23).contains(&hour) || !(0..=59).contains(&minute) || !(0..=59).contains(&second) {
+        return Err(invalid());
+    }
+
+    let seconds = days_from_civil(year, month, day)
+        .and_then(|days| days.checked_mul(86400))
+        .and_then(|seconds| seconds.checked_add(hour * 3600 + minute * 60 + second - offset))
+        .ok_or_else(invalid)?;
+
+    let since_epoch = Duration::from_secs(seconds.unsigned_abs());
+    if seconds >= 0 {
+        UNIX_EPOCH.checked_add(since_epoch).ok_or_else(invalid)
+    } else {
+        UNIX_EPOCH.checked_sub(since_epoch).ok_or_else(invalid)
+    }
+}
+
+fn parse_fields(value: &str, separator: char) -> Option<Vec<i64>> {
+    value
+        .split(separator)
+        .map(|field| field.parse().ok())
+        .collect()
+}
+
+/// Parses a shift length such as `12h`, `1d` or `2w`.
+fn parse_shift(shift: &str) -> Result<Duration, String> {
+    let invalid = || format!("invalid shift '{}'", shift);
+
+    let (last, _) = shift.char_indices().last().ok_or_else(invalid)?;
+    let (count, unit) = shift.split_at(last);
+    let count: u64 = count.parse().map_err(|_| invalid())?;
+    let unit = match unit {
+        "h" => 3600,
+        "d" => 86400,
+        "w" => 7 * 86400,
+        _ => return Err(invalid()),
+    };
+
+    if count == 0 {
+        return Err(invalid());
+    }
+
+    count
+        .checked_mul(unit)
+        .map(Duration::from_secs)
+        .ok_or_else(invalid)
+}
+
+fn days_in_month(year: i64, month: i64) -> i64 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Days since 1970-01-01 of a date in the proleptic Gregorian calendar, or `None` if the year is
+/// too far out to count.
+fn days_from_civil(year: i64, month: i64, day: i64) -> Option<i64> {
+    let year = if month <= 2 {
+        year.checked_sub(1)?
+    } else {
+        year
+    };
+    let era = if year >= 0 {
+        year
+    } else {
+        year.checked_sub(399)?
+    } / 400;
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+
+    era.checked_mul(146097)?.checked_add(day_of_era - 719468)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::ManualClock;
+
+    const SCHEDULE: &str = r#"
+        utc_offset = "-05:00"
+
+        [[rotations]]
+        name = "primary"
+
+        [[rotations.layers]]
+        start = "2024-01-01T09:00"
+        shift = "1w"
+        participants = [{ user = "alice", device = "phone" }, { user = "bob" }]
+
+        [[rotations.layers]]
+        start = "2024-01-06T00:00"
+        end = "2024-01-08T00:00"
+        shift = "1d"
+        participants = [{ user = "weekend" }]
+
+        [[rotations.overrides]]
+        start = "2024-01-03T12:00"
+        end = "2024-01-03T18:00"
+        user = { user = "carol" }
+    "#;
+
+    /// `YYYY-MM-DDTHH:MM` in the schedule's UTC-5.
+    fn at(time: &str) -> SystemTime {
+        parse_local_time(time, -5 * 3600).unwrap()
+    }
+
+    fn on_call(schedule: &Schedule, time: &str) -> Option<String> {
+        schedule
+            .on_call_at("primary", at(time))
+            .map(|user| user.user.clone())
+    }
+
+    #[test]
+    fn parses_local_times() {
+        assert_eq!(
+            parse_local_time("2020-09-13T12:26:40", 0),
+            Ok(UNIX_EPOCH + Duration::from_secs(1_600_000_000))
+        );
+        assert_eq!(
+            parse_local_time("2020-09-13T14:26:40", 2 * 3600),
+            Ok(UNIX_EPOCH + Duration::from_secs(1_600_000_000))
+        );
+        assert!(parse_local_time("2020-13-01T00:00", 0).is_err());
+        assert_eq!(parse_offset("-05:30").unwrap(), -(5 * 3600 + 30 * 60));
+        assert_eq!(parse_shift("12h"), Ok(Duration::from_secs(12 * 3600)));
+        assert!(parse_shift("0w").is_err());
+    }
+
+    #[test]
+    fn rejects_days_past_end_of_month() {
+        assert!(parse_local_time("2024-02-29T00:00", 0).is_ok());
+        assert!(parse_local_time("2024-02-30T00:00", 0).is_err());
+        assert!(parse_local_time("2024-02-31T00:00", 0).is_err());
+        assert!(parse_local_time("2023-02-29T00:00", 0).is_err());
+        assert!(parse_local_time("2000-02-29T00:00", 0).is_ok());
+        assert!(parse_local_time("1900-02-29T00:00", 0).is_err());
+        assert!(parse_local_time("2024-04-31T00:00", 0).is_err());
+        assert!(parse_local_time("2024-12-31T00:00", 0).is_ok());
+    }
+
+    #[test]
+    fn rejects_overflowing_shift_and_year() {
+        assert_eq!(
+            parse_shift("99999999999999999w"),
+            Err(String::from("invalid shift '99999999999999999w'"))
+        );
+        assert!(parse_local_time("999999999999999-01-01T00:00", 0).is_err());
+        assert!(parse_local_time("-9223372036854775808-01-01T00:00", 0).is_err());
+
+        let schedule = r#"
+            [[rotations]]
+            name = "primary"
+
+            [[rotations.layers]]
+            start = "99999999999999-01-01T00:00"
+            shift = "1d"
+            participants = [{ user = "alice" }]
+        "#;
+        match Schedule::from_toml(schedule) {
+            Err(Error(ErrorKind::InvalidSchedule(reason), _)) => {
+                assert!(reason.contains("invalid time"), "{}", reason)
+            }
+            other => panic!("Expected InvalidSchedule, got {:?}", other.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn rejects_shift_with_multibyte_unit() {
+        assert_eq!(parse_shift("1é"), Err(String::from("invalid shift '1é'")));
+        assert_eq!(parse_shift("é"), Err(String::from("invalid shift 'é'")));
+        assert!(parse_shift("").is_err());
+    }
+
+    #[test]
+    fn resolves_rotation_layers_and_overrides() {
+        let schedule = Schedule::from_toml(SCHEDULE).unwrap();
+
+        assert_eq!(on_call(&schedule, "2023-12-31T09:00"), None);
+        assert_eq!(
+            on_call(&schedule, "2024-01-01T09:00").as_deref(),
+            Some("alice")
+        );
+        assert_eq!(
+            on_call(&schedule, "2024-01-03T13:00").as_deref(),
+            Some("carol")
+        );
+        assert_eq!(
+            on_call(&schedule, "2024-01-06T10:00").as_deref(),
+            Some("weekend")
+        );
+        assert_eq!(
+            on_call(&schedule, "2024-01-08T08:59").as_deref(),
+            Some("alice")
+        );
+        assert_eq!(
+            on_call(&schedule, "2024-01-08T09:00").as_deref(),
+            Some("bob")
+        );
+        assert_eq!(
+            on_call(&schedule, "2024-01-15T09:00").as_deref(),
+            Some("alice")
+        );
+    }
+
+    #[test]
+    fn loads_json() {
+        let schedule = Schedule::from_json(
+            r#"{"rotations": [{"name": "primary", "layers": [
+                {"start": "2024-01-01T00:00", "shift": "1d", "participants": [{"user": "alice"}]}
+            ]}]}"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            schedule
+                .on_call_at("primary", parse_local_time("2024-01-02T00:00", 0).unwrap())
+                .map(|user| user.user.as_str()),
+            Some("alice")
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_layers() {
+        let result = Schedule::from_toml(
+            r#"
+            [[rotations]]
+            name = "primary"
+            [[rotations.layers]]
+            start = "2024-01-01T00:00"
+            shift = "1x"
+            participants = [{ user = "alice" }]
+            "#,
+        );
+
+        match result {
+            Err(Error(ErrorKind::InvalidSchedule(reason), _)) => {
+                assert_eq!(reason, "primary: invalid shift '1x'")
+            }
+            _ => panic!("expected an invalid schedule"),
+        }
+    }
+
+    #[test]
+    fn addresses_message_to_on_call_user() {
+        let clock = ManualClock::new(at("2024-01-02T09:00"));
+        let schedule = Schedule::from_toml(SCHEDULE).unwrap().clock(clock.clone());
+        let message = SendMessage::new("token", "placeholder", "db01 is down");
+
+        let addressed = schedule.address("primary", &message).unwrap();
+
+        assert_eq!(addressed.recipients.to_string(), "alice");
+        assert_eq!(addressed.devices, ["phone"]);
+
+        clock.set(at("2023-01-01T00:00"));
+        assert!(schedule.address("primary", &message).is_err());
+        assert!(schedule.address("unknown", &message).is_err());
+    }
+}
@@ -0,0 +1,115 @@
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::Error;
+use crate::jsonl;
+use crate::types::Message;
+
+/// Durable record of the Open Client messages that were handled but not yet deleted.
+///
+/// Used by [OpenClientSession::process](struct.OpenClientSession.html#method.process) to skip
+/// messages that are downloaded again because deleting them failed or was interrupted. Entries
+/// are dropped once the messages they refer to have been deleted from Pushover, so the store
+/// stays small.
+pub struct SeenStore {
+    path: PathBuf,
+    file: File,
+    /// Message id by `umid`.
+    seen: BTreeMap<u32, u32>,
+}
+
+#[derive(Deserialize, Serialize)]
+struct Record {
+    id: u32,
+    umid: u32,
+}
+
+impl SeenStore {
+    /// Opens the store at `path`, creating it if needed.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let path = path.as_ref().to_owned();
+        let mut seen = BTreeMap::new();
+
+        if path.exists() {
+            for record in jsonl::read::<Record>(&path)? {
+                seen.insert(record.umid, record.id);
+            }
+        }
+
+        let file = write_log(&path, &seen)?;
+
+        Ok(Self { path, file, seen })
+    }
+
+    /// Whether the message with `umid` has been handled.
+    pub fn contains(&self, umid: u32) -> bool {
+        self.seen.contains_key(&umid)
+    }
+
+    pub fn len(&self) -> usize {
+        self.seen.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.seen.is_empty()
+    }
+
+    /// Durably records `message` as handled.
+    pub(crate) fn insert(&mut self, message: &Message) -> Result<(), Error> {
+        jsonl::append(
+            &mut self.file,
+            &Record {
+                id: message.id,
+                umid: message.umid,
+            },
+        )?;
+        self.seen.insert(message.umid, message.id);
+
+        Ok(())
+    }
+
+    /// Forgets the messages up to `id`, which have been deleted from Pushover.
+    pub(crate) fn prune(&mut self, id: u32) -> Result<(), Error> {
+        self.seen.retain(|_, seen| *seen > id);
+        self.file = write_log(&self.path, &self.seen)?;
+
+        Ok(())
+    }
+}
+
+/// Rewrites the log with only the entries in `seen`.
+fn write_log(path: &Path, seen: &BTreeMap<u32, u32>) -> Result<File, Error> {
+    jsonl::rewrite(path, seen.iter().map(|(&umid, &id)| Record { id, umid }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn refuses_log_with_corrupt_record() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("seen.jsonl");
+        let log = "{\"id\":1,\"umid\":10}\nnot json\n{\"id\":3,\"umid\":30}\n";
+        fs::write(&path, log).unwrap();
+
+        assert!(SeenStore::open(&path).is_err());
+        assert_eq!(fs::read_to_string(&path).unwrap(), log);
+    }
+
+    #[test]
+    fn skips_torn_last_record() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("seen.jsonl");
+        fs::write(&path, "{\"id\":1,\"umid\":10}\n{\"id\":2,\"um").unwrap();
+
+        let store = SeenStore::open(&path).unwrap();
+
+        assert!(store.contains(10));
+        assert_eq!(store.len(), 1);
+    }
+}
@@ -0,0 +1,27 @@
+use std::fmt;
+
+/// Platform an Open Client device registers as.
+///
+/// Pushover only accepts `Open` for now. The enum is non-exhaustive so platforms it adds later
+/// can be supported without breaking matches on it.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd)]
+#[non_exhaustive]
+pub enum DeviceOs {
+    #[default]
+    Open,
+}
+
+impl DeviceOs {
+    /// The code sent to Pushover.
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            DeviceOs::Open => "O",
+        }
+    }
+}
+
+impl fmt::Display for DeviceOs {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
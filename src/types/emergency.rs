@@ -0,0 +1,182 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use crate::error::Error;
+use crate::validation::{Validator, MAX_EMERGENCY_EXPIRE, MIN_EMERGENCY_RETRY};
+
+/// How an emergency-priority message is repeated until acknowledged.
+///
+/// ```rust
+/// use std::time::Duration;
+/// use pushover::EmergencyOptions;
+///
+/// let options = EmergencyOptions::builder(Duration::from_secs(60), Duration::from_secs(3600))
+///     .tag("database")
+///     .build()
+///     .expect("Invalid options");
+/// ```
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq, PartialOrd, Serialize)]
+#[serde(try_from = "UncheckedOptions")]
+pub struct EmergencyOptions {
+    retry: Duration,
+    expire: Duration,
+    callback_url: Option<Url>,
+    tags: Vec<String>,
+}
+
+/// Deserialized options, checked by the builder before use.
+#[derive(Deserialize)]
+struct UncheckedOptions {
+    retry: Duration,
+    expire: Duration,
+    callback_url: Option<Url>,
+    #[serde(default)]
+    tags: Vec<String>,
+}
+
+impl EmergencyOptions {
+    /// Start an [EmergencyOptionsBuilder](struct.EmergencyOptionsBuilder.html). The message is
+    /// repeated every `retry` (at least 30 seconds) until acknowledged or `expire` (at most 3
+    /// hours) has passed.
+    pub fn builder(retry: Duration, expire: Duration) -> EmergencyOptionsBuilder {
+        EmergencyOptionsBuilder {
+            options: Self {
+                retry,
+                expire,
+                callback_url: None,
+                tags: Vec::new(),
+            },
+        }
+    }
+
+    pub fn retry(&self) -> Duration {
+        self.retry
+    }
+
+    pub fn expire(&self) -> Duration {
+        self.expire
+    }
+
+    pub fn callback_url(&self) -> Option<&Url> {
+        self.callback_url.as_ref()
+    }
+
+    pub fn tags(&self) -> &[String] {
+        &self.tags
+    }
+}
+
+impl TryFrom<UncheckedOptions> for EmergencyOptions {
+    type Error = Error;
+
+    fn try_from(unchecked: UncheckedOptions) -> Result<Self, Error> {
+        let mut builder = Self::builder(unchecked.retry, unchecked.expire);
+        builder.options.callback_url = unchecked.callback_url;
+        builder.options.tags = unchecked.tags;
+
+        builder.build()
+    }
+}
+
+/// Builder for [EmergencyOptions](struct.EmergencyOptions.html).
+#[derive(Clone, Debug)]
+pub struct EmergencyOptionsBuilder {
+    options: EmergencyOptions,
+}
+
+impl EmergencyOptionsBuilder {
+    /// URL Pushover calls when the message is acknowledged.
+    pub fn callback_url(mut self, url: Url) -> Self {
+        self.options.callback_url = Some(url);
+        self
+    }
+
+    /// Tag for cancelling the message together with others carrying the same tag.
+    pub fn tag<T: Into<String>>(mut self, tag: T) -> Self {
+        self.options.tags.push(tag.into());
+        self
+    }
+
+    pub fn build(self) -> Result<EmergencyOptions, Error> {
+        let mut validator = Validator::new();
+
+        validator.check(
+            self.options.retry >= MIN_EMERGENCY_RETRY,
+            "retry",
+            format!("must be at least {} seconds", MIN_EMERGENCY_RETRY.as_secs()),
+        );
+        validator.check(
+            self.options.expire > Duration::from_secs(0)
+                && self.options.expire <= MAX_EMERGENCY_EXPIRE,
+            "expire",
+            format!(
+                "must be between 1 and {} seconds",
+                MAX_EMERGENCY_EXPIRE.as_secs()
+            ),
+        );
+        for tag in &self.options.tags {
+            validator.check(
+                !tag.is_empty() && !tag.contains(','),
+                "tags",
+                "must be non-empty and must not contain commas",
+            );
+        }
+
+        validator.finish()?;
+
+        Ok(self.options)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ErrorKind;
+
+    #[test]
+    fn build_accepts_limits() {
+        let options =
+            EmergencyOptions::builder(Duration::from_secs(30), Duration::from_secs(10800))
+                .callback_url(Url::parse("https://example.com/ack").unwrap())
+                .tag("tag")
+                .build()
+                .unwrap();
+
+        assert_eq!(options.retry(), Duration::from_secs(30));
+        assert_eq!(options.expire(), Duration::from_secs(10800));
+        assert_eq!(
+            options.callback_url().unwrap().as_str(),
+            "https://example.com/ack"
+        );
+        assert_eq!(options.tags(), ["tag"]);
+    }
+
+    #[test]
+    fn build_rejects_out_of_range_durations() {
+        let result =
+            EmergencyOptions::builder(Duration::from_secs(29), Duration::from_secs(10801)).build();
+
+        match result.expect_err("Expected error") {
+            Error(ErrorKind::InvalidRequest(violations), _) => {
+                let fields: Vec<&str> = violations.iter().map(|v| v.field).collect();
+
+                assert_eq!(fields, vec!["retry", "expire"]);
+            }
+            _ => panic!("Did not receive InvalidRequest"),
+        }
+    }
+
+    #[test]
+    fn deserialize_checks_limits() {
+        let valid =
+            r#"{"retry":{"secs":60,"nanos":0},"expire":{"secs":3600,"nanos":0},"tags":["db"]}"#;
+        let options: EmergencyOptions = serde_json::from_str(valid).unwrap();
+        assert_eq!(options.tags(), ["db"]);
+
+        let invalid = r#"{"retry":{"secs":5,"nanos":0},"expire":{"secs":3600,"nanos":0}}"#;
+        let error = serde_json::from_str::<EmergencyOptions>(invalid).expect_err("Expected error");
+        assert!(error.to_string().contains("retry"));
+    }
+}
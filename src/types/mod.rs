@@ -1,13 +1,26 @@
+mod device_os;
+mod emergency;
 mod operating_system;
 mod priority;
+mod recipients;
 mod sound;
+mod timestamp;
 mod user;
 
-use serde::Deserialize;
+use std::time::SystemTime;
 
+use serde::{Deserialize, Serialize};
+
+use crate::deserializers::{deserialize_unix_timestamp, serialize_unix_timestamp};
+
+pub use self::device_os::DeviceOs;
+pub use self::emergency::{EmergencyOptions, EmergencyOptionsBuilder};
 pub use self::operating_system::OperatingSystem;
 pub use self::priority::Priority;
+pub use self::recipients::Recipients;
 pub use self::sound::Sound;
+pub(crate) use self::timestamp::{from_optional_unix_seconds, from_unix_seconds};
+pub use self::timestamp::TimestampExt;
 pub use self::user::User;
 
 #[derive(Clone, Debug, PartialEq, PartialOrd)]
@@ -16,7 +29,7 @@ pub enum UserType {
     Email(String),
 }
 
-#[derive(Clone, Debug, Deserialize, PartialEq, PartialOrd)]
+#[derive(Clone, Debug, Deserialize, PartialEq, PartialOrd, Serialize)]
 pub struct Message {
     pub id: u32,
     pub umid: u32,
@@ -25,7 +38,11 @@ pub struct Message {
     pub app: String,
     pub aid: u32,
     pub icon: String,
-    pub date: u32,
+    #[serde(
+        deserialize_with = "deserialize_unix_timestamp",
+        serialize_with = "serialize_unix_timestamp"
+    )]
+    pub date: SystemTime,
     pub priority: Priority,
     pub sound: Option<String>,
     pub url: Option<String>,
@@ -1,53 +1,58 @@
 use std::fmt;
 
 use serde::de::{self, Deserialize, Deserializer};
+use serde::{Serialize, Serializer};
 
-#[derive(Clone, Debug, PartialEq, PartialOrd)]
+/// Priority level of a message, as sent to and received from Pushover.
+///
+/// Sending with `Emergency` also needs [EmergencyOptions](struct.EmergencyOptions.html).
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
 pub enum Priority {
     Lowest,
     Low,
     Normal,
     High,
-    Emergency {
-        retry: u32,
-        expire: u32,
-        callback_url: Option<String>,
-    },
+    Emergency,
 }
 
 impl fmt::Display for Priority {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let printable = match *self {
-            Priority::Lowest => "-2",
-            Priority::Low => "-1",
-            Priority::Normal => "0",
-            Priority::High => "1",
-            Priority::Emergency { .. } => "2",
-        };
-
-        write!(f, "{}", printable)
+        write!(f, "{}", self.as_int())
     }
 }
 
 impl Priority {
+    pub fn as_int(&self) -> i8 {
+        match *self {
+            Priority::Lowest => -2,
+            Priority::Low => -1,
+            Priority::Normal => 0,
+            Priority::High => 1,
+            Priority::Emergency => 2,
+        }
+    }
+
     pub fn from_int(i: i8) -> Option<Self> {
         match i {
             -2 => Some(Priority::Lowest),
             -1 => Some(Priority::Low),
             0 => Some(Priority::Normal),
             1 => Some(Priority::High),
-            2 => {
-                Some(Priority::Emergency {
-                         retry: 0,
-                         expire: 0,
-                         callback_url: None,
-                     })
-            }
+            2 => Some(Priority::Emergency),
             _ => None,
         }
     }
 }
 
+impl Serialize for Priority {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_i8(self.as_int())
+    }
+}
+
 impl<'de> Deserialize<'de> for Priority {
     fn deserialize<D>(deserializer: D) -> Result<Priority, D::Error>
         where D: Deserializer<'de>
@@ -57,4 +62,4 @@ impl<'de> Deserialize<'de> for Priority {
         Priority::from_int(raw)
             .ok_or_else(|| de::Error::invalid_value(de::Unexpected::Signed(raw as i64), &"-2, -1, 0, 1, 2"))
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,116 @@
+use std::fmt;
+use std::iter::FromIterator;
+
+use serde::{Deserialize, Serialize};
+
+use crate::validation::MAX_RECIPIENTS;
+
+/// User or group keys a message is sent to.
+///
+/// Converts from a single key or a comma-separated list of keys.
+#[derive(Clone, Debug, Default, Deserialize, Eq, Hash, PartialEq, PartialOrd, Serialize)]
+#[serde(transparent)]
+pub struct Recipients {
+    keys: Vec<String>,
+}
+
+impl Recipients {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn push<T: Into<String>>(&mut self, key: T) {
+        self.keys.push(key.into());
+    }
+
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &str> {
+        self.keys.iter().map(String::as_str)
+    }
+
+    /// Splits into groups small enough for a single call, at most 50 keys each.
+    pub fn batches(&self) -> Vec<Recipients> {
+        self.keys
+            .chunks(MAX_RECIPIENTS)
+            .map(|keys| Recipients {
+                keys: keys.to_vec(),
+            })
+            .collect()
+    }
+}
+
+impl fmt::Display for Recipients {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.keys.join(","))
+    }
+}
+
+impl From<&str> for Recipients {
+    fn from(keys: &str) -> Self {
+        keys.split(',')
+            .map(str::trim)
+            .filter(|key| !key.is_empty())
+            .collect()
+    }
+}
+
+impl From<String> for Recipients {
+    fn from(keys: String) -> Self {
+        keys.as_str().into()
+    }
+}
+
+impl From<&String> for Recipients {
+    fn from(keys: &String) -> Self {
+        keys.as_str().into()
+    }
+}
+
+impl From<Vec<String>> for Recipients {
+    fn from(keys: Vec<String>) -> Self {
+        Self { keys }
+    }
+}
+
+impl<T: Into<String>> FromIterator<T> for Recipients {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        Self {
+            keys: iter.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_comma_separated_keys() {
+        let recipients = Recipients::from("key_1, key_2,,key_3");
+
+        assert_eq!(
+            recipients.iter().collect::<Vec<_>>(),
+            vec!["key_1", "key_2", "key_3"]
+        );
+        assert_eq!(recipients.to_string(), "key_1,key_2,key_3");
+    }
+
+    #[test]
+    fn batches_of_fifty() {
+        let recipients: Recipients = (0..120).map(|i| format!("key_{}", i)).collect();
+        let batches = recipients.batches();
+
+        assert_eq!(
+            batches.iter().map(Recipients::len).collect::<Vec<_>>(),
+            vec![50, 50, 20]
+        );
+        assert_eq!(batches[2].iter().next(), Some("key_100"));
+    }
+}
@@ -1,6 +1,9 @@
 use std::fmt;
 
-#[derive(Clone, Debug, PartialEq, PartialOrd)]
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, Deserialize, PartialEq, PartialOrd, Serialize)]
+#[serde(rename_all = "lowercase")]
 pub enum Sound {
     Pushover,
     Bike,
@@ -0,0 +1,86 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Conversions for the `SystemTime`s used by requests and responses.
+///
+/// Pushover sends and receives times as Unix seconds. The `chrono` and `time` features add
+/// conversions to those crates' types; `SystemTime` already converts from them with `into()`.
+pub trait TimestampExt {
+    /// Seconds since the Unix epoch, as sent to Pushover.
+    fn unix_seconds(&self) -> u64;
+
+    #[cfg(feature = "chrono")]
+    fn to_chrono(&self) -> chrono::DateTime<chrono::Utc>;
+
+    #[cfg(feature = "time")]
+    fn to_offset_date_time(&self) -> time::OffsetDateTime;
+}
+
+impl TimestampExt for SystemTime {
+    fn unix_seconds(&self) -> u64 {
+        self.duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or(0)
+    }
+
+    #[cfg(feature = "chrono")]
+    fn to_chrono(&self) -> chrono::DateTime<chrono::Utc> {
+        (*self).into()
+    }
+
+    #[cfg(feature = "time")]
+    fn to_offset_date_time(&self) -> time::OffsetDateTime {
+        (*self).into()
+    }
+}
+
+pub(crate) fn from_unix_seconds(seconds: u64) -> SystemTime {
+    UNIX_EPOCH + Duration::from_secs(seconds)
+}
+
+/// Pushover reports times that haven't happened yet as `0`.
+pub(crate) fn from_optional_unix_seconds(seconds: u64) -> Option<SystemTime> {
+    if seconds == 0 {
+        None
+    } else {
+        Some(from_unix_seconds(seconds))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_unix_seconds() {
+        assert_eq!(
+            from_unix_seconds(1_600_000_000).unix_seconds(),
+            1_600_000_000
+        );
+    }
+
+    #[test]
+    fn zero_is_not_set() {
+        assert_eq!(from_optional_unix_seconds(0), None);
+        assert_eq!(from_optional_unix_seconds(1), Some(from_unix_seconds(1)));
+    }
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn converts_to_chrono() {
+        assert_eq!(
+            from_unix_seconds(1_600_000_000).to_chrono().timestamp(),
+            1_600_000_000
+        );
+    }
+
+    #[cfg(feature = "time")]
+    #[test]
+    fn converts_to_time() {
+        assert_eq!(
+            from_unix_seconds(1_600_000_000)
+                .to_offset_date_time()
+                .unix_timestamp(),
+            1_600_000_000
+        );
+    }
+}
@@ -1,13 +1,14 @@
 use crate::deserializers::deserialize_option_empty_string;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Debug, Deserialize, PartialEq, PartialOrd)]
+#[derive(Clone, Debug, Deserialize, PartialEq, PartialOrd, Serialize)]
 pub struct User {
     pub user: String,
-    #[serde(deserialize_with = "deserialize_option_empty_string")]
+    #[serde(default, deserialize_with = "deserialize_option_empty_string")]
     pub device: Option<String>,
-    #[serde(deserialize_with = "deserialize_option_empty_string")]
+    #[serde(default, deserialize_with = "deserialize_option_empty_string")]
     pub memo: Option<String>,
+    #[serde(default)]
     pub disabled: bool,
 }
 
@@ -0,0 +1,133 @@
+use std::fmt;
+use std::time::Duration;
+
+use crate::error::{Error, ErrorKind};
+
+pub(crate) const MAX_MESSAGE_LENGTH: usize = 1024;
+pub(crate) const MAX_TITLE_LENGTH: usize = 250;
+pub(crate) const MAX_URL_LENGTH: usize = 512;
+pub(crate) const MAX_URL_TITLE_LENGTH: usize = 100;
+pub(crate) const MAX_RECIPIENTS: usize = 50;
+pub(crate) const MIN_EMERGENCY_RETRY: Duration = Duration::from_secs(30);
+pub(crate) const MAX_EMERGENCY_EXPIRE: Duration = Duration::from_secs(10800);
+pub(crate) const MAX_GLANCE_FIELD_LENGTH: usize = 100;
+pub(crate) const MAX_GLANCE_PERCENT: u8 = 100;
+pub(crate) const MAX_MEMO_LENGTH: usize = 200;
+pub(crate) const MAX_DEVICE_NAME_LENGTH: usize = 25;
+pub(crate) const KEY_LENGTH: usize = 30;
+
+/// A field of a request that breaks one of Pushover's limits.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Violation {
+    pub field: &'static str,
+    pub message: String,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.field, self.message)
+    }
+}
+
+/// Collects every violation of a request before reporting them together.
+#[derive(Default)]
+pub(crate) struct Validator {
+    violations: Vec<Violation>,
+}
+
+impl Validator {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn check<M: Into<String>>(&mut self, valid: bool, field: &'static str, message: M) {
+        if !valid {
+            self.violations.push(Violation {
+                field,
+                message: message.into(),
+            });
+        }
+    }
+
+    pub fn not_empty(&mut self, field: &'static str, value: &str) {
+        self.check(!value.is_empty(), field, "must not be empty");
+    }
+
+    /// Pushover counts characters, not bytes.
+    pub fn max_length(&mut self, field: &'static str, value: &str, max: usize) {
+        let length = value.chars().count();
+
+        self.check(
+            length <= max,
+            field,
+            format!("must be at most {} characters, got {}", max, length),
+        );
+    }
+
+    pub fn optional_max_length(&mut self, field: &'static str, value: &Option<String>, max: usize) {
+        if let Some(ref value) = *value {
+            self.max_length(field, value, max);
+        }
+    }
+
+    pub fn key(&mut self, field: &'static str, value: &str) {
+        self.check(
+            value.len() == KEY_LENGTH && value.chars().all(|c| c.is_ascii_alphanumeric()),
+            field,
+            format!("must be {} alphanumeric characters", KEY_LENGTH),
+        );
+    }
+
+    pub fn device_name(&mut self, field: &'static str, value: &str) {
+        self.check(
+            !value.is_empty()
+                && value.len() <= MAX_DEVICE_NAME_LENGTH
+                && value
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-'),
+            field,
+            format!(
+                "must be 1 to {} characters of A-Z, a-z, 0-9, _ or -",
+                MAX_DEVICE_NAME_LENGTH
+            ),
+        );
+    }
+
+    pub fn finish(self) -> Result<(), Error> {
+        if self.violations.is_empty() {
+            Ok(())
+        } else {
+            Err(ErrorKind::InvalidRequest(self.violations).into())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_characters_not_bytes() {
+        let mut validator = Validator::new();
+        validator.max_length("message", &"é".repeat(10), 10);
+
+        assert!(validator.finish().is_ok());
+    }
+
+    #[test]
+    fn reports_every_violation() {
+        let mut validator = Validator::new();
+        validator.not_empty("message", "");
+        validator.key("user", "not a key");
+        validator.device_name("device", "bad device name");
+
+        match validator.finish().expect_err("Expected error") {
+            Error(ErrorKind::InvalidRequest(violations), _) => {
+                let fields: Vec<&str> = violations.iter().map(|v| v.field).collect();
+
+                assert_eq!(fields, vec!["message", "user", "device"]);
+            }
+            _ => panic!("Did not receive InvalidRequest"),
+        }
+    }
+}
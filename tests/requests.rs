@@ -2,9 +2,22 @@ extern crate mockito;
 extern crate pushover;
 extern crate tokio_core;
 
+use futures::StreamExt;
 use mockito::{mock, Matcher};
-use pushover::requests::message::{Limits, LimitsResponse};
-use pushover::{Error, ErrorKind, API};
+use pushover::requests::glance::Glance;
+use pushover::requests::message::{Limits, LimitsResponse, Overflow, SendMessage};
+use pushover::requests::open_client::Login;
+use pushover::requests::verification::Verification;
+use pushover::{
+    BackgroundSender, BulkLicense, BulkVerifier, Deduplicator, Digest, EmergencyOptions, Error,
+    ErrorKind, EscalationEvent, EscalationPolicy, GlanceUpdater, GroupChange, GroupImport,
+    GroupSync, KeyStatus, LicenseOutcome, ManualClock, OpenClientSession, OperatingSystem, Outbox,
+    OutboxWorker, Progress, RateLimiter, Router, SeenStore, User, UserType, VerificationReport,
+    API,
+};
+use pushover::{Message, Priority, Recipients};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, UNIX_EPOCH};
 
 #[test]
 fn test_sync_client_returns_pushover_error() {
@@ -51,7 +64,7 @@ fn test_sync_client_does_not_return_error() {
             assert_eq!(request, "request_number");
             assert_eq!(limit, 1);
             assert_eq!(remaining, 2);
-            assert_eq!(reset, 3);
+            assert_eq!(reset, UNIX_EPOCH + Duration::from_secs(3));
         }
         _ => panic!("Received error"),
     }
@@ -106,8 +119,1295 @@ fn test_async_client_does_not_return_error() {
             assert_eq!(request, "request_number");
             assert_eq!(limit, 1);
             assert_eq!(remaining, 2);
-            assert_eq!(reset, 3);
+            assert_eq!(reset, UNIX_EPOCH + Duration::from_secs(3));
         }
         _ => panic!("Received error"),
     }
 }
+
+#[test]
+fn test_sanitized_request_redacts_credentials() {
+    let request = Limits::new("token");
+    let sanitized = API::new()
+        .base_url("https://example.com")
+        .sanitized(&request);
+
+    assert_eq!(
+        sanitized.to_string(),
+        "GET https://example.com/1/apps/limits.json?token=REDACTED"
+    );
+}
+
+#[test]
+fn test_transport_errors_redact_credentials() {
+    let api = API::new().base_url("http://127.0.0.1:1");
+    let request = SendMessage::new("SECRETTOKEN123", "user_key", "message");
+
+    let errors = vec![
+        api.send(&request).expect_err("Expected error"),
+        tokio_test::block_on(api.send_async(&request)).expect_err("Expected error"),
+    ];
+
+    for error in errors {
+        match error.kind() {
+            ErrorKind::Reqqest(..) => {}
+            other => panic!("Expected Reqqest, got {:?}", other),
+        }
+        assert!(!format!("{}", error).contains("SECRETTOKEN123"));
+        assert!(!format!("{:?}", error).contains("SECRETTOKEN123"));
+        assert!(format!("{}", error).contains("token=REDACTED"));
+    }
+}
+
+#[test]
+fn test_client_validates_before_sending() {
+    let _m = mock("POST", Matcher::Any)
+        .match_query(Matcher::UrlEncoded("token".into(), "validate_token".into()))
+        .expect(0)
+        .create();
+
+    let request = SendMessage::new("validate_token", "uQiRzpo4DXghDmr9QzzfQu27cmVRsG", "");
+    let response = API::new()
+        .base_url(&mockito::server_url())
+        .validate(true)
+        .send(&request);
+
+    match response.expect_err("Expected error") {
+        Error(ErrorKind::InvalidRequest(violations), _) => {
+            assert_eq!(violations.len(), 1);
+            assert_eq!(violations[0].field, "message");
+        }
+        _ => panic!("Did not receive InvalidRequest"),
+    }
+
+    _m.assert();
+}
+
+#[test]
+fn test_sync_client_sends_split_parts_in_order() {
+    let first = mock("POST", "/1/messages.json")
+        .match_query(Matcher::UrlEncoded(
+            "message".into(),
+            format!("{} (1/2)", "a".repeat(1018)),
+        ))
+        .with_body("{\"status\":1, \"request\":\"request_1\", \"receipt\": \"receipt_1\"}")
+        .create();
+    let second = mock("POST", "/1/messages.json")
+        .match_query(Matcher::UrlEncoded(
+            "message".into(),
+            format!("{} (2/2)", "a".repeat(982)),
+        ))
+        .with_body("{\"status\":1, \"request\":\"request_2\"}")
+        .create();
+
+    let mut request = SendMessage::new("token", "user_key", "a".repeat(2000));
+    request.set_overflow(Overflow::Split);
+
+    let responses: Vec<_> = API::new()
+        .base_url(&mockito::server_url())
+        .send_parts(&request)
+        .into_iter()
+        .map(|result| result.expect("Error sending part"))
+        .collect();
+
+    let requests: Vec<&str> = responses.iter().map(|r| r.request.as_str()).collect();
+    assert_eq!(requests, vec!["request_1", "request_2"]);
+    assert_eq!(responses[0].receipt.as_deref(), Some("receipt_1"));
+
+    first.assert();
+    second.assert();
+}
+
+#[test]
+fn test_sync_client_keeps_sent_parts_when_a_part_fails() {
+    let _first = mock("POST", "/1/messages.json")
+        .match_query(Matcher::UrlEncoded(
+            "message".into(),
+            format!("{} (1/2)", "b".repeat(1018)),
+        ))
+        .with_body("{\"status\":1, \"request\":\"request_1\", \"receipt\": \"receipt_1\"}")
+        .create();
+    let _second = mock("POST", "/1/messages.json")
+        .match_query(Matcher::UrlEncoded(
+            "message".into(),
+            format!("{} (2/2)", "b".repeat(982)),
+        ))
+        .with_status(500)
+        .with_body("{\"status\":0, \"request\":\"request_2\", \"errors\": [\"internal error\"]}")
+        .create();
+
+    let mut request = SendMessage::new("token", "user_key", "b".repeat(2000));
+    request.set_overflow(Overflow::Split);
+    let api = API::new().base_url(&mockito::server_url());
+
+    let results = api.send_parts(&request);
+
+    assert_eq!(results.len(), 2);
+    assert_eq!(
+        results[0].as_ref().unwrap().receipt.as_deref(),
+        Some("receipt_1")
+    );
+    assert!(results[1].is_err());
+
+    match api.send(&request).expect_err("Expected error") {
+        Error(ErrorKind::SplitRequired(2), _) => {}
+        e => panic!("Did not receive SplitRequired: {}", e),
+    }
+}
+
+#[test]
+fn test_sync_client_reports_results_per_recipient() {
+    let keys: Vec<String> = (0..52).map(|i| format!("user_{}", i)).collect();
+    let first_batch = keys[..50].join(",");
+
+    let _first = mock("POST", "/1/messages.json")
+        .match_query(Matcher::UrlEncoded("user".into(), first_batch))
+        .with_body("{\"status\":1, \"request\":\"request_1\"}")
+        .create();
+    let _second = mock("POST", "/1/messages.json")
+        .match_query(Matcher::UrlEncoded("user".into(), "user_50,user_51".into()))
+        .with_body("{\"status\":0, \"user\":\"invalid\", \"request\":\"request_2\", \"errors\": [\"user identifier is invalid\"]}")
+        .create();
+    let _valid = mock("POST", "/1/messages.json")
+        .match_query(Matcher::UrlEncoded("user".into(), "user_50".into()))
+        .with_body("{\"status\":1, \"request\":\"request_3\"}")
+        .create();
+    let _invalid = mock("POST", "/1/messages.json")
+        .match_query(Matcher::UrlEncoded("user".into(), "user_51".into()))
+        .with_body("{\"status\":0, \"user\":\"invalid\", \"request\":\"request_4\", \"errors\": [\"user identifier is invalid\"]}")
+        .create();
+
+    let request = SendMessage::new("batch_token", keys.iter().collect::<Recipients>(), "hello");
+    let results = API::new()
+        .base_url(&mockito::server_url())
+        .send_batched(&request)
+        .expect("Error sending batches");
+
+    assert_eq!(results.len(), 52);
+    assert_eq!(results[0].recipient, "user_0");
+    assert_eq!(results[49].result.as_ref().unwrap().request, "request_1");
+    assert_eq!(results[50].recipient, "user_50");
+    assert_eq!(results[50].result.as_ref().unwrap().request, "request_3");
+    assert_eq!(results[51].recipient, "user_51");
+    match results[51].result {
+        Err(Error(ErrorKind::InvalidRecipient { ref field, .. }, _)) => assert_eq!(field, "user"),
+        ref other => panic!("Expected InvalidRecipient, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_batch_wide_rejection_is_not_retried_per_recipient() {
+    let _batch = mock("POST", "/1/messages.json")
+        .match_query(Matcher::AllOf(vec![
+            Matcher::UrlEncoded("token".into(), "invalid_batch_token".into()),
+            Matcher::UrlEncoded("user".into(), "batch_a,batch_b".into()),
+        ]))
+        .with_status(400)
+        .with_body("{\"status\":0, \"token\":\"invalid\", \"request\":\"request_1\", \"errors\": [\"application token is invalid\"]}")
+        .expect(1)
+        .create();
+    let single = mock("POST", "/1/messages.json")
+        .match_query(Matcher::AllOf(vec![
+            Matcher::UrlEncoded("token".into(), "invalid_batch_token".into()),
+            Matcher::Regex("user=batch_[ab](&|$)".into()),
+        ]))
+        .expect(0)
+        .create();
+
+    let request = SendMessage::new("invalid_batch_token", "batch_a,batch_b", "hello");
+    let results = API::new()
+        .base_url(&mockito::server_url())
+        .send_batched(&request)
+        .expect("Error sending batches");
+
+    assert_eq!(results.len(), 2);
+    assert_eq!(results[0].recipient, "batch_a");
+    assert_eq!(results[1].recipient, "batch_b");
+    for result in &results {
+        match result.result {
+            Err(Error(ErrorKind::PushoverError { ref errors, .. }, _)) => {
+                assert_eq!(errors, &["application token is invalid"])
+            }
+            ref other => panic!("Expected PushoverError, got {:?}", other),
+        }
+    }
+    single.assert();
+}
+
+fn mock_fan_out(user: &str, status: usize, body: &str) -> mockito::Mock {
+    mock("POST", "/1/messages.json")
+        .match_query(Matcher::AllOf(vec![
+            Matcher::UrlEncoded("token".into(), "fan_out_token".into()),
+            Matcher::UrlEncoded("user".into(), user.into()),
+        ]))
+        .with_status(status)
+        .with_body(body)
+        .create()
+}
+
+fn fan_out_requests() -> Vec<SendMessage> {
+    ["fan_a", "fan_b", "fan_c", "fan_d"]
+        .iter()
+        .map(|user| SendMessage::new("fan_out_token", *user, "incident"))
+        .collect()
+}
+
+#[test]
+fn test_send_all_stops_on_quota_exhaustion() {
+    let _a = mock_fan_out("fan_a", 200, "{\"status\":1, \"request\":\"request_a\"}");
+    let _b = mock_fan_out("fan_b", 200, "{\"status\":1, \"request\":\"request_b\"}");
+    let _c = mock_fan_out(
+        "fan_c",
+        429,
+        "{\"status\":0, \"request\":\"request_c\", \"errors\": [\"quota\"]}",
+    );
+    let _d = mock_fan_out("fan_d", 200, "{\"status\":1, \"request\":\"request_d\"}");
+
+    let api = API::new().base_url(&mockito::server_url());
+
+    let results: Vec<_> =
+        tokio_test::block_on(api.send_all(fan_out_requests(), 2).collect::<Vec<_>>());
+
+    assert_eq!(results.len(), 3);
+    assert_eq!(results[0].as_ref().unwrap().request, "request_a");
+    assert_eq!(results[1].as_ref().unwrap().request, "request_b");
+    match results[2] {
+        Err(Error(ErrorKind::QuotaExceeded { ref request, .. }, _)) => {
+            assert_eq!(request, "request_c")
+        }
+        _ => panic!("Did not receive QuotaExceeded"),
+    }
+
+    let results = api.send_all_blocking(fan_out_requests(), 3);
+
+    assert_eq!(results.len(), 3);
+    assert_eq!(results[0].as_ref().unwrap().request, "request_a");
+    assert_eq!(results[1].as_ref().unwrap().request, "request_b");
+    assert!(results[2].is_err());
+}
+
+#[test]
+fn test_rate_limiter_reads_quota_headers() {
+    let _m = mock("POST", "/1/messages.json")
+        .match_query(Matcher::UrlEncoded("token".into(), "limited_token".into()))
+        .with_header("X-Limit-App-Limit", "10000")
+        .with_header("X-Limit-App-Remaining", "3")
+        .with_header("X-Limit-App-Reset", "4102444800")
+        .with_body("{\"status\":1, \"request\":\"request_number\"}")
+        .expect(1)
+        .create();
+
+    let api = API::new()
+        .base_url(&mockito::server_url())
+        .rate_limiter(RateLimiter::new(10.0, 10).emergency_reserve(5));
+
+    let request = SendMessage::new("limited_token", "user_key", "hello");
+    api.send(&request).expect("Error sending message");
+
+    match api.send(&request).expect_err("Expected error") {
+        Error(ErrorKind::QuotaReserved(remaining), _) => assert_eq!(remaining, 3),
+        _ => panic!("Did not receive QuotaReserved"),
+    }
+
+    _m.assert();
+}
+
+#[test]
+fn test_outbox_worker_delivers_pending_messages() {
+    let _m = mock("POST", "/1/messages.json")
+        .match_query(Matcher::UrlEncoded("token".into(), "outbox_token".into()))
+        .with_body("{\"status\":1, \"request\":\"request_number\"}")
+        .expect(2)
+        .create();
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("outbox.jsonl");
+    let outbox = Arc::new(Outbox::open(&path).expect("Error opening outbox"));
+    outbox
+        .enqueue(SendMessage::new("outbox_token", "user_key", "first"))
+        .unwrap();
+
+    let worker = OutboxWorker::spawn(API::new().base_url(&mockito::server_url()), outbox.clone());
+    outbox
+        .enqueue(SendMessage::new("outbox_token", "user_key", "second"))
+        .unwrap();
+
+    for _ in 0..100 {
+        if outbox.pending().is_empty() {
+            break;
+        }
+        std::thread::sleep(Duration::from_millis(20));
+    }
+    worker.stop();
+
+    assert!(outbox.pending().is_empty());
+    assert!(Outbox::open(&path).unwrap().pending().is_empty());
+    _m.assert();
+}
+
+#[test]
+fn test_outbox_worker_moves_past_undeliverable_message() {
+    let poison = mock("POST", "/1/messages.json")
+        .match_query(Matcher::AllOf(vec![
+            Matcher::UrlEncoded("token".into(), "poison_token".into()),
+            Matcher::UrlEncoded("message".into(), "poison".into()),
+        ]))
+        .with_status(400)
+        .with_body("Bad Request")
+        .expect(1)
+        .create();
+    let good = mock("POST", "/1/messages.json")
+        .match_query(Matcher::AllOf(vec![
+            Matcher::UrlEncoded("token".into(), "poison_token".into()),
+            Matcher::UrlEncoded("message".into(), "good".into()),
+        ]))
+        .with_body("{\"status\":1, \"request\":\"request_number\"}")
+        .expect(1)
+        .create();
+
+    let dir = tempfile::tempdir().unwrap();
+    let outbox = Arc::new(Outbox::open(dir.path().join("outbox.jsonl")).unwrap());
+    outbox
+        .enqueue(SendMessage::new("poison_token", "user_key", "poison"))
+        .unwrap();
+    outbox
+        .enqueue(SendMessage::new("poison_token", "user_key", "good"))
+        .unwrap();
+
+    let worker = OutboxWorker::spawn(API::new().base_url(&mockito::server_url()), outbox.clone());
+    for _ in 0..100 {
+        if outbox.pending().is_empty() {
+            break;
+        }
+        std::thread::sleep(Duration::from_millis(20));
+    }
+    worker.stop();
+
+    assert!(outbox.pending().is_empty());
+    let failed = outbox.failed();
+    assert_eq!(failed.len(), 1);
+    assert_eq!(failed[0].1.message.message, "poison");
+    poison.assert();
+    good.assert();
+}
+
+#[test]
+fn test_outbox_worker_retries_server_errors() {
+    let _m = mock("POST", "/1/messages.json")
+        .match_query(Matcher::UrlEncoded(
+            "token".into(),
+            "unavailable_token".into(),
+        ))
+        .with_status(503)
+        .with_body("{\"status\":0, \"request\":\"request_number\", \"errors\":[\"try again\"]}")
+        .create();
+
+    let dir = tempfile::tempdir().unwrap();
+    let outbox = Arc::new(Outbox::open(dir.path().join("outbox.jsonl")).unwrap());
+    outbox
+        .enqueue(SendMessage::new("unavailable_token", "user_key", "message"))
+        .unwrap();
+
+    let worker = OutboxWorker::spawn(API::new().base_url(&mockito::server_url()), outbox.clone());
+    std::thread::sleep(Duration::from_millis(200));
+    worker.stop();
+
+    assert_eq!(outbox.pending().len(), 1);
+    assert!(outbox.failed().is_empty());
+}
+
+#[test]
+fn test_background_sender_reports_failures() {
+    let _ok = mock("POST", "/1/messages.json")
+        .match_query(Matcher::AllOf(vec![
+            Matcher::UrlEncoded("token".into(), "background_token".into()),
+            Matcher::UrlEncoded("message".into(), "ok".into()),
+        ]))
+        .with_body("{\"status\":1, \"request\":\"request_number\"}")
+        .expect(2)
+        .create();
+    let _rejected = mock("POST", "/1/messages.json")
+        .match_query(Matcher::AllOf(vec![
+            Matcher::UrlEncoded("token".into(), "background_token".into()),
+            Matcher::UrlEncoded("message".into(), "rejected".into()),
+        ]))
+        .with_status(400)
+        .with_body(
+            "{\"status\":0, \"errors\":[\"user key is invalid\"], \"request\":\"request_number\"}",
+        )
+        .create();
+
+    let failures = Arc::new(Mutex::new(Vec::new()));
+    let sender = {
+        let failures = failures.clone();
+        BackgroundSender::new(API::new().base_url(&mockito::server_url()), 10)
+            .on_failure(move |message, _| failures.lock().unwrap().push(message.message.clone()))
+    };
+
+    for text in &["ok", "rejected", "ok"] {
+        assert!(sender
+            .enqueue(SendMessage::new("background_token", "user_key", *text))
+            .unwrap()
+            .is_none());
+    }
+
+    assert!(sender.flush(Duration::from_secs(5)));
+    assert!(sender.is_empty());
+    assert_eq!(*failures.lock().unwrap(), ["rejected"]);
+    _ok.assert();
+}
+
+#[test]
+fn test_dedup_suppresses_repeats() {
+    let _m = mock("POST", "/1/messages.json")
+        .match_query(Matcher::UrlEncoded("token".into(), "dedup_token".into()))
+        .with_body("{\"status\":1, \"request\":\"request_number\"}")
+        .expect(1)
+        .create();
+
+    let dedup = Deduplicator::new(
+        API::new().base_url(&mockito::server_url()),
+        Duration::from_secs(60),
+    );
+    let msg = SendMessage::new("dedup_token", "user_key", "db01 is down");
+
+    assert!(tokio_test::block_on(dedup.send_async(&msg))
+        .unwrap()
+        .is_some());
+    assert!(tokio_test::block_on(dedup.send_async(&msg))
+        .unwrap()
+        .is_none());
+    assert!(dedup.send(&msg).unwrap().is_none());
+    assert!(dedup.flush().unwrap().is_empty());
+    _m.assert();
+}
+
+#[test]
+fn test_digest_sends_low_priority_together() {
+    let _urgent = mock("POST", "/1/messages.json")
+        .match_query(Matcher::AllOf(vec![
+            Matcher::UrlEncoded("token".into(), "digest_token".into()),
+            Matcher::UrlEncoded("priority".into(), "1".into()),
+        ]))
+        .with_body("{\"status\":1, \"request\":\"request_number\"}")
+        .expect(1)
+        .create();
+    let _digest = mock("POST", "/1/messages.json")
+        .match_query(Matcher::AllOf(vec![
+            Matcher::UrlEncoded("token".into(), "digest_token".into()),
+            Matcher::UrlEncoded("title".into(), "2 notifications".into()),
+            Matcher::UrlEncoded("message".into(), "• first\n• second".into()),
+        ]))
+        .with_body("{\"status\":1, \"request\":\"request_number\"}")
+        .expect(1)
+        .create();
+
+    let digest = Digest::new(
+        API::new().base_url(&mockito::server_url()),
+        Duration::from_secs(3600),
+        10,
+    );
+
+    for (text, priority) in &[
+        ("first", Priority::Low),
+        ("urgent", Priority::High),
+        ("second", Priority::Lowest),
+    ] {
+        let mut msg = SendMessage::new("digest_token", "user_key", *text);
+        msg.set_priority(*priority);
+        digest.send(&msg).unwrap();
+    }
+
+    assert_eq!(digest.len(), 2);
+    digest.flush().unwrap();
+    assert!(digest.is_empty());
+
+    _urgent.assert();
+    _digest.assert();
+}
+
+#[test]
+fn test_digest_finish_returns_unsent_messages() {
+    let _failed = mock("POST", "/1/messages.json")
+        .match_query(Matcher::UrlEncoded(
+            "token".into(),
+            "unsent_digest_token".into(),
+        ))
+        .with_status(500)
+        .expect(1)
+        .create();
+
+    let digest = Digest::new(
+        API::new().base_url(&mockito::server_url()),
+        Duration::from_secs(3600),
+        10,
+    );
+
+    for text in &["first", "second"] {
+        let mut msg = SendMessage::new("unsent_digest_token", "user_key", *text);
+        msg.set_priority(Priority::Low);
+        digest.send(&msg).unwrap();
+    }
+
+    match digest.finish() {
+        Err(Error(ErrorKind::DigestUnsent(messages), _)) => {
+            let texts: Vec<&str> = messages.iter().map(|m| m.message.as_str()).collect();
+            assert_eq!(texts, ["first", "second"]);
+        }
+        other => panic!("expected unsent messages, got {:?}", other),
+    }
+
+    _failed.assert();
+}
+
+#[test]
+fn test_escalation_pages_next_tier() {
+    let tier_one = "escalationTierOne000000000000";
+    let tier_two = "escalationTierTwo000000000000";
+    let page = |user: &str, receipt: &str| {
+        mock("POST", "/1/messages.json")
+            .match_query(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("token".into(), "escalation_token".into()),
+                Matcher::UrlEncoded("user".into(), user.into()),
+            ]))
+            .with_body(format!(
+                "{{\"status\":1, \"request\":\"request_number\", \"receipt\":\"{}\"}}",
+                receipt
+            ))
+            .expect(1)
+            .create()
+    };
+    let status = |receipt: &str, acknowledged: u8| {
+        mock("GET", format!("/1/receipts/{}.json", receipt).as_str())
+            .match_query(Matcher::Any)
+            .with_body(format!(
+                "{{\"status\":1, \"acknowledged\":{}, \"acknowledged_at\":0, \
+                 \"acknowledged_by\":\"{}\", \"acknowledged_by_device\":\"phone\", \
+                 \"last_delivered_at\":0, \"expired\":0, \"expires_at\":0, \
+                 \"called_back\":0, \"called_back_at\":0, \"request\":\"request_number\"}}",
+                acknowledged, tier_two
+            ))
+            .create()
+    };
+
+    let _page_one = page(tier_one, "escalation_receipt_one");
+    let _page_two = page(tier_two, "escalation_receipt_two");
+    let _status_one = status("escalation_receipt_one", 0);
+    let _status_two = status("escalation_receipt_two", 1);
+    let _cancel = mock("POST", "/1/receipts/escalation_receipt_one/cancel.json")
+        .match_query(Matcher::Any)
+        .with_body("{\"status\":1, \"request\":\"request_number\"}")
+        .expect(1)
+        .create();
+
+    let policy = EscalationPolicy::new()
+        .tier(tier_one, Duration::from_millis(50))
+        .tier(tier_two, Duration::from_secs(5))
+        .poll_interval(Duration::from_millis(10));
+    let mut msg = SendMessage::new("escalation_token", tier_one, "db01 is down");
+    msg.set_emergency(
+        EmergencyOptions::builder(Duration::from_secs(30), Duration::from_secs(3600))
+            .build()
+            .unwrap(),
+    );
+
+    let timeline = policy
+        .run(&API::new().base_url(&mockito::server_url()), &msg)
+        .unwrap();
+
+    assert_eq!(timeline.acknowledged_by(), Some(tier_two));
+    assert_eq!(
+        timeline.paged(),
+        [&Recipients::from(tier_one), &Recipients::from(tier_two)]
+    );
+    assert!(timeline
+        .events
+        .iter()
+        .any(|event| matches!(event, EscalationEvent::Cancelled { tier: 0, .. })));
+    _page_one.assert();
+    _page_two.assert();
+    _cancel.assert();
+}
+
+fn receipt_status(receipt: &str, acknowledged: u8, expired: u8) -> mockito::Mock {
+    mock("GET", format!("/1/receipts/{}.json", receipt).as_str())
+        .match_query(Matcher::Any)
+        .with_body(format!(
+            "{{\"status\":1, \"acknowledged\":{}, \"acknowledged_at\":0, \
+             \"acknowledged_by\":\"someone\", \"acknowledged_by_device\":\"phone\", \
+             \"last_delivered_at\":0, \"expired\":{}, \"expires_at\":0, \
+             \"called_back\":0, \"called_back_at\":0, \"request\":\"request_number\"}}",
+            acknowledged, expired
+        ))
+}
+
+fn page_tier(token: &str, user: &str, receipt: &str) -> mockito::Mock {
+    mock("POST", "/1/messages.json")
+        .match_query(Matcher::AllOf(vec![
+            Matcher::UrlEncoded("token".into(), token.into()),
+            Matcher::UrlEncoded("user".into(), user.into()),
+        ]))
+        .with_body(format!(
+            "{{\"status\":1, \"request\":\"request_number\", \"receipt\":\"{}\"}}",
+            receipt
+        ))
+}
+
+fn escalation_message(token: &str, user: &str) -> SendMessage {
+    let mut msg = SendMessage::new(token, user, "db01 is down");
+    msg.set_emergency(
+        EmergencyOptions::builder(Duration::from_secs(30), Duration::from_secs(3600))
+            .build()
+            .unwrap(),
+    );
+    msg
+}
+
+#[test]
+fn test_escalation_checks_receipt_before_paging_next_tier() {
+    let tier_one = "escalationLateOne000000000000";
+    let tier_two = "escalationLateTwo000000000000";
+    let _page_one = page_tier("late_token", tier_one, "late_receipt")
+        .expect(1)
+        .create();
+    let page_two = page_tier("late_token", tier_two, "late_receipt_two")
+        .expect(0)
+        .create();
+    // Unacknowledged at the tier's only poll, acknowledged by the time the next tier is due.
+    let _waiting = receipt_status("late_receipt", 0, 0).expect(1).create();
+    let _acknowledged = receipt_status("late_receipt", 1, 0).create();
+
+    let policy = EscalationPolicy::new()
+        .tier(tier_one, Duration::from_millis(10))
+        .tier(tier_two, Duration::from_secs(5))
+        .poll_interval(Duration::from_secs(1));
+
+    let timeline = policy
+        .run(
+            &API::new().base_url(&mockito::server_url()),
+            &escalation_message("late_token", tier_one),
+        )
+        .unwrap();
+
+    assert_eq!(timeline.acknowledged_by(), Some("someone"));
+    assert_eq!(timeline.paged(), [&Recipients::from(tier_one)]);
+    page_two.assert();
+}
+
+#[test]
+fn test_escalation_skips_cancelling_expired_receipt() {
+    let tier_one = "escalationExpiredOne000000000";
+    let tier_two = "escalationExpiredTwo000000000";
+    let _page_one = page_tier("expired_token", tier_one, "expired_receipt_one").create();
+    let _page_two = page_tier("expired_token", tier_two, "expired_receipt_two").create();
+    let _status_one = receipt_status("expired_receipt_one", 0, 1).create();
+    let _status_two = receipt_status("expired_receipt_two", 1, 0).create();
+    let cancel = mock("POST", "/1/receipts/expired_receipt_one/cancel.json")
+        .match_query(Matcher::Any)
+        .expect(0)
+        .create();
+
+    let policy = EscalationPolicy::new()
+        .tier(tier_one, Duration::from_secs(5))
+        .tier(tier_two, Duration::from_secs(5))
+        .poll_interval(Duration::from_millis(10));
+
+    let timeline = policy
+        .run(
+            &API::new().base_url(&mockito::server_url()),
+            &escalation_message("expired_token", tier_one),
+        )
+        .unwrap();
+
+    assert_eq!(timeline.paged().len(), 2);
+    assert!(timeline
+        .events
+        .iter()
+        .any(|event| matches!(event, EscalationEvent::Expired { tier: 0, .. })));
+    cancel.assert();
+}
+
+#[test]
+fn test_escalation_records_failed_cancel_and_carries_on() {
+    let tier_one = "escalationCancelOne0000000000";
+    let tier_two = "escalationCancelTwo0000000000";
+    let _page_one = page_tier("cancel_token", tier_one, "cancel_receipt_one").create();
+    let _page_two = page_tier("cancel_token", tier_two, "cancel_receipt_two").create();
+    let _status_one = receipt_status("cancel_receipt_one", 0, 0).create();
+    let status_two = receipt_status("cancel_receipt_two", 1, 0)
+        .expect_at_least(1)
+        .create();
+    let _cancel = mock("POST", "/1/receipts/cancel_receipt_one/cancel.json")
+        .match_query(Matcher::Any)
+        .with_status(400)
+        .with_body(
+            "{\"status\":0, \"request\":\"request_number\", \"errors\":[\"receipt not found\"]}",
+        )
+        .create();
+
+    let policy = EscalationPolicy::new()
+        .tier(tier_one, Duration::from_millis(20))
+        .tier(tier_two, Duration::from_secs(5))
+        .poll_interval(Duration::from_millis(10));
+
+    let timeline = policy
+        .run(
+            &API::new().base_url(&mockito::server_url()),
+            &escalation_message("cancel_token", tier_one),
+        )
+        .unwrap();
+
+    assert_eq!(timeline.acknowledged_by(), Some("someone"));
+    assert!(timeline.events.iter().any(|event| matches!(
+        event,
+        EscalationEvent::CancelFailed { tier: 0, reason, .. } if reason.contains("receipt not found")
+    )));
+    assert_eq!(timeline.active_receipts(), ["cancel_receipt_one"]);
+    status_two.assert();
+}
+
+#[test]
+fn test_escalation_returns_timeline_when_paging_fails() {
+    let tier_one = "escalationFailOne000000000000";
+    let tier_two = "escalationFailTwo000000000000";
+    let _page_one = mock("POST", "/1/messages.json")
+        .match_query(Matcher::UrlEncoded("user".into(), tier_one.into()))
+        .with_body("{\"status\":1, \"request\":\"request_number\", \"receipt\":\"escalation_fail_receipt\"}")
+        .create();
+    let _page_two = mock("POST", "/1/messages.json")
+        .match_query(Matcher::UrlEncoded("user".into(), tier_two.into()))
+        .with_status(400)
+        .with_body(
+            "{\"status\":0, \"request\":\"request_number\", \"errors\":[\"user key is invalid\"]}",
+        )
+        .create();
+    let _status = mock("GET", "/1/receipts/escalation_fail_receipt.json")
+        .match_query(Matcher::Any)
+        .with_body(
+            "{\"status\":1, \"acknowledged\":0, \"acknowledged_at\":0, \
+             \"acknowledged_by\":\"\", \"acknowledged_by_device\":\"\", \
+             \"last_delivered_at\":0, \"expired\":0, \"expires_at\":0, \
+             \"called_back\":0, \"called_back_at\":0, \"request\":\"request_number\"}",
+        )
+        .create();
+
+    let policy = EscalationPolicy::new()
+        .tier(tier_one, Duration::from_millis(20))
+        .tier(tier_two, Duration::from_secs(5))
+        .poll_interval(Duration::from_millis(10));
+    let mut msg = SendMessage::new("escalation_fail_token", tier_one, "db01 is down");
+    msg.set_emergency(
+        EmergencyOptions::builder(Duration::from_secs(30), Duration::from_secs(3600))
+            .build()
+            .unwrap(),
+    );
+
+    let failure = policy
+        .run(&API::new().base_url(&mockito::server_url()), &msg)
+        .expect_err("Expected failure");
+
+    assert_eq!(failure.timeline.paged(), [&Recipients::from(tier_one)]);
+    assert_eq!(
+        failure.timeline.active_receipts(),
+        ["escalation_fail_receipt"]
+    );
+    assert!(matches!(
+        failure.error,
+        Error(ErrorKind::PushoverError { .. }, _)
+    ));
+}
+
+#[test]
+fn test_group_sync_applies_plan() {
+    let _list = mock("GET", "/1/groups/syncGroup.json")
+        .match_query(Matcher::Any)
+        .with_body(
+            "{\"status\":1, \"request\":\"request_number\", \"name\":\"ops\", \"users\":[\
+             {\"user\":\"stale\", \"device\":\"\", \"memo\":\"\", \"disabled\":false}]}",
+        )
+        .expect(2)
+        .create();
+    let _add = mock("POST", "/1/groups/syncGroup/add_user.json")
+        .match_query(Matcher::UrlEncoded("user".into(), "fresh".into()))
+        .with_body("{\"status\":1, \"request\":\"request_number\"}")
+        .expect(1)
+        .create();
+    let _remove = mock("POST", "/1/groups/syncGroup/delete_user.json")
+        .match_query(Matcher::UrlEncoded("user".into(), "stale".into()))
+        .with_status(400)
+        .with_body(
+            "{\"status\":0, \"errors\":[\"user is not a member\"], \"request\":\"request_number\"}",
+        )
+        .expect(1)
+        .create();
+
+    let api = API::new().base_url(&mockito::server_url());
+    let sync = GroupSync::new("sync_token", "syncGroup", vec![User::new("fresh")]);
+
+    let dry_run = sync.clone().dry_run(true).sync(&api).unwrap();
+    assert_eq!(dry_run.plan.changes.len(), 2);
+    assert!(dry_run.results.is_empty());
+
+    let report = sync.sync(&api).unwrap();
+    assert!(!report.is_success());
+    assert_eq!(
+        report.results[0].change,
+        GroupChange::Add(User::new("fresh"))
+    );
+    assert!(report.results[0].result.is_ok());
+    assert!(matches!(report.results[1].change, GroupChange::Remove(_)));
+    assert!(report.results[1].result.is_err());
+
+    _list.assert();
+    _add.assert();
+    _remove.assert();
+}
+
+#[test]
+fn test_group_sync_restores_member_when_update_fails() {
+    let _list = mock("GET", "/1/groups/updateGroup.json")
+        .match_query(Matcher::Any)
+        .with_body(
+            "{\"status\":1, \"request\":\"request_number\", \"name\":\"ops\", \"users\":[\
+             {\"user\":\"updated\", \"device\":\"\", \"memo\":\"old\", \"disabled\":false}]}",
+        )
+        .create();
+    let remove = mock("POST", "/1/groups/updateGroup/delete_user.json")
+        .match_query(Matcher::UrlEncoded("user".into(), "updated".into()))
+        .with_body("{\"status\":1, \"request\":\"request_number\"}")
+        .expect(1)
+        .create();
+    let add = mock("POST", "/1/groups/updateGroup/add_user.json")
+        .match_query(Matcher::UrlEncoded("memo".into(), "new".into()))
+        .with_status(400)
+        .with_body(
+            "{\"status\":0, \"errors\":[\"memo is invalid\"], \"request\":\"request_number\"}",
+        )
+        .expect(1)
+        .create();
+    let restore = mock("POST", "/1/groups/updateGroup/add_user.json")
+        .match_query(Matcher::UrlEncoded("memo".into(), "old".into()))
+        .with_body("{\"status\":1, \"request\":\"request_number\"}")
+        .expect(1)
+        .create();
+
+    let mut desired = User::new("updated");
+    desired.set_memo("new");
+    let api = API::new().base_url(&mockito::server_url());
+    let report = GroupSync::new("sync_token", "updateGroup", vec![desired])
+        .sync(&api)
+        .unwrap();
+
+    assert!(!report.is_success());
+    assert!(matches!(
+        report.results[0].change,
+        GroupChange::Update { .. }
+    ));
+    match report.results[0].result {
+        Err(Error(ErrorKind::PushoverError { ref errors, .. }, _)) => {
+            assert_eq!(errors, &["memo is invalid"])
+        }
+        ref other => panic!("Expected PushoverError, got {:?}", other),
+    }
+    remove.assert();
+    add.assert();
+    restore.assert();
+}
+
+#[test]
+fn test_group_import_verifies_users() {
+    let valid = "importValidUser000000000000000";
+    let invalid = "importInvalidUser0000000000000";
+    let _valid = mock("POST", "/1/users/validate.json")
+        .match_query(Matcher::AllOf(vec![
+            Matcher::UrlEncoded("token".into(), "import_token".into()),
+            Matcher::UrlEncoded("user".into(), valid.into()),
+        ]))
+        .with_body("{\"status\":1, \"request\":\"request_number\", \"devices\":[\"phone\"]}")
+        .create();
+    let _invalid = mock("POST", "/1/users/validate.json")
+        .match_query(Matcher::AllOf(vec![
+            Matcher::UrlEncoded("token".into(), "import_token".into()),
+            Matcher::UrlEncoded("user".into(), invalid.into()),
+        ]))
+        .with_status(400)
+        .with_body(
+            "{\"status\":0, \"errors\":[\"user key is invalid\"], \"request\":\"request_number\"}",
+        )
+        .create();
+    let _add = mock(
+        "POST",
+        "/1/groups/importGroup0000000000000000000/add_user.json",
+    )
+    .match_query(Matcher::UrlEncoded("user".into(), valid.into()))
+    .with_body("{\"status\":1, \"request\":\"request_number\"}")
+    .expect(1)
+    .create();
+
+    let csv = format!(
+        "group,user,device,memo,disabled\nops,{},phone,Alice,false\nops,{},,,false\n",
+        valid, invalid
+    );
+    let report = GroupImport::new("import_token", "importGroup0000000000000000000")
+        .import_csv(&API::new().base_url(&mockito::server_url()), csv.as_bytes())
+        .unwrap();
+
+    assert_eq!(report.added.len(), 1);
+    assert_eq!(report.added[0].user, valid);
+    assert_eq!(report.rejected.len(), 1);
+    assert_eq!(report.rejected[0].row, 2);
+    assert_eq!(report.rejected[0].reason, "user key is invalid");
+    _add.assert();
+}
+
+#[test]
+fn test_bulk_verifier_classifies_keys() {
+    let verify = |user: &str, status: usize, body: &str| {
+        mock("POST", "/1/users/validate.json")
+            .match_query(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("token".into(), "bulk_token".into()),
+                Matcher::UrlEncoded("user".into(), user.into()),
+            ]))
+            .with_status(status)
+            .with_body(body)
+            .create()
+    };
+    let _user = verify(
+        "bulkUser",
+        200,
+        "{\"status\":1, \"group\":0, \"devices\":[\"phone\"], \"request\":\"request_number\"}",
+    );
+    let _group = verify(
+        "bulkGroup",
+        200,
+        "{\"status\":1, \"group\":1, \"devices\":[], \"request\":\"request_number\"}",
+    );
+    let _invalid = verify(
+        "bulkInvalid",
+        400,
+        "{\"status\":0, \"user\":\"invalid\", \"errors\":[\"user key is invalid\"], \"request\":\"request_number\"}",
+    );
+
+    let requests = ["bulkUser", "bulkGroup", "bulkInvalid"]
+        .iter()
+        .map(|key| Verification::new("bulk_token", *key));
+    let verifier = BulkVerifier::new(API::new().base_url(&mockito::server_url()))
+        .concurrency(2)
+        .rate_limiter(RateLimiter::new(100.0, 10));
+
+    let statuses = |report: VerificationReport| {
+        report
+            .keys
+            .into_iter()
+            .map(|key| key.status)
+            .collect::<Vec<_>>()
+    };
+    let expected = [
+        KeyStatus::ValidUser,
+        KeyStatus::ValidGroup,
+        KeyStatus::InvalidKey,
+    ];
+
+    assert_eq!(statuses(verifier.verify(requests.clone())), expected);
+    assert_eq!(
+        statuses(tokio_test::block_on(verifier.verify_async(requests))),
+        expected
+    );
+}
+
+#[test]
+fn test_bulk_license_stops_when_credits_run_out() {
+    let _credits = mock("GET", "/1/licenses.json")
+        .match_query(Matcher::UrlEncoded("token".into(), "license_token".into()))
+        .with_body("{\"status\":1, \"credits\":1, \"request\":\"request_number\"}")
+        .create();
+    let verify = |user: &str, licenses: &str| {
+        mock("POST", "/1/users/validate.json")
+            .match_query(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("token".into(), "license_token".into()),
+                Matcher::UrlEncoded("user".into(), user.into()),
+            ]))
+            .with_body(format!(
+                "{{\"status\":1, \"group\":0, \"devices\":[\"phone\"], \"licenses\":{}, \"request\":\"request_number\"}}",
+                licenses
+            ))
+            .create()
+    };
+    let _licensed = verify("licensedUser", "[\"Android\"]");
+    let _unlicensed = verify("unlicensedUser", "[\"iOS\"]");
+    let assign = mock("POST", "/1/licenses/assign.json")
+        .match_query(Matcher::AllOf(vec![
+            Matcher::UrlEncoded("token".into(), "license_token".into()),
+            Matcher::UrlEncoded("user".into(), "unlicensedUser".into()),
+            Matcher::UrlEncoded("os".into(), "Android".into()),
+        ]))
+        .with_body("{\"status\":1, \"credits\":0, \"request\":\"request_number\"}")
+        .expect(1)
+        .create();
+
+    let users = vec![
+        UserType::UserKey(String::from("licensedUser")),
+        UserType::UserKey(String::from("unlicensedUser")),
+        UserType::Email(String::from("late@example.com")),
+    ];
+    let report = BulkLicense::new("license_token")
+        .os(OperatingSystem::Android)
+        .skip_licensed(true)
+        .assign(&API::new().base_url(&mockito::server_url()), users)
+        .unwrap();
+
+    assign.assert();
+    assert_eq!(report.starting_credits, 1);
+    assert_eq!(report.credits, 0);
+    assert!(report.exhausted);
+    assert!(matches!(
+        report.results[0].outcome,
+        LicenseOutcome::AlreadyLicensed
+    ));
+    assert!(matches!(
+        report.results[1].outcome,
+        LicenseOutcome::Assigned { credits: 0 }
+    ));
+    assert!(matches!(
+        report.results[2].outcome,
+        LicenseOutcome::NotAttempted
+    ));
+}
+
+#[test]
+fn test_glance_updater_coalesces_updates() {
+    let glance = |percent: &str, hits: usize| {
+        mock("POST", "/1/glances.json")
+            .match_query(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("token".into(), "glance_token".into()),
+                Matcher::UrlEncoded("percent".into(), percent.into()),
+            ]))
+            .with_body("{\"status\":1, \"request\":\"request_number\"}")
+            .expect(hits)
+            .create()
+    };
+    let first = glance("10", 1);
+    let skipped = glance("20", 0);
+    let last = glance("30", 1);
+
+    let clock = ManualClock::new(UNIX_EPOCH);
+    let updater = GlanceUpdater::new(
+        API::new().base_url(&mockito::server_url()),
+        Glance::new("glance_token", "uQiRzpo4DXghDmr9QzzfQu27cmVRsG"),
+        Duration::from_secs(60),
+    )
+    .clock(clock.clone());
+
+    assert!(updater
+        .update(|glance| glance.set_percent(10))
+        .unwrap()
+        .is_some());
+    assert!(updater
+        .update(|glance| glance.set_percent(20))
+        .unwrap()
+        .is_none());
+    assert!(updater
+        .update(|glance| glance.set_percent(30))
+        .unwrap()
+        .is_none());
+    clock.advance(Duration::from_secs(60));
+    assert!(updater.tick().unwrap().is_some());
+    assert!(updater.tick().unwrap().is_none());
+
+    first.assert();
+    skipped.assert();
+    last.assert();
+}
+
+#[test]
+fn test_progress_sends_completion_message() {
+    let glances = mock("POST", "/1/glances.json")
+        .match_query(Matcher::UrlEncoded("token".into(), "progress_token".into()))
+        .with_body("{\"status\":1, \"request\":\"request_number\"}")
+        .expect(2)
+        .create();
+    let completed = mock("POST", "/1/messages.json")
+        .match_query(Matcher::AllOf(vec![
+            Matcher::UrlEncoded("token".into(), "progress_token".into()),
+            Matcher::UrlEncoded("priority".into(), "1".into()),
+            Matcher::UrlEncoded("title".into(), "Backfill".into()),
+            Matcher::UrlEncoded("message".into(), "Completed 3/3 in 0s".into()),
+        ]))
+        .with_body("{\"status\":1, \"request\":\"request_number\"}")
+        .expect(1)
+        .create();
+
+    let mut glance = Glance::new("progress_token", "uQiRzpo4DXghDmr9QzzfQu27cmVRsG");
+    glance.set_title("Backfill");
+    let progress = Progress::new(API::new().base_url(&mockito::server_url()), glance, 3)
+        .clock(ManualClock::new(UNIX_EPOCH));
+
+    let mut rows = progress.wrap(vec![1, 2, 3]);
+    assert_eq!(rows.by_ref().sum::<i32>(), 6);
+
+    assert!(rows.finished().unwrap().is_ok());
+    assert_eq!(rows.progress().percent(), 100);
+    glances.assert();
+    completed.assert();
+}
+
+#[test]
+fn test_open_client_session_registers_and_fetches() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("session.json");
+
+    let login = mock("POST", "/1/users/login.json")
+        .with_body("{\"status\":1, \"id\":\"sessionUser\", \"secret\":\"sessionSecret\", \"request\":\"request_number\"}")
+        .expect(1)
+        .create();
+    let _taken = mock("POST", "/1/devices.json")
+        .match_body(Matcher::Regex("name=sessionBot&".into()))
+        .with_status(400)
+        .with_body("{\"status\":0, \"errors\":{\"name\":[\"has already been taken\"]}, \"request\":\"request_number\"}")
+        .create();
+    let renamed = mock("POST", "/1/devices.json")
+        .match_body(Matcher::Regex("name=sessionBot-2&".into()))
+        .with_body("{\"status\":1, \"id\":\"sessionDevice\", \"request\":\"request_number\"}")
+        .expect(1)
+        .create();
+    let _download = mock("GET", "/1/messages.json")
+        .match_query(Matcher::AllOf(vec![
+            Matcher::UrlEncoded("secret".into(), "sessionSecret".into()),
+            Matcher::UrlEncoded("device_id".into(), "sessionDevice".into()),
+        ]))
+        .with_body(r#"{"status":1,"request":"request_number","messages":[
+            {"id":7,"umid":70,"message":"first","app":"app","aid":1,"icon":"icon","date":1600000000,"priority":0,"acked":0},
+            {"id":9,"umid":90,"message":"second","app":"app","aid":1,"icon":"icon","date":1600000000,"priority":0,"acked":0}]}"#)
+        .create();
+    let delete = mock(
+        "POST",
+        "/1/devices/sessionDevice/update_highest_message.json",
+    )
+    .match_query(Matcher::UrlEncoded("message".into(), "9".into()))
+    .with_body("{\"status\":1, \"request\":\"request_number\"}")
+    .expect(1)
+    .create();
+
+    let api = API::new().base_url(&mockito::server_url());
+    let credentials = Login::new("session@example.com", "password");
+    let session = OpenClientSession::open(api.clone(), &path, &credentials, "sessionBot").unwrap();
+
+    assert_eq!(session.credentials().device_name, "sessionBot-2");
+    assert_eq!(session.fetch_new().unwrap().len(), 2);
+
+    let reopened = OpenClientSession::open(api, &path, &credentials, "sessionBot").unwrap();
+    assert_eq!(reopened.credentials(), session.credentials());
+
+    login.assert();
+    renamed.assert();
+    delete.assert();
+}
+
+#[test]
+fn test_open_client_session_processes_at_least_once() {
+    let dir = tempfile::tempdir().unwrap();
+    let credentials = dir.path().join("session.json");
+    let seen = dir.path().join("seen.jsonl");
+    std::fs::write(
+        &credentials,
+        r#"{"user_id":"user","secret":"processSecret","device_id":"processDevice","device_name":"bot"}"#,
+    )
+    .unwrap();
+
+    let _download = mock("GET", "/1/messages.json")
+        .match_query(Matcher::AllOf(vec![
+            Matcher::UrlEncoded("secret".into(), "processSecret".into()),
+            Matcher::UrlEncoded("device_id".into(), "processDevice".into()),
+        ]))
+        .with_body(r#"{"status":1,"request":"request_number","messages":[
+            {"id":1,"umid":10,"message":"ok","app":"app","aid":1,"icon":"icon","date":1600000000,"priority":0,"acked":0},
+            {"id":2,"umid":20,"message":"fails","app":"app","aid":1,"icon":"icon","date":1600000000,"priority":0,"acked":0}]}"#)
+        .create();
+    let delete = mock(
+        "POST",
+        "/1/devices/processDevice/update_highest_message.json",
+    )
+    .match_query(Matcher::UrlEncoded("message".into(), "1".into()))
+    .with_body("{\"status\":1, \"request\":\"request_number\"}")
+    .expect(1)
+    .create();
+
+    let session =
+        OpenClientSession::load(API::new().base_url(&mockito::server_url()), &credentials).unwrap();
+    let mut store = SeenStore::open(&seen).unwrap();
+
+    let report = session
+        .process(&mut store, |message| match message.message.as_str() {
+            "fails" => Err("handler failed"),
+            _ => Ok(()),
+        })
+        .unwrap();
+
+    assert_eq!(report.handled, 1);
+    assert_eq!(report.deleted_through, Some(1));
+    assert_eq!(report.failed.unwrap().1, "handler failed");
+    assert!(store.is_empty());
+    delete.assert();
+}
+
+#[test]
+fn test_router_runs_actions() {
+    let dir = tempfile::tempdir().unwrap();
+    let credentials = dir.path().join("session.json");
+    let appended = dir.path().join("append.jsonl");
+    let piped = dir.path().join("command.json");
+    std::fs::write(
+        &credentials,
+        r#"{"user_id":"user","secret":"routerSecret","device_id":"routerDevice","device_name":"bot"}"#,
+    )
+    .unwrap();
+
+    let forwarded = mock("POST", "/1/messages.json")
+        .match_query(Matcher::AllOf(vec![
+            Matcher::UrlEncoded("token".into(), "router_token".into()),
+            Matcher::UrlEncoded("user".into(), "routerUser".into()),
+            Matcher::UrlEncoded("priority".into(), "1".into()),
+        ]))
+        .with_body("{\"status\":1, \"request\":\"request_number\"}")
+        .expect(1)
+        .create();
+    let acknowledged = mock("POST", "/1/receipts/routerReceipt/acknowledge.json")
+        .match_query(Matcher::UrlEncoded("secret".into(), "routerSecret".into()))
+        .with_body("{\"status\":1, \"request\":\"request_number\"}")
+        .expect(1)
+        .create();
+
+    let router = Router::from_json(&format!(
+        r#"{{"rules":[{{"name":"page","app":"Grafana","receipt":true,"actions":[
+            {{"type":"command","program":"sh","args":["-c","cat > '{}'"]}},
+            {{"type":"append","path":"{}"}},
+            {{"type":"forward","token":"router_token","user":"routerUser"}},
+            {{"type":"acknowledge"}}]}}]}}"#,
+        piped.display(),
+        appended.display()
+    ))
+    .unwrap();
+    let session =
+        OpenClientSession::load(API::new().base_url(&mockito::server_url()), &credentials).unwrap();
+    let message: Message = serde_json::from_str(
+        r#"{"id":1,"umid":10,"title":"db01 down","message":"db01 is down","app":"Grafana","aid":1,
+            "icon":"icon","date":1600000000,"priority":2,"acked":0,"receipt":"routerReceipt"}"#,
+    )
+    .unwrap();
+
+    let results = router.route(&session, &message);
+
+    assert_eq!(results.len(), 4);
+    assert!(results.iter().all(|result| result.result.is_ok()));
+    let line = std::fs::read_to_string(&appended).unwrap();
+    assert_eq!(
+        serde_json::from_str::<Message>(line.trim_end()).unwrap(),
+        message
+    );
+    assert_eq!(
+        serde_json::from_str::<Message>(&std::fs::read_to_string(&piped).unwrap()).unwrap(),
+        message
+    );
+    forwarded.assert();
+    acknowledged.assert();
+}